@@ -130,6 +130,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn deserialize_empty_dynamic_array() {
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct TestMessage {
+            text1: Dynamic<[u8; 255]>,
+            text2: Dynamic<[u8; 255]>,
+        }
+
+        let mut deserializer: Deserializer<TestMessage> = Deserializer::new();
+
+        // The length of the first array is in the second buffer
+        deserializer.deserialize(&mut []);
+        deserializer.deserialize(&mut [0, b'l', b'o', b'l']);
+
+        let parsed_message = deserializer.into_structure().unwrap();
+
+        assert_eq!(parsed_message,
+                   TestMessage{
+                       text1: Dynamic::<[u8; 255]>::new(),
+                       text2: Dynamic::<[u8; 255]>::with_data("lol".as_bytes()),
+                   }
+        );
+    }
+
     #[test]
     fn tail_array_optimization_struct() {
         #[derive(Debug, PartialEq, UavcanStruct, Clone)]
@@ -11,6 +11,8 @@ use {
     Struct,
 };
 
+use time::FrameTimestamp;
+
 use deserializer::{
     Deserializer,
 };
@@ -44,6 +46,7 @@ pub(crate) struct FrameAssembler<S: Struct> {
     crc_calculated: TransferCRC,
     toggle: bool,
     transfer_id: TransferID,    
+    timestamp: Option<FrameTimestamp>,
 }
 
 impl<S: Struct> FrameAssembler<S> {
@@ -57,6 +60,7 @@ impl<S: Struct> FrameAssembler<S> {
             crc_calculated: TransferCRC::from_signature(S::DATA_TYPE_SIGNATURE),
             toggle: false,
             transfer_id: TransferID::new(0x00),
+            timestamp: None,
         }
     }
     
@@ -83,6 +87,7 @@ impl<S: Struct> FrameAssembler<S> {
             self.toggle = false;
             self.transfer_id = frame.tail_byte().transfer_id();
             self.id = frame.id();
+            self.timestamp = frame.timestamp();
             self.started = true;
         }
 
@@ -112,7 +117,9 @@ impl<S: Struct> FrameAssembler<S> {
         if self.crc_calculated != self.crc_received.unwrap_or(self.crc_calculated) {
             Result::Err(BuildError::CRCError)
         } else if let Ok(body) = self.deserializer.into_structure() {
-            Ok(Frame::from_parts(self.id, body))
+            let mut frame = Frame::from_parts(self.id, body);
+            frame.timestamp = self.timestamp;
+            Ok(frame)
         } else {
             Err(BuildError::NotFinishedParsing)
         }
@@ -153,7 +160,7 @@ mod tests {
             const TYPE_ID: Option<u16> = Some(341);
         }
         
-        let can_frame = CanFrame{id: TransferFrameID::new(0), dlc: 8, data: [1, 0, 0, 0, 0b10011100, 5, 0, TailByte::new(true, true, false, TransferID::new(0)).into()]};
+        let can_frame = CanFrame{id: TransferFrameID::new(0), timestamp: None, dlc: 8, data: [1, 0, 0, 0, 0b10011100, 5, 0, TailByte::new(true, true, false, TransferID::new(0)).into()]};
         
         let mut message_builder = FrameAssembler::new();
         message_builder.add_transfer_frame(can_frame).unwrap();
@@ -195,31 +202,35 @@ mod tests {
             level: LogLevel{value: u3::new(0)},
             source: Dynamic::<[u8; 31]>::with_data("test source".as_bytes()),
             text: Dynamic::<[u8; 90]>::with_data("test text".as_bytes()),
-        }, 0, NodeID::new(32));
+        }, TransferPriority::HIGHEST, NodeID::new(32));
 
         let crc = 0x6383;
         let mut message_builder = FrameAssembler::new();
         
         message_builder.add_transfer_frame(CanFrame{
             id: TransferFrameID::new(4194080),
+            timestamp: None,
             dlc: 8,
             data: [crc.get_bits(0..8) as u8, crc.get_bits(8..16) as u8, 0u8.set_bits(5..8, 0).set_bits(0..5, 11).get_bits(0..8), b't', b'e', b's', b't', TailByte::new(true, false, false, TransferID::new(0)).into()],
         }).unwrap();
         
         message_builder.add_transfer_frame(CanFrame{
             id: TransferFrameID::new(4194080),
+            timestamp: None,
             dlc: 8,
             data: [b' ', b's', b'o', b'u', b'r', b'c', b'e', TailByte::new(false, false, false, TransferID::new(0)).into()],
         }).unwrap();
         
         message_builder.add_transfer_frame(CanFrame{
             id: TransferFrameID::new(4194080),
+            timestamp: None,
             dlc: 8,
             data: [b't', b'e', b's', b't', b' ', b't', b'e', TailByte::new(false, false, false, TransferID::new(0)).into()],
         }).unwrap();
         
         message_builder.add_transfer_frame(CanFrame{
             id: TransferFrameID::new(4194080),
+            timestamp: None,
             dlc: 3,
             data: [b'x', b't', TailByte::new(false, true, true, TransferID::new(0)).into(), 0, 0, 0, 0, 0],
         }).unwrap();
@@ -118,7 +118,7 @@ mod tests {
         impl Message for NodeStatus {
             const TYPE_ID: Option<u16> = Some(341);
         }   
-        let can_frame = CanFrame{id: TransferFrameID::from(TransferFrameID::new(87328)), dlc: 8, data: [1, 0, 0, 0, 0b10011100, 5, 0, TailByte::new(true, true, false, TransferID::new(0)).into()]};
+        let can_frame = CanFrame{id: TransferFrameID::from(TransferFrameID::new(87328)), timestamp: None, dlc: 8, data: [1, 0, 0, 0, 0b10011100, 5, 0, TailByte::new(true, true, false, TransferID::new(0)).into()]};
 
         let uavcan_frame = Frame::from_message(NodeStatus{
             uptime_sec: 1,
@@ -126,7 +126,7 @@ mod tests {
             mode: u3::new(3),
             sub_mode: u3::new(4),
             vendor_specific_status_code: 5,
-        }, 0, NodeID::new(32));
+        }, TransferPriority::HIGHEST, NodeID::new(32));
 
         let mut frame_generator = FrameDisassembler::from_uavcan_frame(uavcan_frame, TransferID::new(0));
 
@@ -159,7 +159,7 @@ mod tests {
             level: LogLevel{value: u3::new(0)},
             source: Dynamic::<[u8; 31]>::with_data("test source".as_bytes()),
             text: Dynamic::<[u8; 90]>::with_data("test text".as_bytes()),
-        }, 0, NodeID::new(32));
+        }, TransferPriority::HIGHEST, NodeID::new(32));
 
         let mut frame_generator = FrameDisassembler::from_uavcan_frame(uavcan_frame, TransferID::new(0));
 
@@ -170,6 +170,7 @@ mod tests {
             frame_generator.next_transfer_frame(),
             Some(CanFrame{
                 id: TransferFrameID::new(4194080),
+                timestamp: None,
                 dlc: 8,
                 data: [crc.get_bits(0..8) as u8, crc.get_bits(8..16) as u8, 0u8.set_bits(0..5, 11).set_bits(5..8, 0).get_bits(0..8), b't', b'e', b's', b't', TailByte::new(true, false, false, TransferID::new(0)).into()],
             })
@@ -179,6 +180,7 @@ mod tests {
             frame_generator.next_transfer_frame(),
             Some(CanFrame{
                 id: TransferFrameID::new(4194080),
+                timestamp: None,
                 dlc: 8,
                 data: [b' ', b's', b'o', b'u', b'r', b'c', b'e', TailByte::new(false, false, true, TransferID::new(0)).into()],
             })
@@ -188,6 +190,7 @@ mod tests {
             frame_generator.next_transfer_frame(),
             Some(CanFrame{
                 id: TransferFrameID::new(4194080),
+                timestamp: None,
                 dlc: 8,
                 data: [b't', b'e', b's', b't', b' ', b't', b'e', TailByte::new(false, false, false, TransferID::new(0)).into()],
             })
@@ -197,6 +200,7 @@ mod tests {
             frame_generator.next_transfer_frame(),
             Some(CanFrame{
                 id: TransferFrameID::new(4194080),
+                timestamp: None,
                 dlc: 3,
                 data: [b'x', b't', TailByte::new(false, true, true, TransferID::new(0)).into(), 0, 0, 0, 0, 0],
             })
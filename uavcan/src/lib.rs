@@ -25,6 +25,7 @@ extern crate bit_field;
 extern crate embedded_types;
 extern crate ux;
 extern crate half;
+extern crate critical_section;
 
 mod lib {
     pub mod core {
@@ -53,18 +54,31 @@ mod deserializer;
 mod frame_assembler;
 mod serializer;
 mod frame_disassembler;
+mod transfer_id_table;
 pub mod node;
 pub mod storage;
+pub mod redundancy;
+pub mod time;
+pub mod protocol;
 
 use bit_field::BitField;
 
 use transfer::TransferFrameID;
+use transfer::TransferPriority;
+use transfer::CanId;
+use types::{
+    u2,
+    u14,
+};
+use time::FrameTimestamp;
 
 
 pub use node::NodeConfig;
 pub use node::NodeID;
 pub use node::Node;
 pub use node::SimpleNode;
+pub use node::MultiInterfaceNode;
+pub use node::FullNode;
 
 
 /// These data type is only exposed so `Struct` can be derived.
@@ -196,11 +210,19 @@ pub trait Struct: Sized + Serializable {
 
 pub trait Message: Struct {
     const TYPE_ID: Option<u16>;
+
+    /// The priority used when broadcasting this message without specifying a priority.
+    const PRIORITY: TransferPriority = TransferPriority::MEDIUM;
 }
 
 pub trait Request: Struct {
     type RESPONSE: Response;
     const TYPE_ID: Option<u8>;
+
+    /// The priority used when sending this request without specifying a priority.
+    ///
+    /// The response will be sent with the same priority as the request.
+    const PRIORITY: TransferPriority = TransferPriority::MEDIUM;
 }
 
 pub trait Response: Struct {
@@ -212,40 +234,41 @@ pub trait Response: Struct {
 pub(crate) struct Frame<T: Struct> {
     id: TransferFrameID,
     body: T,
+    /// When the first frame of the transfer was received, if the interface timestamps received frames.
+    timestamp: Option<FrameTimestamp>,
 }
 
 impl<T: Struct> Frame<T> {
 
     
-    pub fn from_message(message: T, priority: u8, source_node: NodeID) -> Self where T: Message {
+    pub fn from_message(message: T, priority: TransferPriority, source_node: NodeID) -> Self where T: Message {
+        Frame::from_parts(
+            Frame::<T>::message_id(priority, source_node),
+            message,
+        )
+    }
+
+    /// Returns the `TransferFrameID` used when `source_node` broadcasts a message of type `T`.
+    pub fn message_id(priority: TransferPriority, source_node: NodeID) -> TransferFrameID where T: Message {
         if let Some(type_id) = T::TYPE_ID {
-            let mut id = 0;
-            id.set_bits(0..7, u32::from(source_node));
-            id.set_bit(7, false);
-            id.set_bits(8..24, u32::from(type_id));
-            id.set_bits(24..29, u32::from(priority));
-            
-            Frame::from_parts(
-                TransferFrameID::new(id),
-                message,
-            )
+            TransferFrameID::from(CanId::Message {
+                priority,
+                type_id,
+                source: source_node,
+            })
         } else {
             unimplemented!("Resolvation of type id is not supported yet")
         }
     }
 
-    /*
-    pub fn from_anonymous_message(message: T, priority: u8, discriminator: u16) -> Self where T: Message {
+    pub fn from_anonymous_message(message: T, priority: TransferPriority, discriminator: u14) -> Self where T: Message {
         if let Some(type_id) = T::TYPE_ID {
-            let mut id = 0;
-            id.set_bits(0..7, 0);
-            id.set_bit(7, false);
-            id.set_bits(8..10, u32::from(type_id));
-            id.set_bits(10..24, u32::from(discriminator));
-            id.set_bits(24..29, u32::from(priority));
-            
             Frame::from_parts(
-                TransferFrameID::new(id),
+                TransferFrameID::from(CanId::AnonymousMessage {
+                    priority,
+                    type_id: u2::new(type_id.get_bits(0..2) as u8),
+                    discriminator,
+                }),
                 message,
             )
         } else {
@@ -254,18 +277,15 @@ impl<T: Struct> Frame<T> {
 
     }
 
-    pub fn from_request(request: T, priority: u8, source_node: NodeID, destination_node: NodeID) -> Self where T: Request{
+    pub fn from_request(request: T, priority: TransferPriority, source_node: NodeID, destination_node: NodeID) -> Self where T: Request {
         if let Some(type_id) = T::TYPE_ID {
-            let mut id = 0;
-            id.set_bits(0..7, u32::from(source_node));
-            id.set_bit(7, false);
-            id.set_bits(8..15, u32::from(destination_node));
-            id.set_bit(15, true);
-            id.set_bits(16..24, u32::from(type_id));
-            id.set_bits(24..29, u32::from(priority));
-            
             Frame::from_parts(
-                TransferFrameID::new(id),
+                TransferFrameID::from(CanId::ServiceRequest {
+                    priority,
+                    type_id,
+                    source: source_node,
+                    destination: destination_node,
+                }),
                 request,
             )
         } else {
@@ -274,18 +294,15 @@ impl<T: Struct> Frame<T> {
 
     }
 
-    pub fn from_response(response: T, priority: u8, source_node: NodeID, destination_node: NodeID) -> Self where T: Response {
+    pub fn from_response(response: T, priority: TransferPriority, source_node: NodeID, destination_node: NodeID) -> Self where T: Response {
         if let Some(type_id) = T::TYPE_ID {
-            let mut id = 0;
-            id.set_bits(0..7, u32::from(source_node));
-            id.set_bit(7, false);
-            id.set_bits(8..15, u32::from(destination_node));
-            id.set_bit(15, true);
-            id.set_bits(16..24, u32::from(type_id));
-            id.set_bits(24..29, u32::from(priority));
-
             Frame::from_parts(
-                TransferFrameID::new(id),
+                TransferFrameID::from(CanId::ServiceResponse {
+                    priority,
+                    type_id,
+                    source: source_node,
+                    destination: destination_node,
+                }),
                 response,
             )
         } else {
@@ -293,10 +310,9 @@ impl<T: Struct> Frame<T> {
         }
 
     }
-    */
     
     fn from_parts(id: TransferFrameID, body: T) -> Self {
-        Frame{id: id, body: body}
+        Frame{id: id, body: body, timestamp: None}
     }
     
     fn into_parts(self) -> (TransferFrameID, T) {
@@ -316,9 +332,10 @@ mod tests {
 
     // Implementing some types common for several tests
     
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, PartialEq, Clone)]
     pub struct CanFrame {
         pub id: TransferFrameID,
+        pub timestamp: Option<FrameTimestamp>,
         pub dlc: usize,
         pub data: [u8; 8],
     }
@@ -327,7 +344,7 @@ mod tests {
         const MAX_DATA_LENGTH: usize = 8;
         
         fn new(id: TransferFrameID) -> CanFrame {
-            CanFrame{id: id, dlc: 0, data: [0; 8]}
+            CanFrame{id: id, timestamp: None, dlc: 0, data: [0; 8]}
         }
         
         fn set_data_length(&mut self, length: usize) {
@@ -346,8 +363,118 @@ mod tests {
         fn id(&self) -> TransferFrameID {
             self.id 
         }
+
+        fn timestamp(&self) -> Option<FrameTimestamp> {
+            self.timestamp
+        }
     }
 
-    
+    /// An interface where transmitted frames end up in `tx` and frames in `rx` are received
+    ///
+    /// `tx_timestamp` is reported as the transmission timestamp of all frames,
+    /// and received frames without a timestamp are given `rx_timestamp`.
+    /// When `tx` holds `tx_capacity` frames, transmitting fails with `IOError::BufferExhausted`.
+    #[cfg(feature="std")]
+    #[derive(Debug, Default)]
+    pub struct TestInterface {
+        pub tx: ::std::cell::RefCell<::std::collections::VecDeque<CanFrame>>,
+        pub rx: ::std::cell::RefCell<::std::collections::VecDeque<CanFrame>>,
+        pub tx_timestamp: ::std::cell::Cell<Option<FrameTimestamp>>,
+        pub rx_timestamp: ::std::cell::Cell<Option<FrameTimestamp>>,
+        pub tx_capacity: ::std::cell::Cell<Option<usize>>,
+    }
+
+    #[cfg(feature="std")]
+    impl TestInterface {
+        /// Moves all frames transmitted on `self` into the receive buffer of `other`
+        pub fn transfer_to(&self, other: &TestInterface) {
+            other.rx.borrow_mut().extend(self.tx.borrow_mut().drain(..));
+        }
+    }
+
+    #[cfg(feature="std")]
+    impl transfer::TransferInterface for TestInterface {
+        type Frame = CanFrame;
+
+        fn transmit(&self, frame: &CanFrame) -> Result<(), transfer::IOError> {
+            if self.tx_capacity.get().map_or(false, |capacity| self.tx.borrow().len() >= capacity) {
+                return Err(transfer::IOError::BufferExhausted);
+            }
+            self.tx.borrow_mut().push_back(frame.clone());
+            Ok(())
+        }
+
+        fn receive(&self) -> Option<CanFrame> {
+            let mut frame = self.rx.borrow_mut().pop_front()?;
+            frame.timestamp = frame.timestamp.or(self.rx_timestamp.get());
+            Some(frame)
+        }
+
+        fn transmission_timestamp(&self, _id: TransferFrameID) -> Option<FrameTimestamp> {
+            self.tx_timestamp.get()
+        }
+    }
+
+    /// The node used by most tests
+    #[cfg(feature="std")]
+    pub type TestNode<'a> = SimpleNode<TestInterface, &'a TestInterface, storage::HeapStorage<CanFrame>>;
+
+    /// Creates a `TestNode` on `interface` with the node ID `id`, or an anonymous one for `None`
+    #[cfg(feature="std")]
+    pub fn test_node(interface: &TestInterface, id: Option<u8>) -> TestNode {
+        SimpleNode::new(interface, NodeConfig{id: id.map(NodeID::new), ..NodeConfig::default()})
+    }
+
+    /// Transmits the queued transfers of all nodes, delivers every frame to all the other interfaces
+    /// and lets the nodes receive them
+    #[cfg(feature="std")]
+    pub fn exchange(nodes: &[(&TestNode, &TestInterface)]) {
+        for &(node, _) in nodes {
+            node.flush_transmissions();
+        }
+        for (i, &(_, sender)) in nodes.iter().enumerate() {
+            let frames: Vec<CanFrame> = sender.tx.borrow_mut().drain(..).collect();
+            for (_, &(_, receiver)) in nodes.iter().enumerate().filter(|&(j, _)| j != i) {
+                receiver.rx.borrow_mut().extend(frames.iter().cloned());
+            }
+        }
+        for &(node, _) in nodes {
+            node.flush_receptions();
+        }
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[DataTypeSignature = "0x5e3c8a0b9d2f4e17"]
+    pub struct EchoRequest {
+        pub value: u32,
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    #[DataTypeSignature = "0x5e3c8a0b9d2f4e17"]
+    pub struct EchoResponse {
+        pub value: u32,
+    }
+
+    impl Request for EchoRequest {
+        type RESPONSE = EchoResponse;
+        const TYPE_ID: Option<u8> = Some(200);
+    }
+
+    impl Response for EchoResponse {
+        type REQUEST = EchoRequest;
+        const TYPE_ID: Option<u8> = Some(200);
+    }
+
+    #[test]
+    fn request_frame_id() {
+        let frame = Frame::from_request(EchoRequest{value: 0}, TransferPriority::new(3), NodeID::new(10), NodeID::new(42));
+        assert_eq!(frame.id, TransferFrameID::new((3 << 24) | (200 << 16) | (1 << 15) | (42 << 8) | (1 << 7) | 10));
+    }
+
+    #[test]
+    fn response_frame_id() {
+        let frame = Frame::from_response(EchoResponse{value: 0}, TransferPriority::new(3), NodeID::new(42), NodeID::new(10));
+        assert_eq!(frame.id, TransferFrameID::new((3 << 24) | (200 << 16) | (10 << 8) | (1 << 7) | 42));
+    }
     
 }
@@ -1,17 +1,33 @@
 //! Everything related to Uavcan Nodes
 
 use lib::core::marker::PhantomData;
+use lib::core::cell::{
+    Cell,
+    RefCell,
+    RefMut,
+};
+
+use lib::core::convert::TryFrom;
+use lib::core::fmt;
+use lib::core::iter;
+
+use critical_section::Mutex;
 
 use {
     Frame,
     Struct,
     Message,
+    Request,
+    Response,
 };
 
 use storage::{
     Storage,
     SubscriberStorageHandle,
     InterfaceStorageHandle,
+    StorageError,
+    DropPolicy,
+    Inserted,
 };
 
 use transfer::{
@@ -19,7 +35,38 @@ use transfer::{
     TransferFrame,
     TransferFrameID,
     TransferID,
-    TransferFrameIDFilter,
+    CanId,
+    TransferPriority,
+    FullTransferID,
+    TailByte,
+};
+
+use time::{
+    Duration,
+    Timestamp,
+    FrameTimestamp,
+};
+
+use transfer_id_table::TransferIDTable;
+
+use redundancy::{
+    RedundancyFilter,
+    REDUNDANCY_FILTER_CAPACITY,
+};
+
+use protocol::{
+    NodeStatus,
+    NodeStatusPublisher,
+    GetNodeInfoRequest,
+    NodeInfoServer,
+    GetDataTypeInfoRequest,
+    GetDataTypeInfoResponse,
+    DataTypeKind,
+    RestartNodeRequest,
+    RestartNodeResponse,
+    GetTransportStatsRequest,
+    GetTransportStatsResponse,
+    CanIfaceStats,
 };
 
 use frame_disassembler::FrameDisassembler;
@@ -28,7 +75,15 @@ use frame_assembler::AssemblerResult;
 use frame_assembler::AssemblerError;
 use frame_assembler::BuildError;
 
-use embedded_types::io::Error as IOError;
+use crc::TransferCRC;
+
+use types::{
+    u14,
+    u48,
+    Dynamic,
+};
+
+use bit_field::BitField;
 
 /// The 7 bit `NodeID` used in Uavcan
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -60,11 +115,73 @@ impl NodeID {
 /// Allows implementation of application level features genericaly for all types of Uavcan Nodes.
 pub trait Node<I: TransferInterface, S: Storage<I::Frame>> {
 
-    /// Broadcast a `Message` on the Uavcan network. 
-    fn broadcast<T: Struct + Message>(&self, message: T) -> Result<(), IOError>;
+    /// Returns the `NodeID` of this node, or `None` if the node is anonymous.
+    fn id(&self) -> Option<NodeID>;
+
+    /// Changes the `NodeID` of this node.
+    ///
+    /// This is typically done when a dynamic node ID have been allocated for an anonymous node.
+    /// Subscriptions and service servers created before the change are not updated.
+    fn set_id(&self, id: NodeID);
+
+    /// Broadcast a `Message` on the Uavcan network with the default priority of the message type.
+    ///
+    /// Anonymous nodes can only broadcast messages fitting in a single frame,
+    /// broadcasting larger messages will fail with `Err(TransmitError::AnonymousMultiFrameTransfer)`.
+    fn broadcast<T: Struct + Message>(&self, message: T) -> Result<(), TransmitError> {
+        self.broadcast_with_priority(message, T::PRIORITY)
+    }
+
+    /// Broadcast a `Message` on the Uavcan network with a specific priority.
+    fn broadcast_with_priority<T: Struct + Message>(&self, message: T, priority: TransferPriority) -> Result<(), TransmitError>;
 
     /// Subscribe to broadcasts of a specific `Message`.
-    fn subscribe<T: Struct + Message>(&self) -> Subscriber<T, I::Frame, S::SubscriberStorageHandle>;
+    ///
+    /// Only messages from nodes with a `NodeID` are received, anonymous messages require `subscribe_anonymous`.
+    ///
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage can't hold another subscription.
+    fn subscribe<T: Struct + Message>(&self) -> Result<Subscriber<T, I::Frame, S::SubscriberStorageHandle>, StorageError>;
+
+    /// Subscribe to anonymous broadcasts of a specific `Message`.
+    ///
+    /// Anonymous transfers only carry the two least significant bits of the data type ID.
+    /// All anonymous messages with a matching data type ID will be received by the subscriber.
+    ///
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage can't hold another subscription.
+    fn subscribe_anonymous<T: Struct + Message>(&self) -> Result<Subscriber<T, I::Frame, S::SubscriberStorageHandle>, StorageError>;
+
+    /// Send a service `Request` to the node with id `destination` with the default priority of the request type.
+    ///
+    /// The returned `ResponseHandle` is used to receive the matching response.
+    /// If no response is received before `deadline` the handle will report a timeout.
+    ///
+    /// Anonymous nodes are not allowed to send requests and will get `Err(TransmitError::AnonymousServiceTransfer)`.
+    /// If the storage can't hold the subscription for the response, `Err(TransmitError::Storage(StorageError::OutOfSpace))` is returned.
+    fn request<T: Struct + Request>(&self, destination: NodeID, request: T, deadline: Timestamp) -> Result<ResponseHandle<T, I::Frame, S::SubscriberStorageHandle>, TransmitError> {
+        self.request_with_priority(destination, request, T::PRIORITY, deadline)
+    }
+
+    /// Send a service `Request` to the node with id `destination` with a specific priority.
+    ///
+    /// Behaves like `request` in all other aspects.
+    fn request_with_priority<T: Struct + Request>(&self, destination: NodeID, request: T, priority: TransferPriority, deadline: Timestamp) -> Result<ResponseHandle<T, I::Frame, S::SubscriberStorageHandle>, TransmitError>;
+
+    /// Serve incoming `Request`s of a specific type addressed to this node.
+    ///
+    /// Anonymous nodes can't be addressed, and a `ServiceServer` created by an anonymous node will never receive any requests.
+    ///
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage can't hold another subscription.
+    fn serve<T: Struct + Request>(&self) -> Result<ServiceServer<T, I::Frame, S::SubscriberStorageHandle>, StorageError>;
+
+    /// Send a `Response` back to the node that sent the request `token` belongs to.
+    ///
+    /// The response is sent with the same `TransferID` and priority as the request.
+    fn respond<T: Struct + Response>(&self, token: ResponseToken<T::REQUEST>, response: T) -> Result<(), TransmitError>;
+
+    /// Returns when the last `Message` of type `T` broadcast with the default priority was transmitted on the bus.
+    ///
+    /// Returns `None` if the node is anonymous or if the interface doesn't support timestamping of transmissions.
+    fn transmission_timestamp<T: Struct + Message>(&self) -> Option<FrameTimestamp>;
 }
 
     
@@ -78,6 +195,7 @@ pub trait Node<I: TransferInterface, S: Storage<I::Frame>> {
 ///
 /// let mut node_config = NodeConfig::default();
 /// node_config.id = Some(NodeID::new(127));
+/// node_config.name = "com.example.sensor";
 ///
 /// ```
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -87,73 +205,524 @@ pub struct NodeConfig {
     ///
     /// Nodes with `id = None` is, in Uavcan terms, an anonymous Node.
     pub id: Option<NodeID>,
+
+    /// Human readable name of the node in reversed internet domain notation, e.g. "com.example.sensor".
+    ///
+    /// Reported to other nodes through `GetNodeInfo`, only the first 80 characters are used.
+    pub name: &'static str,
+
+    pub software_version: Version,
+
+    /// Version control system commit the software was built from, e.g. the short git hash.
+    pub vcs_commit: Option<u32>,
+
+    /// CRC-64-WE of the firmware image.
+    pub image_crc: Option<u64>,
+
+    pub hardware_version: Version,
+
+    /// Unique ID of the node hardware, all zeros if not available.
+    pub unique_id: [u8; 16],
+
+    /// Certificate proving the authenticity of the hardware, only the first 255 bytes are used.
+    pub certificate_of_authenticity: &'static [u8],
+
+    /// Which frame to drop when a received frame is inserted into a full subscriber queue.
+    ///
+    /// Transfers that don't fit in the interface queues are not transmitted at all.
+    pub drop_policy: DropPolicy,
 }
 
 impl Default for NodeConfig {
     fn default() -> Self {
         NodeConfig{
             id: None,
+            name: "",
+            software_version: Version::default(),
+            vcs_commit: None,
+            image_crc: None,
+            hardware_version: Version::default(),
+            unique_id: [0; 16],
+            certificate_of_authenticity: &[],
+            drop_policy: DropPolicy::default(),
         }
     }
 }
 
+/// A major and minor version number
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+}
+
+/// Traffic counters of a node
+///
+/// All counters start at zero when the node is created.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TransportStats {
+    /// Transfers queued for transmission.
+    pub transfers_tx: u64,
+
+    /// End frames received, completing a transfer.
+    pub transfers_rx: u64,
+
+    /// Received frames that can't be part of any valid transfer.
+    ///
+    /// A `FullNode` also counts the `ReceiveError`s of its standard service servers here.
+    /// Errors reported by subscribers and servers created by the application are not counted.
+    pub transfer_errors: u64,
+
+    /// Frames handed over to the interfaces.
+    pub frames_tx: u64,
+
+    /// Frames received from the interfaces.
+    pub frames_rx: u64,
+
+    /// Received frames dropped, or causing another frame to be dropped, because a subscriber queue was full.
+    pub rx_frames_dropped: u64,
+
+    /// Frames to transmit dropped because an interface queue was full, counted for every interface dropping them.
+    pub tx_frames_dropped: u64,
+}
+
+
+/// Multi frame transfers not completed within this time are discarded.
+pub const TRANSFER_TIMEOUT_MS: u16 = 2000;
+
+/// The number of multi frame transfers a `Subscriber` or `ServiceServer` can reassemble concurrently.
+///
+/// Frames of additional transfers are discarded, and the transfer reported with `ReceiveErrorCode::MissingStartFrame`.
+/// The number of such transfers is returned by `Subscriber::dropped_transfers` and `ServiceServer::dropped_transfers`.
+pub const MAX_PARTIAL_TRANSFERS: usize = 4;
 
 /// A subscription handle used to receive a specific `Message`
 #[derive(Debug)]
 pub struct Subscriber<T: Struct + Message, F: TransferFrame, H: SubscriberStorageHandle<F>> {
     storage_handle: H,
+    partial_transfers: PartialTransfers<T>,
+    /// Subscribed to anonymous messages rather than messages from nodes with a `NodeID`.
+    anonymous: bool,
     phantom: PhantomData<(T, F)>,
 }
 
 impl <T: Struct + Message, F: TransferFrame, H: SubscriberStorageHandle<F>> Subscriber<T, F, H> {
-    fn new(storage_handle: H) -> Self {
+    fn new(storage_handle: H, anonymous: bool) -> Self {
         Subscriber{
             storage_handle,
+            partial_transfers: PartialTransfers::new(),
+            anonymous,
             phantom: PhantomData,
         }
     }
 
-    /// Receives a message that is subscribed on.
-    ///
-    /// Messages are returned in a manner that respects the `TransferFrameID` priority.
-    /// For equal priority, FIFO logic is used.
-    pub fn receive(&self) -> Option<Result<T, ReceiveError>> {
-        if let Some(full_id) = self.storage_handle.find_id(|x| x.is_end_frame()) {
-            let mut assembler = FrameAssembler::new();
-            loop {
-                match assembler.add_transfer_frame(self.storage_handle.remove(&full_id.frame_id).unwrap()) {
-                    Err(AssemblerError::ToggleError) => {
-                        self.storage_handle.retain(|x| x.full_id() != full_id);
-                        return Some(Err(ReceiveError {
-                            transfer_frame_id: full_id.frame_id,
-                            transfer_id: full_id.transfer_id,
-                            error_code: ReceiveErrorCode::ToggleError,
-                        }));
-                    },
-                    Err(_) => panic!("Unexpected error from FrameAssembler"),
-                    Ok(AssemblerResult::Finished) => {
-                        match assembler.build() {
-                            Ok(frame) => return Some(Ok(frame.into_parts().1)),
-                            Err(BuildError::CRCError) => {
-                                self.storage_handle.retain(|x| x.full_id() != full_id);
-                                return Some(Err(ReceiveError {
-                                    transfer_frame_id: full_id.frame_id,
-                                    transfer_id: full_id.transfer_id,
-                                    error_code: ReceiveErrorCode::CRCError,
-                                }));
-                            },
-                            Err(_) => panic!("Unexpected error from FrameAssembler"),
-                        }
-                    },
-                    Ok(AssemblerResult::Ok) => (),
-                }
+    /// Discards the oldest partially received transfer if it have not been completed within `TRANSFER_TIMEOUT_MS`.
+    ///
+    /// Every partial transfer is timed from the timestamp of its own start frame,
+    /// or from the first call after it was started if the interface doesn't timestamp received frames.
+    /// Later frames of a discarded transfer are dropped by `receive`, the end frame reported with `ReceiveErrorCode::MissingStartFrame`.
+    ///
+    /// Returns a `ReceiveErrorCode::Timeout` error for the discarded transfer.
+    /// Should be called periodically, repeatedly until it returns `None`, after `receive` have returned `None`.
+    pub fn remove_timed_out(&self, now: Timestamp) -> Option<ReceiveError> {
+        self.partial_transfers.remove_timed_out(now)
+    }
+
+    /// Returns the number of transfers discarded because `MAX_PARTIAL_TRANSFERS` transfers were already being reassembled.
+    pub fn dropped_transfers(&self) -> u64 {
+        self.partial_transfers.dropped.get()
+    }
+
+    /// Receives a message that is subscribed on, together with information about the transfer it was received in.
+    ///
+    /// Frames are taken out of storage highest `TransferFrameID` priority first, frames with equal priority in the order they were received,
+    /// and fed to the assembler of their transfer.
+    /// Messages are returned as soon as their end frame is received.
+    ///
+    /// Subscribers created with `Node::subscribe` discard anonymous messages,
+    /// as the discriminator of an anonymous message can make its `TransferFrameID` match any data type ID.
+    pub fn receive(&self) -> Option<Result<ReceivedTransfer<T>, ReceiveError>> {
+        let anonymous = self.anonymous;
+        let (full_id, result) = self.partial_transfers.receive(&self.storage_handle, |x| anonymous || CanId::try_from(x.id()).map_or(false, |id| id.source().is_some()))?;
+        Some(result.map(|frame| {
+            let id = CanId::try_from(frame.id).expect("Message IDs are always valid");
+            ReceivedTransfer {
+                source: match id {
+                    CanId::AnonymousMessage{discriminator, ..} => TransferSource::Anonymous(discriminator),
+                    _ => TransferSource::Node(id.source().expect("Only anonymous messages lack a source")),
+                },
+                priority: id.priority(),
+                type_id: T::TYPE_ID.expect("Subscribers are only created for types with a data type ID"),
+                transfer_id: full_id.transfer_id,
+                timestamp: frame.timestamp,
+                body: frame.body,
             }
+        }))
+    }
+    
+    
+}
+
+/// A received `Message` together with information about the transfer it was received in
+#[derive(Debug, PartialEq)]
+pub struct ReceivedTransfer<T: Struct + Message> {
+    pub body: T,
+
+    /// The node that sent the message.
+    pub source: TransferSource,
+
+    pub priority: TransferPriority,
+
+    /// The data type ID of the message, always `T::TYPE_ID`.
+    ///
+    /// Anonymous transfers only carry the two least significant bits of the data type ID, the rest is implied by the subscription.
+    pub type_id: u16,
+
+    pub transfer_id: TransferID,
+
+    /// When the first frame of the transfer was received, if the interface timestamps received frames.
+    pub timestamp: Option<FrameTimestamp>,
+}
+
+/// The sender of a received `Message`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TransferSource {
+    /// The message was sent by the node with this `NodeID`.
+    Node(NodeID),
+
+    /// The message was sent by an anonymous node, identified only by the discriminator of the transfer.
+    Anonymous(u14),
+}
+
+impl TransferSource {
+    /// Returns the `NodeID` of the sender, or `None` if the sender is anonymous.
+    pub fn node_id(&self) -> Option<NodeID> {
+        match *self {
+            TransferSource::Node(id) => Some(id),
+            TransferSource::Anonymous(_) => None,
+        }
+    }
+}
+
+/// A handle used to receive the response of a previously sent `Request`
+#[derive(Debug)]
+pub struct ResponseHandle<T: Struct + Request, F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    storage_handle: H,
+    partial_transfers: PartialTransfers<T::RESPONSE>,
+    frame_id: TransferFrameID,
+    transfer_id: TransferID,
+    deadline: Timestamp,
+    completed: Cell<bool>,
+    phantom: PhantomData<(T, F)>,
+}
+
+impl <T: Struct + Request, F: TransferFrame, H: SubscriberStorageHandle<F>> ResponseHandle<T, F, H> {
+    fn new(storage_handle: H, frame_id: TransferFrameID, transfer_id: TransferID, deadline: Timestamp) -> Self {
+        ResponseHandle{
+            storage_handle,
+            partial_transfers: PartialTransfers::new(),
+            frame_id,
+            transfer_id,
+            deadline,
+            completed: Cell::new(false),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the `TransferID` used for the request this handle belongs to.
+    pub fn transfer_id(&self) -> TransferID {
+        self.transfer_id
+    }
+
+    /// Receives the response matching the request.
+    ///
+    /// Returns `None` while waiting for the response and `Some(Err(_))` with `ReceiveErrorCode::Timeout`
+    /// if no response have been received and `now` is past the deadline.
+    /// Once a response has been returned, all later calls return `None`.
+    /// Responses to other requests (with a different `TransferID`) are discarded.
+    pub fn receive(&self, now: Timestamp) -> Option<Result<T::RESPONSE, ReceiveError>> {
+        if self.completed.get() {
+            return None;
+        }
+
+        let transfer_id = self.transfer_id;
+        if let Some((_, result)) = self.partial_transfers.receive(&self.storage_handle, |x| x.tail_byte().transfer_id() == transfer_id) {
+            self.completed.set(true);
+            Some(result.map(|frame| frame.into_parts().1))
+        } else if now >= self.deadline {
+            Some(Err(ReceiveError {
+                transfer_frame_id: self.frame_id,
+                transfer_id: self.transfer_id,
+                error_code: ReceiveErrorCode::Timeout,
+            }))
         } else {
             None
         }
     }
+}
+
+/// A handle used to receive `Request`s addressed to this node
+#[derive(Debug)]
+pub struct ServiceServer<T: Struct + Request, F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    storage_handle: H,
+    partial_transfers: PartialTransfers<T>,
+    phantom: PhantomData<(T, F)>,
+}
+
+impl <T: Struct + Request, F: TransferFrame, H: SubscriberStorageHandle<F>> ServiceServer<T, F, H> {
+    fn new(storage_handle: H) -> Self {
+        ServiceServer{
+            storage_handle,
+            partial_transfers: PartialTransfers::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Discards the oldest partially received request if it have not been completed within `TRANSFER_TIMEOUT_MS`.
+    ///
+    /// See `Subscriber::remove_timed_out`.
+    pub fn remove_timed_out(&self, now: Timestamp) -> Option<ReceiveError> {
+        self.partial_transfers.remove_timed_out(now)
+    }
+
+    /// See `Subscriber::dropped_transfers`.
+    pub fn dropped_transfers(&self) -> u64 {
+        self.partial_transfers.dropped.get()
+    }
+
+    /// Receives a request addressed to this node.
+    ///
+    /// The `ResponseToken` of the returned `IncomingRequest` must be passed to `Node::respond` to answer the request.
+    pub fn receive(&self) -> Option<Result<IncomingRequest<T>, ReceiveError>> {
+        let (full_id, result) = self.partial_transfers.receive(&self.storage_handle, |_| true)?;
+        let frame = match result {
+            Ok(frame) => frame,
+            Err(error) => return Some(Err(error)),
+        };
+
+        let (id, body) = frame.into_parts();
+        match CanId::try_from(id) {
+            Ok(CanId::ServiceRequest{source, priority, ..}) => Some(Ok(IncomingRequest {
+                body,
+                token: ResponseToken {
+                    source,
+                    transfer_id: full_id.transfer_id,
+                    priority,
+                    phantom: PhantomData,
+                },
+            })),
+            // Requests sent from node 0 can't be responded to
+            _ => Some(Err(ReceiveError {
+                transfer_frame_id: id,
+                transfer_id: full_id.transfer_id,
+                error_code: ReceiveErrorCode::InvalidID,
+            })),
+        }
+    }
+}
+
+/// A received `Request` together with what's needed to respond to it
+#[derive(Debug, PartialEq, Eq)]
+pub struct IncomingRequest<T: Struct + Request> {
+    pub body: T,
+    pub token: ResponseToken<T>,
+}
+
+/// Identifies the request a response belongs to.
+///
+/// The token is consumed when responding, making sure every request is responded to at most once.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ResponseToken<T: Struct + Request> {
+    source: NodeID,
+    transfer_id: TransferID,
+    priority: TransferPriority,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Struct + Request> ResponseToken<T> {
+    /// Returns the `NodeID` of the node that sent the request.
+    pub fn source(&self) -> NodeID {
+        self.source
+    }
+    
+    /// Returns the `TransferID` of the request.
+    pub fn transfer_id(&self) -> TransferID {
+        self.transfer_id
+    }
+
+    /// Returns the `TransferPriority` of the request.
+    pub fn priority(&self) -> TransferPriority {
+        self.priority
+    }
+}
+
+/// Returns a copy of the single frame anonymous transfer `frame` with the discriminator derived from the payload.
+///
+/// The discriminator is the CRC of the payload, making it unlikely that two anonymous nodes
+/// sending different data will use the same `TransferFrameID`.
+fn with_anonymous_discriminator<F: TransferFrame>(frame: F) -> F {
+    let payload_length = frame.data().len() - 1;
     
+    let mut crc = TransferCRC::from(0xffff);
+    crc.add(&frame.data()[0..payload_length]);
     
+    let id = match CanId::try_from(frame.id()) {
+        Ok(CanId::AnonymousMessage{priority, type_id, ..}) => CanId::AnonymousMessage {
+            priority,
+            type_id,
+            discriminator: u14::new((u16::from(crc) >> 1).get_bits(0..14)),
+        },
+        _ => unreachable!("Only anonymous messages have a discriminator"),
+    };
+
+    let mut new_frame = F::new(TransferFrameID::from(id));
+    new_frame.set_data_length(frame.data().len());
+    new_frame.data_as_mut().copy_from_slice(frame.data());
+    new_frame
+}
+
+/// Multi frame transfers being reassembled, keyed by `FullTransferID`
+///
+/// Each frame is fed to the `FrameAssembler` of its transfer as soon as it's taken out of storage,
+/// so only the partially decoded structure is kept for a transfer, not its raw frames.
+struct PartialTransfers<T: Struct> {
+    transfers: RefCell<[Option<PartialTransfer<T>>; MAX_PARTIAL_TRANSFERS]>,
+    /// Transfers discarded for lack of a free slot.
+    dropped: Cell<u64>,
+}
+
+struct PartialTransfer<T: Struct> {
+    full_id: FullTransferID,
+    assembler: FrameAssembler<T>,
+    started: Option<Timestamp>,
+}
+
+impl<T: Struct> PartialTransfers<T> {
+    fn new() -> Self {
+        PartialTransfers {
+            transfers: RefCell::new(Default::default()),
+            dropped: Cell::new(0),
+        }
+    }
+
+    /// Feeds frames from storage to their assemblers until a transfer is completed, or storage is empty.
+    ///
+    /// The frame with the highest `TransferFrameID` priority is taken out of storage first.
+    /// Frames not matching `accept` are discarded without being assembled.
+    fn receive<F, H, A>(&self, storage_handle: &H, mut accept: A) -> Option<(FullTransferID, Result<Frame<T>, ReceiveError>)>
+        where F: TransferFrame,
+              H: SubscriberStorageHandle<F>,
+              A: FnMut(&F) -> bool,
+    {
+        let mut transfers = self.transfers.borrow_mut();
+        loop {
+            let frame = storage_handle.pop()?;
+            if !accept(&frame) {
+                continue;
+            }
+            let full_id = frame.full_id();
+
+            // Single frame transfers are assembled right away, and never need a slot
+            if frame.is_start_frame() && frame.is_end_frame() {
+                let mut assembler = FrameAssembler::new();
+                let result = assembler.add_transfer_frame(frame);
+                return Some((full_id, result.map_err(|error| assembler_error(full_id, error)).and_then(|_| build_transfer(full_id, assembler))));
+            }
+
+            if frame.is_start_frame() {
+                // A restarted transfer replaces the partial one
+                let slot = transfers.iter().position(|x| x.as_ref().map_or(false, |x| x.full_id == full_id))
+                    .or_else(|| transfers.iter().position(Option::is_none));
+                match slot {
+                    Some(index) => transfers[index] = Some(PartialTransfer {
+                        full_id,
+                        assembler: FrameAssembler::new(),
+                        started: frame.timestamp().map(|x| x.monotonic),
+                    }),
+                    None => self.dropped.set(self.dropped.get().saturating_add(1)),
+                }
+            }
+
+            let index = match transfers.iter().position(|x| x.as_ref().map_or(false, |x| x.full_id == full_id)) {
+                Some(index) => index,
+                // The start of the transfer was lost, discarded after timing out, or there were no free slots
+                None if frame.is_end_frame() => return Some((full_id, Err(assembler_error(full_id, AssemblerError::FirstFrameNotStartFrame)))),
+                None => continue,
+            };
+
+            let result = transfers[index].as_mut().unwrap().assembler.add_transfer_frame(frame);
+            match result {
+                Ok(AssemblerResult::Ok) => (),
+                Ok(AssemblerResult::Finished) => {
+                    let transfer = transfers[index].take().unwrap();
+                    return Some((full_id, build_transfer(full_id, transfer.assembler)));
+                },
+                Err(error) => {
+                    transfers[index] = None;
+                    storage_handle.retain(|x| x.full_id() != full_id);
+                    return Some((full_id, Err(assembler_error(full_id, error))));
+                },
+            }
+        }
+    }
+
+    /// Discards the oldest partial transfer if it's older than `TRANSFER_TIMEOUT_MS`.
+    ///
+    /// The age of a transfer is measured from the timestamp of its start frame,
+    /// or from the first call to this method after it was started if the interface doesn't timestamp received frames.
+    fn remove_timed_out(&self, now: Timestamp) -> Option<ReceiveError> {
+        let mut transfers = self.transfers.borrow_mut();
+        let (index, started) = transfers.iter_mut()
+            .enumerate()
+            .filter_map(|(index, x)| x.as_mut().map(|x| (index, *x.started.get_or_insert(now))))
+            .min_by_key(|&(_, started)| started)?;
+
+        if now.duration_since(started) < Duration::from_millis(u64::from(TRANSFER_TIMEOUT_MS)) {
+            return None;
+        }
+
+        let full_id = transfers[index].take().unwrap().full_id;
+        Some(ReceiveError {
+            transfer_frame_id: full_id.frame_id,
+            transfer_id: full_id.transfer_id,
+            error_code: ReceiveErrorCode::Timeout,
+        })
+    }
+}
+
+impl<T: Struct> fmt::Debug for PartialTransfers<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let transfers = self.transfers.borrow();
+        f.debug_list()
+            .entries(transfers.iter().flatten().map(|x| x.full_id))
+            .finish()
+    }
+}
+
+/// Converts an error from assembling the transfer `full_id` to a `ReceiveError`.
+fn assembler_error(full_id: FullTransferID, error: AssemblerError) -> ReceiveError {
+    let error_code = match error {
+        AssemblerError::ToggleError => ReceiveErrorCode::ToggleError,
+        AssemblerError::FirstFrameNotStartFrame => ReceiveErrorCode::MissingStartFrame,
+        _ => panic!("Unexpected error from FrameAssembler"),
+    };
+    ReceiveError {
+        transfer_frame_id: full_id.frame_id,
+        transfer_id: full_id.transfer_id,
+        error_code,
+    }
+}
+
+/// Builds the `Frame` of the completely assembled transfer `full_id`.
+fn build_transfer<T: Struct>(full_id: FullTransferID, assembler: FrameAssembler<T>) -> Result<Frame<T>, ReceiveError> {
+    assembler.build().map_err(|error| match error {
+        BuildError::CRCError => ReceiveError {
+            transfer_frame_id: full_id.frame_id,
+            transfer_id: full_id.transfer_id,
+            error_code: ReceiveErrorCode::CRCError,
+        },
+        BuildError::NotFinishedParsing => panic!("Unexpected error from FrameAssembler"),
+    })
 }
 
 /// Full Error status from a failed receive
@@ -169,6 +738,26 @@ pub struct ReceiveError {
 pub enum ReceiveErrorCode {
     CRCError,
     ToggleError,
+    Timeout,
+    InvalidID,
+    MissingStartFrame,
+}
+
+/// The error kind for a failed transmission
+#[derive(Debug, PartialEq, Eq)]
+pub enum TransmitError {
+    /// Anonymous nodes are only allowed to send single frame transfers.
+    AnonymousMultiFrameTransfer,
+    /// Anonymous nodes are not allowed to send service transfers.
+    AnonymousServiceTransfer,
+    /// No interface queue could hold the transfer, so it was dropped.
+    Storage(StorageError),
+}
+
+impl From<StorageError> for TransmitError {
+    fn from(error: StorageError) -> Self {
+        TransmitError::Storage(error)
+    }
 }
 
 /// A minimal featured Uavcan node.
@@ -182,9 +771,7 @@ pub struct SimpleNode<I, D, S>
           S: Storage<I::Frame>,
 {
     interface: D,
-    interface_storage: S::InterfaceStorageHandle,
-    storage: S,
-    config: NodeConfig,
+    core: NodeCore<I::Frame, S, 1>,
 }
 
 
@@ -192,14 +779,14 @@ impl<I, D, S> SimpleNode<I, D, S>
     where I: TransferInterface,
           D: ::lib::core::ops::Deref<Target=I>,
           S: Storage<I::Frame>,
+          I::Frame: Clone,
 {
+    /// # Panics
+    /// Panics if the storage can't hold an interface queue.
     pub fn new(interface: D, config: NodeConfig) -> Self {
-        let storage = S::new();
         SimpleNode{
             interface: interface,
-            interface_storage: storage.new_interface(),
-            config: config,
-            storage: storage,
+            core: NodeCore::new(S::new(), config),
         }
     }
 
@@ -209,7 +796,8 @@ impl<I, D, S> SimpleNode<I, D, S>
     /// from incoming interface mailboxes to the storage buffer.
     pub fn flush_receptions(&self) {
         while let Some(new_frame) = self.interface.receive() {
-            self.storage.insert_subscriber_queue(new_frame).expect("Storage full");
+            self.core.update_stats(|stats| stats.frames_rx += 1);
+            self.core.insert_received(new_frame);
         }
     }
 
@@ -219,16 +807,24 @@ impl<I, D, S> SimpleNode<I, D, S>
     /// from storage buffers to the outgoing interface mailboxes.
     pub fn flush_transmissions(&self) {
         //TODO: Handle priority inversion concerns correctly
-        while let Some(top_frame) = self.interface_storage.pop() {
+        let interface_storage = &self.core.interface_storage[0];
+        while let Some(top_frame) = interface_storage.pop() {
             match self.interface.transmit(&top_frame) {
-                Ok(_) => (),
+                Ok(_) => self.core.update_stats(|stats| stats.frames_tx += 1),
                 Err(_) => {
-                    self.interface_storage.push(top_frame).expect("Storage Full");
+                    if interface_storage.push_front(top_frame).is_err() {
+                        self.core.update_stats(|stats| stats.tx_frames_dropped += 1);
+                    }
                     return;
                 }
             }
         }
     }
+
+    /// Returns the traffic counters of this node.
+    pub fn transport_stats(&self) -> TransportStats {
+        self.core.stats()
+    }
 }
 
 
@@ -236,51 +832,1814 @@ impl<I, D, S> Node<I, S> for SimpleNode<I, D, S>
     where I: TransferInterface,
           D: ::lib::core::ops::Deref<Target=I>,
           S: Storage<I::Frame>,
+          I::Frame: Clone,
 {
-    fn broadcast<T: Struct + Message>(&self, message: T) -> Result<(), IOError> {
-        let priority = 0;
-        let transfer_id = TransferID::new(0);
-        
-        let mut generator = if let Some(ref node_id) = self.config.id {
-            FrameDisassembler::from_uavcan_frame(Frame::from_message(message, priority, *node_id), transfer_id)
-        } else {
-            unimplemented!("Anonymous transfers not implemented")
-        };
-        
-        while let Some(can_frame) = generator.next_transfer_frame() {
-            self.storage.insert_interface_queue(can_frame).unwrap();
-        }
-        // TODO: Transfer into interface at this point or first attempt to add directly to interface.
+    fn id(&self) -> Option<NodeID> {
+        self.core.id()
+    }
 
-        Ok(())
+    fn set_id(&self, id: NodeID) {
+        self.core.set_id(id);
     }
 
-    fn subscribe<T: Struct + Message>(&self) -> Subscriber<T, I::Frame, S::SubscriberStorageHandle> {
-        let id = if let Some(type_id) = T::TYPE_ID {
-            u32::from(type_id) << 8
-        } else {
-            unimplemented!("Resolvation of type id is not supported yet")
-        };
+    fn broadcast_with_priority<T: Struct + Message>(&self, message: T, priority: TransferPriority) -> Result<(), TransmitError> {
+        self.core.broadcast_with_priority(message, priority)
+    }
 
-        let filter = TransferFrameIDFilter::new(id, 0x1ff << 7);
-    
-        Subscriber::new(self.storage.subscribe_to(filter))
+    fn subscribe<T: Struct + Message>(&self) -> Result<Subscriber<T, I::Frame, S::SubscriberStorageHandle>, StorageError> {
+        self.core.subscribe()
     }
-}
 
+    fn subscribe_anonymous<T: Struct + Message>(&self) -> Result<Subscriber<T, I::Frame, S::SubscriberStorageHandle>, StorageError> {
+        self.core.subscribe_anonymous()
+    }
 
+    fn request_with_priority<T: Struct + Request>(&self, destination: NodeID, request: T, priority: TransferPriority, deadline: Timestamp) -> Result<ResponseHandle<T, I::Frame, S::SubscriberStorageHandle>, TransmitError> {
+        self.core.request_with_priority(destination, request, priority, deadline)
+    }
 
+    fn serve<T: Struct + Request>(&self) -> Result<ServiceServer<T, I::Frame, S::SubscriberStorageHandle>, StorageError> {
+        self.core.serve()
+    }
 
+    fn respond<T: Struct + Response>(&self, token: ResponseToken<T::REQUEST>, response: T) -> Result<(), TransmitError> {
+        self.core.respond(token, response)
+    }
 
+    fn transmission_timestamp<T: Struct + Message>(&self) -> Option<FrameTimestamp> {
+        let id = Frame::<T>::message_id(T::PRIORITY, self.core.id()?);
+        self.interface.transmission_timestamp(id)
+    }
+}
 
-impl From<NodeID> for u8 {
-    fn from(id: NodeID) -> u8 {
-        id.0
+/// A node connected to `N` redundant interfaces.
+///
+/// Every outgoing frame is queued for transmission on all interfaces, which are flushed independently.
+/// A transfer that doesn't fit in the queue of one interface is dropped on that interface only.
+/// Receptions from all interfaces are merged, with duplicates removed by a `RedundancyFilter`
+/// keeping track of `FILTER_CAPACITY` kinds of transfers.
+#[derive(Debug)]
+pub struct MultiInterfaceNode<I, D, S, const N: usize, const FILTER_CAPACITY: usize = REDUNDANCY_FILTER_CAPACITY>
+    where I: TransferInterface,
+          D: ::lib::core::ops::Deref<Target=I>,
+          S: Storage<I::Frame>,
+{
+    interfaces: [D; N],
+    redundancy_filter: Mutex<RefCell<RedundancyFilter<FILTER_CAPACITY>>>,
+    core: NodeCore<I::Frame, S, N>,
+}
+
+impl<I, D, S, const N: usize, const FILTER_CAPACITY: usize> MultiInterfaceNode<I, D, S, N, FILTER_CAPACITY>
+    where I: TransferInterface,
+          D: ::lib::core::ops::Deref<Target=I>,
+          S: Storage<I::Frame>,
+          I::Frame: Clone,
+{
+    /// # Panics
+    /// Panics if the storage can't hold `N` interface queues.
+    pub fn new(interfaces: [D; N], config: NodeConfig) -> Self {
+        MultiInterfaceNode{
+            interfaces,
+            redundancy_filter: Mutex::new(RefCell::new(RedundancyFilter::new())),
+            core: NodeCore::new(S::new(), config),
+        }
+    }
+
+    /// Call this method after an interface have sucesfully received a new frame or periodically
+    ///
+    /// Moves as many frames as possible from the incoming interface mailboxes to the storage buffer,
+    /// dropping frames of transfers already received on another interface.
+    /// The interfaces are read one frame at the time in turn, so the copies of a transfer are received close together.
+    /// `now` is used for switching between interfaces if the frames are not timestamped by the interfaces.
+    pub fn flush_receptions(&self, now: Timestamp) {
+        let mut received = true;
+        while received {
+            received = false;
+            for (index, interface) in self.interfaces.iter().enumerate() {
+                if let Some(new_frame) = interface.receive() {
+                    received = true;
+                    self.core.update_stats(|stats| stats.frames_rx += 1);
+                    if new_frame.data().is_empty() || critical_section::with(|cs| self.redundancy_filter.borrow(cs).borrow_mut().accept(&new_frame, index, now)) {
+                        self.core.insert_received(new_frame);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Call this method after an interface have successfully transmitted a new frame or periodically
+    ///
+    /// Moves as many frames as possible from the storage buffers to the outgoing interface mailboxes.
+    /// A full interface does not prevent transmission on the others.
+    pub fn flush_transmissions(&self) {
+        for (interface, interface_storage) in self.interfaces.iter().zip(self.core.interface_storage.iter()) {
+            while let Some(top_frame) = interface_storage.pop() {
+                if interface.transmit(&top_frame).is_err() {
+                    if interface_storage.push_front(top_frame).is_err() {
+                        self.core.update_stats(|stats| stats.tx_frames_dropped += 1);
+                    }
+                    break;
+                }
+                self.core.update_stats(|stats| stats.frames_tx += 1);
+            }
+        }
+    }
+
+    /// Returns the traffic counters of this node, summed over all interfaces.
+    pub fn transport_stats(&self) -> TransportStats {
+        self.core.stats()
     }
 }
 
-impl From<NodeID> for u32 {
-    fn from(id: NodeID) -> u32 {
-        u32::from(id.0)
+impl<I, D, S, const N: usize, const FILTER_CAPACITY: usize> Node<I, S> for MultiInterfaceNode<I, D, S, N, FILTER_CAPACITY>
+    where I: TransferInterface,
+          D: ::lib::core::ops::Deref<Target=I>,
+          S: Storage<I::Frame>,
+          I::Frame: Clone,
+{
+    fn id(&self) -> Option<NodeID> {
+        self.core.id()
+    }
+
+    fn set_id(&self, id: NodeID) {
+        self.core.set_id(id);
+    }
+
+    fn broadcast_with_priority<T: Struct + Message>(&self, message: T, priority: TransferPriority) -> Result<(), TransmitError> {
+        self.core.broadcast_with_priority(message, priority)
+    }
+
+    fn subscribe<T: Struct + Message>(&self) -> Result<Subscriber<T, I::Frame, S::SubscriberStorageHandle>, StorageError> {
+        self.core.subscribe()
+    }
+
+    fn subscribe_anonymous<T: Struct + Message>(&self) -> Result<Subscriber<T, I::Frame, S::SubscriberStorageHandle>, StorageError> {
+        self.core.subscribe_anonymous()
+    }
+
+    fn request_with_priority<T: Struct + Request>(&self, destination: NodeID, request: T, priority: TransferPriority, deadline: Timestamp) -> Result<ResponseHandle<T, I::Frame, S::SubscriberStorageHandle>, TransmitError> {
+        self.core.request_with_priority(destination, request, priority, deadline)
+    }
+
+    fn serve<T: Struct + Request>(&self) -> Result<ServiceServer<T, I::Frame, S::SubscriberStorageHandle>, StorageError> {
+        self.core.serve()
+    }
+
+    fn respond<T: Struct + Response>(&self, token: ResponseToken<T::REQUEST>, response: T) -> Result<(), TransmitError> {
+        self.core.respond(token, response)
+    }
+
+    /// Returns the transmission timestamp reported by the first interface capable of timestamping.
+    fn transmission_timestamp<T: Struct + Message>(&self) -> Option<FrameTimestamp> {
+        let id = Frame::<T>::message_id(T::PRIORITY, self.core.id()?);
+        self.interfaces.iter().filter_map(|interface| interface.transmission_timestamp(id)).next()
+    }
+}
+
+/// A node providing the standard services expected from a Uavcan node.
+///
+/// On top of what `SimpleNode` does, the `FullNode`:
+///
+/// - Publishes `NodeStatus`, the status is changed through `status_publisher`.
+/// - Responds to `GetNodeInfo` with the identity from the `NodeConfig`, see `NodeInfoServer`.
+/// - Responds to `GetDataTypeInfo` for the data types used by the node itself.
+/// - Responds to `RestartNode`, the application must check `restart_requested` and restart.
+/// - Responds to `GetTransportStats`.
+///
+/// `tick` must be called periodically, at least every `NodeStatus::MAX_BROADCASTING_PERIOD_MS`.
+#[derive(Debug)]
+pub struct FullNode<I, D, S>
+    where I: TransferInterface,
+          D: ::lib::core::ops::Deref<Target=I>,
+          S: Storage<I::Frame>,
+{
+    node: SimpleNode<I, D, S>,
+    config: NodeConfig,
+    status_publisher: RefCell<NodeStatusPublisher>,
+    servers: RefCell<Option<StandardServers<I::Frame, S::SubscriberStorageHandle>>>,
+    restart_requested: Cell<bool>,
+}
+
+#[derive(Debug)]
+struct StandardServers<F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    node_info: NodeInfoServer<F, H>,
+    data_type_info: ServiceServer<GetDataTypeInfoRequest, F, H>,
+    restart: ServiceServer<RestartNodeRequest, F, H>,
+    transport_stats: ServiceServer<GetTransportStatsRequest, F, H>,
+}
+
+/// A data type used by the `FullNode` itself, reported through `GetDataTypeInfo`.
+struct KnownDataType {
+    name: &'static str,
+    kind: u8,
+    id: u16,
+    signature: u64,
+    flags: u8,
+}
+
+impl<I, D, S> FullNode<I, D, S>
+    where I: TransferInterface,
+          D: ::lib::core::ops::Deref<Target=I>,
+          S: Storage<I::Frame>,
+          I::Frame: Clone,
+{
+    /// Creates a new node that started at `start`.
+    ///
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage can't hold the servers of the standard services.
+    ///
+    /// # Panics
+    /// Panics if the storage can't hold an interface queue.
+    pub fn new(interface: D, config: NodeConfig, start: Timestamp) -> Result<Self, StorageError> {
+        let node = SimpleNode::new(interface, config.clone());
+        let servers = FullNode::<I, D, S>::serve_standard(&node, &config)?;
+        Ok(FullNode {
+            node,
+            config,
+            status_publisher: RefCell::new(NodeStatusPublisher::new(start)),
+            servers: RefCell::new(Some(servers)),
+            restart_requested: Cell::new(false),
+        })
+    }
+
+    fn serve_standard(node: &SimpleNode<I, D, S>, config: &NodeConfig) -> Result<StandardServers<I::Frame, S::SubscriberStorageHandle>, StorageError> {
+        Ok(StandardServers {
+            node_info: NodeInfoServer::new(node, config)?,
+            data_type_info: node.serve()?,
+            restart: node.serve()?,
+            transport_stats: node.serve()?,
+        })
+    }
+
+    /// Returns the publisher keeping track of the health and mode reported in `NodeStatus`.
+    pub fn status_publisher(&self) -> RefMut<'_, NodeStatusPublisher> {
+        self.status_publisher.borrow_mut()
+    }
+
+    /// Returns true if a valid `RestartNode` request have been received.
+    pub fn restart_requested(&self) -> bool {
+        self.restart_requested.get()
+    }
+
+    /// Returns the traffic counters of this node.
+    pub fn transport_stats(&self) -> TransportStats {
+        self.node.transport_stats()
+    }
+
+    /// See `SimpleNode::flush_receptions`.
+    pub fn flush_receptions(&self) {
+        self.node.flush_receptions()
+    }
+
+    /// See `SimpleNode::flush_transmissions`.
+    pub fn flush_transmissions(&self) {
+        self.node.flush_transmissions()
+    }
+
+    /// Publishes `NodeStatus` when due and responds to the standard service requests.
+    ///
+    /// Should be called after receptions are flushed, transmissions must be flushed afterwards.
+    /// A full interface queue doesn't stop the remaining work, the first error is returned.
+    pub fn tick(&self, now: Timestamp) -> Result<(), TransmitError> {
+        let mut result = self.status_publisher.borrow_mut().tick(&self.node, now);
+
+        let servers = self.servers.borrow();
+        let servers = match *servers {
+            Some(ref servers) => servers,
+            None => return result,
+        };
+
+        let status = self.status_publisher.borrow().status(now);
+        while let Some(received) = servers.node_info.server().receive() {
+            match received {
+                Ok(request) => result = result.and(self.node.respond(request.token, servers.node_info.response(status.clone()))),
+                Err(_) => self.count_transfer_error(),
+            }
+        }
+
+        while let Some(received) = servers.data_type_info.receive() {
+            match received {
+                Ok(request) => {
+                    let response = FullNode::<I, D, S>::data_type_info(&request.body);
+                    result = result.and(self.node.respond(request.token, response));
+                },
+                Err(_) => self.count_transfer_error(),
+            }
+        }
+
+        while let Some(received) = servers.restart.receive() {
+            match received {
+                Ok(request) => {
+                    let ok = u64::from(request.body.magic_number) == RestartNodeRequest::MAGIC_NUMBER;
+                    if ok {
+                        self.restart_requested.set(true);
+                    }
+                    result = result.and(self.node.respond(request.token, RestartNodeResponse{ok}));
+                },
+                Err(_) => self.count_transfer_error(),
+            }
+        }
+
+        while let Some(received) = servers.transport_stats.receive() {
+            match received {
+                Ok(request) => {
+                    let response = self.transport_stats_response();
+                    result = result.and(self.node.respond(request.token, response));
+                },
+                Err(_) => self.count_transfer_error(),
+            }
+        }
+
+        result
+    }
+
+    fn count_transfer_error(&self) {
+        self.node.core.update_stats(|stats| stats.transfer_errors += 1);
+    }
+
+    fn data_type_info(request: &GetDataTypeInfoRequest) -> GetDataTypeInfoResponse {
+        let serving = GetDataTypeInfoResponse::FLAG_KNOWN | GetDataTypeInfoResponse::FLAG_SERVING;
+        let known = [
+            KnownDataType {
+                name: "uavcan.protocol.NodeStatus",
+                kind: DataTypeKind::MESSAGE,
+                id: <NodeStatus as Message>::TYPE_ID.unwrap(),
+                signature: NodeStatus::DATA_TYPE_SIGNATURE,
+                flags: GetDataTypeInfoResponse::FLAG_KNOWN | GetDataTypeInfoResponse::FLAG_PUBLISHING,
+            },
+            KnownDataType {
+                name: "uavcan.protocol.GetNodeInfo",
+                kind: DataTypeKind::SERVICE,
+                id: u16::from(<GetNodeInfoRequest as Request>::TYPE_ID.unwrap()),
+                signature: GetNodeInfoRequest::DATA_TYPE_SIGNATURE,
+                flags: serving,
+            },
+            KnownDataType {
+                name: "uavcan.protocol.GetDataTypeInfo",
+                kind: DataTypeKind::SERVICE,
+                id: u16::from(<GetDataTypeInfoRequest as Request>::TYPE_ID.unwrap()),
+                signature: GetDataTypeInfoRequest::DATA_TYPE_SIGNATURE,
+                flags: serving,
+            },
+            KnownDataType {
+                name: "uavcan.protocol.GetTransportStats",
+                kind: DataTypeKind::SERVICE,
+                id: u16::from(<GetTransportStatsRequest as Request>::TYPE_ID.unwrap()),
+                signature: GetTransportStatsRequest::DATA_TYPE_SIGNATURE,
+                flags: serving,
+            },
+            KnownDataType {
+                name: "uavcan.protocol.RestartNode",
+                kind: DataTypeKind::SERVICE,
+                id: u16::from(<RestartNodeRequest as Request>::TYPE_ID.unwrap()),
+                signature: RestartNodeRequest::DATA_TYPE_SIGNATURE,
+                flags: serving,
+            },
+        ];
+
+        let data_type = known.iter().find(|data_type| if request.name.length() > 0 {
+            data_type.name.as_bytes() == request.name.as_ref()
+        } else {
+            data_type.id == request.id && data_type.kind == request.kind.value
+        });
+
+        match data_type {
+            Some(data_type) => GetDataTypeInfoResponse {
+                signature: data_type.signature,
+                id: data_type.id,
+                kind: DataTypeKind{value: data_type.kind},
+                flags: data_type.flags,
+                name: Dynamic::<[u8; 80]>::with_data(data_type.name.as_bytes()),
+            },
+            None => GetDataTypeInfoResponse {
+                signature: 0,
+                id: request.id,
+                kind: request.kind.clone(),
+                flags: 0,
+                name: request.name.clone(),
+            },
+        }
+    }
+
+    fn transport_stats_response(&self) -> GetTransportStatsResponse {
+        // The counters are 48 bit on the wire and wrap around
+        let counter = |value: u64| u48::new(value & 0xffff_ffff_ffff);
+        let stats = self.node.transport_stats();
+        GetTransportStatsResponse {
+            transfers_tx: counter(stats.transfers_tx),
+            transfers_rx: counter(stats.transfers_rx),
+            transfer_errors: counter(stats.transfer_errors),
+            can_iface_stats: Dynamic::<[CanIfaceStats; 3]>::with_data(&[CanIfaceStats {
+                frames_tx: counter(stats.frames_tx),
+                frames_rx: counter(stats.frames_rx),
+                // `TransferInterface` doesn't report errors of the CAN controller
+                errors: counter(0),
+            }]),
+        }
+    }
+}
+
+impl<I, D, S> Node<I, S> for FullNode<I, D, S>
+    where I: TransferInterface,
+          D: ::lib::core::ops::Deref<Target=I>,
+          S: Storage<I::Frame>,
+          I::Frame: Clone,
+{
+    fn id(&self) -> Option<NodeID> {
+        self.node.id()
+    }
+
+    /// Changes the `NodeID` of this node, the standard services are served on the new ID.
+    ///
+    /// The servers for the old ID are dropped before the new ones are created, freeing their storage.
+    /// If the storage still can't hold the new servers, the standard services are not served until the next `set_id`.
+    fn set_id(&self, id: NodeID) {
+        self.node.set_id(id);
+        let mut servers = self.servers.borrow_mut();
+        servers.take();
+        *servers = FullNode::<I, D, S>::serve_standard(&self.node, &self.config).ok();
+    }
+
+    fn broadcast_with_priority<T: Struct + Message>(&self, message: T, priority: TransferPriority) -> Result<(), TransmitError> {
+        self.node.broadcast_with_priority(message, priority)
+    }
+
+    fn subscribe<T: Struct + Message>(&self) -> Result<Subscriber<T, I::Frame, S::SubscriberStorageHandle>, StorageError> {
+        self.node.subscribe()
+    }
+
+    fn subscribe_anonymous<T: Struct + Message>(&self) -> Result<Subscriber<T, I::Frame, S::SubscriberStorageHandle>, StorageError> {
+        self.node.subscribe_anonymous()
+    }
+
+    fn request_with_priority<T: Struct + Request>(&self, destination: NodeID, request: T, priority: TransferPriority, deadline: Timestamp) -> Result<ResponseHandle<T, I::Frame, S::SubscriberStorageHandle>, TransmitError> {
+        self.node.request_with_priority(destination, request, priority, deadline)
+    }
+
+    fn serve<T: Struct + Request>(&self) -> Result<ServiceServer<T, I::Frame, S::SubscriberStorageHandle>, StorageError> {
+        self.node.serve()
+    }
+
+    fn respond<T: Struct + Response>(&self, token: ResponseToken<T::REQUEST>, response: T) -> Result<(), TransmitError> {
+        self.node.respond(token, response)
+    }
+
+    fn transmission_timestamp<T: Struct + Message>(&self) -> Option<FrameTimestamp> {
+        self.node.transmission_timestamp::<T>()
+    }
+}
+
+/// The parts of a node that are independent of how many interfaces it's connected to.
+struct NodeCore<F: TransferFrame, S: Storage<F>, const N: usize> {
+    storage: S,
+    interface_storage: [S::InterfaceStorageHandle; N],
+    drop_policy: DropPolicy,
+    id: Mutex<Cell<Option<NodeID>>>,
+    transfer_ids: Mutex<RefCell<TransferIDTable>>,
+    stats: Mutex<Cell<TransportStats>>,
+    phantom: PhantomData<F>,
+}
+
+// The interface queues are left out, the handles are not required to implement `Debug`
+impl<F: TransferFrame, S: Storage<F> + fmt::Debug, const N: usize> fmt::Debug for NodeCore<F, S, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeCore")
+            .field("storage", &self.storage)
+            .field("drop_policy", &self.drop_policy)
+            .field("id", &self.id)
+            .field("transfer_ids", &self.transfer_ids)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl<F: TransferFrame + Clone, S: Storage<F>, const N: usize> NodeCore<F, S, N> {
+    /// # Panics
+    /// Panics if the storage can't hold `N` interface queues.
+    fn new(storage: S, config: NodeConfig) -> Self {
+        NodeCore {
+            interface_storage: ::lib::core::array::from_fn(|_| storage.new_interface().expect("The storage has no room for an interface queue")),
+            storage,
+            drop_policy: config.drop_policy,
+            id: Mutex::new(Cell::new(config.id)),
+            transfer_ids: Mutex::new(RefCell::new(TransferIDTable::new())),
+            stats: Mutex::new(Cell::new(TransportStats::default())),
+            phantom: PhantomData,
+        }
+    }
+
+    fn id(&self) -> Option<NodeID> {
+        critical_section::with(|cs| self.id.borrow(cs).get())
+    }
+
+    fn set_id(&self, id: NodeID) {
+        critical_section::with(|cs| self.id.borrow(cs).set(Some(id)))
+    }
+
+    fn next_transfer_id(&self, frame_id: TransferFrameID) -> TransferID {
+        critical_section::with(|cs| self.transfer_ids.borrow(cs).borrow_mut().next_transfer_id(frame_id))
+    }
+
+    /// Gives back the transfer ID of a transfer that could not be queued for transmission.
+    fn release_transfer_id(&self, frame_id: TransferFrameID, transfer_id: TransferID) {
+        critical_section::with(|cs| self.transfer_ids.borrow(cs).borrow_mut().release_transfer_id(frame_id, transfer_id))
+    }
+
+    fn stats(&self) -> TransportStats {
+        critical_section::with(|cs| self.stats.borrow(cs).get())
+    }
+
+    fn update_stats<U: FnOnce(&mut TransportStats)>(&self, update: U) {
+        critical_section::with(|cs| {
+            let stats = self.stats.borrow(cs);
+            let mut updated = stats.get();
+            update(&mut updated);
+            stats.set(updated);
+        })
+    }
+
+    /// Routes a received frame to the subscribers.
+    ///
+    /// Frames without a tail byte are dropped and counted as transfer errors.
+    /// Drops caused by full subscriber queues are counted in the stats.
+    fn insert_received(&self, frame: F) {
+        if frame.data().is_empty() {
+            self.update_stats(|stats| stats.transfer_errors += 1);
+            return;
+        }
+
+        if frame.is_end_frame() {
+            self.update_stats(|stats| stats.transfers_rx += 1);
+        }
+        if self.storage.insert_subscriber_queue(frame, self.drop_policy) != Ok(Inserted::Queued) {
+            self.update_stats(|stats| stats.rx_frames_dropped += 1);
+        }
+    }
+
+    /// Queues all frames of a transfer for transmission.
+    fn insert_transfer<T: Struct>(&self, mut generator: FrameDisassembler<T>) -> Result<(), TransmitError> {
+        self.insert_frames(iter::from_fn(move || generator.next_transfer_frame()))
+    }
+
+    /// Queues the frames of a transfer for transmission on every interface.
+    ///
+    /// The interface queues are filled independently. If a frame doesn't fit in a queue, the already queued frames
+    /// of the transfer are removed from that queue again rather than transmitting a truncated transfer,
+    /// while the other interfaces still transmit it. Fails only if no interface queue could hold the transfer.
+    fn insert_frames<G: Iterator<Item=F>>(&self, frames: G) -> Result<(), TransmitError> {
+        let mut accepted = [true; N];
+        let mut length = 0;
+        let mut error = StorageError::OutOfSpace;
+        for frame in frames {
+            for (interface_storage, accepted) in self.interface_storage.iter().zip(accepted.iter_mut()).filter(|(_, accepted)| **accepted) {
+                if let Err(storage_error) = interface_storage.push(frame.clone()) {
+                    interface_storage.remove_transfer(&frame.full_id());
+                    *accepted = false;
+                    error = storage_error;
+                }
+            }
+            length += 1;
+        }
+
+        // Every interface dropping the transfer counts its frames on its own
+        let dropped = accepted.iter().filter(|accepted| !**accepted).count() as u64 * length;
+        if dropped > 0 {
+            self.update_stats(|stats| stats.tx_frames_dropped += dropped);
+        }
+
+        if accepted.iter().any(|accepted| *accepted) {
+            Ok(())
+        } else {
+            Err(TransmitError::from(error))
+        }
+    }
+
+    fn broadcast_with_priority<T: Struct + Message>(&self, message: T, priority: TransferPriority) -> Result<(), TransmitError> {
+        if let Some(node_id) = self.id() {
+            let frame = Frame::from_message(message, priority, node_id);
+            let frame_id = frame.id;
+            let transfer_id = self.next_transfer_id(frame_id);
+            if let Err(error) = self.insert_transfer(FrameDisassembler::from_uavcan_frame(frame, transfer_id)) {
+                self.release_transfer_id(frame_id, transfer_id);
+                return Err(error);
+            }
+        } else {
+            // The discriminator depends on the payload, so the frame is first created with discriminator 0
+            let frame = Frame::from_anonymous_message(message, priority, u14::new(0));
+            let frame_id = frame.id;
+            let mut can_frame: F = FrameDisassembler::from_uavcan_frame(frame, TransferID::new(0)).next_transfer_frame().unwrap();
+            if !can_frame.is_single_frame() {
+                return Err(TransmitError::AnonymousMultiFrameTransfer);
+            }
+
+            // Rejected transfers don't consume a transfer ID
+            let transfer_id = self.next_transfer_id(frame_id);
+            let tail_byte_index = can_frame.data().len() - 1;
+            can_frame.data_as_mut()[tail_byte_index] = TailByte::new(true, true, false, transfer_id).into();
+
+            if let Err(error) = self.insert_frames(iter::once(with_anonymous_discriminator(can_frame))) {
+                self.release_transfer_id(frame_id, transfer_id);
+                return Err(error);
+            }
+        }
+        // TODO: Transfer into interface at this point or first attempt to add directly to interface.
+
+        self.update_stats(|stats| stats.transfers_tx += 1);
+        Ok(())
+    }
+
+    fn subscribe<T: Struct + Message>(&self) -> Result<Subscriber<T, F, S::SubscriberStorageHandle>, StorageError> {
+        let filter = if let Some(type_id) = T::TYPE_ID {
+            CanId::message_filter(type_id)
+        } else {
+            unimplemented!("Resolvation of type id is not supported yet")
+        };
+    
+        Ok(Subscriber::new(self.storage.subscribe_to(filter)?, false))
+    }
+
+    fn subscribe_anonymous<T: Struct + Message>(&self) -> Result<Subscriber<T, F, S::SubscriberStorageHandle>, StorageError> {
+        let type_id = if let Some(type_id) = T::TYPE_ID {
+            type_id
+        } else {
+            unimplemented!("Resolvation of type id is not supported yet")
+        };
+
+        let filter = CanId::anonymous_message_filter(type_id);
+
+        Ok(Subscriber::new(self.storage.subscribe_to(filter)?, true))
+    }
+
+    fn request_with_priority<T: Struct + Request>(&self, destination: NodeID, request: T, priority: TransferPriority, deadline: Timestamp) -> Result<ResponseHandle<T, F, S::SubscriberStorageHandle>, TransmitError> {
+        let node_id = if let Some(node_id) = self.id() {
+            node_id
+        } else {
+            return Err(TransmitError::AnonymousServiceTransfer);
+        };
+
+        let type_id = if let Some(type_id) = T::TYPE_ID {
+            type_id
+        } else {
+            unimplemented!("Resolvation of type id is not supported yet")
+        };
+
+        let response_id = TransferFrameID::from(CanId::ServiceResponse {
+            priority,
+            type_id,
+            source: destination,
+            destination: node_id,
+        });
+
+        // Subscribe before transmitting to make sure the response is not missed,
+        // and before taking a transfer ID so it's not lost if the storage is full
+        let filter = CanId::service_response_filter(type_id, destination, node_id);
+        let subscription = self.storage.subscribe_to(filter)?;
+
+        let frame = Frame::from_request(request, priority, node_id, destination);
+        let frame_id = frame.id;
+        let transfer_id = self.next_transfer_id(frame_id);
+        let handle = ResponseHandle::new(subscription, response_id, transfer_id, deadline);
+
+        if let Err(error) = self.insert_transfer(FrameDisassembler::from_uavcan_frame(frame, transfer_id)) {
+            // Dropping the handle removes the response subscription again
+            drop(handle);
+            self.release_transfer_id(frame_id, transfer_id);
+            return Err(error);
+        }
+
+        self.update_stats(|stats| stats.transfers_tx += 1);
+        Ok(handle)
+    }
+
+    fn serve<T: Struct + Request>(&self) -> Result<ServiceServer<T, F, S::SubscriberStorageHandle>, StorageError> {
+        let type_id = if let Some(type_id) = T::TYPE_ID {
+            type_id
+        } else {
+            unimplemented!("Resolvation of type id is not supported yet")
+        };
+
+        // No valid request is addressed to node 0, so anonymous nodes will never match
+        let filter = CanId::service_request_filter(type_id, self.id());
+
+        Ok(ServiceServer::new(self.storage.subscribe_to(filter)?))
+    }
+
+    fn respond<T: Struct + Response>(&self, token: ResponseToken<T::REQUEST>, response: T) -> Result<(), TransmitError> {
+        let node_id = if let Some(node_id) = self.id() {
+            node_id
+        } else {
+            return Err(TransmitError::AnonymousServiceTransfer);
+        };
+
+        self.insert_transfer(FrameDisassembler::from_uavcan_frame(Frame::from_response(response, token.priority, node_id, token.source), token.transfer_id))?;
+
+        self.update_stats(|stats| stats.transfers_tx += 1);
+        Ok(())
+    }
+}
+
+
+
+
+
+
+impl From<NodeID> for u8 {
+    fn from(id: NodeID) -> u8 {
+        id.0
+    }
+}
+
+impl From<NodeID> for u32 {
+    fn from(id: NodeID) -> u32 {
+        u32::from(id.0)
+    }
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use *;
+    use node::*;
+    use storage::HeapStorage;
+    use time::Timestamp;
+    use transfer::TailByte;
+    use transfer::IOError;
+
+    use tests::{
+        CanFrame,
+        TestInterface,
+        EchoRequest,
+        EchoResponse,
+    };
+
+    #[test]
+    fn request_response() {
+        let interface = TestInterface::default();
+        let config = NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()};
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, config);
+
+        let handle = node.request(NodeID::new(42), EchoRequest{value: 7}, Timestamp::from_micros(1000)).unwrap();
+        node.flush_transmissions();
+
+        let request_frame = interface.tx.borrow_mut().pop_front().unwrap();
+        assert!(request_frame.is_single_frame());
+        assert_eq!(request_frame.tail_byte().transfer_id(), handle.transfer_id());
+
+        assert_eq!(handle.receive(Timestamp::from_micros(0)), None);
+
+        let response = Frame::from_response(EchoResponse{value: 8}, TransferPriority::MEDIUM, NodeID::new(42), NodeID::new(10));
+        let mut generator = FrameDisassembler::from_uavcan_frame(response, handle.transfer_id());
+        interface.rx.borrow_mut().push_back(generator.next_transfer_frame().unwrap());
+        node.flush_receptions();
+
+        assert_eq!(handle.receive(Timestamp::from_micros(0)), Some(Ok(EchoResponse{value: 8})));
+    }
+
+    #[test]
+    fn no_timeout_after_response() {
+        let interface = TestInterface::default();
+        let config = NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()};
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, config);
+
+        let handle = node.request(NodeID::new(42), EchoRequest{value: 7}, Timestamp::from_micros(1000)).unwrap();
+
+        let response = Frame::from_response(EchoResponse{value: 8}, TransferPriority::MEDIUM, NodeID::new(42), NodeID::new(10));
+        let mut generator = FrameDisassembler::from_uavcan_frame(response, handle.transfer_id());
+        interface.rx.borrow_mut().push_back(generator.next_transfer_frame().unwrap());
+        node.flush_receptions();
+
+        assert_eq!(handle.receive(Timestamp::from_micros(0)), Some(Ok(EchoResponse{value: 8})));
+        assert_eq!(handle.receive(Timestamp::from_micros(0)), None);
+        assert_eq!(handle.receive(Timestamp::from_micros(1000)), None);
+    }
+
+    #[test]
+    fn request_timeout() {
+        let interface = TestInterface::default();
+        let config = NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()};
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, config);
+
+        let handle = node.request(NodeID::new(42), EchoRequest{value: 7}, Timestamp::from_micros(1000)).unwrap();
+
+        // A response with the wrong transfer ID must not be accepted
+        let response = Frame::from_response(EchoResponse{value: 8}, TransferPriority::MEDIUM, NodeID::new(42), NodeID::new(10));
+        let mut generator = FrameDisassembler::from_uavcan_frame(response, handle.transfer_id().next());
+        interface.rx.borrow_mut().push_back(generator.next_transfer_frame().unwrap());
+        node.flush_receptions();
+
+        assert_eq!(handle.receive(Timestamp::from_micros(999)), None);
+        assert_eq!(handle.receive(Timestamp::from_micros(1000)).unwrap().unwrap_err().error_code, ReceiveErrorCode::Timeout);
+    }
+
+    #[test]
+    fn multi_frame_response() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        #[DataTypeSignature = "0x1f2e3d4c5b6a7988"]
+        struct LargeRequest {
+            value: u8,
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        #[DataTypeSignature = "0x1f2e3d4c5b6a7988"]
+        struct LargeResponse {
+            a: u64,
+            b: u64,
+        }
+
+        impl Request for LargeRequest {
+            type RESPONSE = LargeResponse;
+            const TYPE_ID: Option<u8> = Some(201);
+        }
+
+        impl Response for LargeResponse {
+            type REQUEST = LargeRequest;
+            const TYPE_ID: Option<u8> = Some(201);
+        }
+
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let handle = node.request(NodeID::new(42), LargeRequest{value: 7}, Timestamp::from_micros(1000)).unwrap();
+
+        let frames = |transfer_id: TransferID, a: u64| -> Vec<CanFrame> {
+            let response = Frame::from_response(LargeResponse{a, b: 0}, TransferPriority::MEDIUM, NodeID::new(42), NodeID::new(10));
+            let mut generator = FrameDisassembler::from_uavcan_frame(response, transfer_id);
+            iter::from_fn(|| generator.next_transfer_frame()).collect()
+        };
+
+        // A late response to an earlier request is interleaved with the frames of the matching response
+        let mut matching = frames(handle.transfer_id(), 1);
+        let end_frame = matching.pop().unwrap();
+        for (stale, matching) in frames(handle.transfer_id().next(), 2).into_iter().zip(matching) {
+            interface.rx.borrow_mut().extend([stale, matching]);
+        }
+        node.flush_receptions();
+
+        // The frames are assembled as they are received, and the stale ones discarded
+        assert_eq!(handle.receive(Timestamp::from_micros(0)), None);
+        assert_eq!(handle.storage_handle.find_id(|_| true), None);
+
+        interface.rx.borrow_mut().push_back(end_frame);
+        node.flush_receptions();
+        assert_eq!(handle.receive(Timestamp::from_micros(0)), Some(Ok(LargeResponse{a: 1, b: 0})));
+        assert_eq!(handle.receive(Timestamp::from_micros(0)), None);
+    }
+
+    #[test]
+    fn serve_request() {
+        let client_interface = TestInterface::default();
+        let client: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&client_interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+
+        let server_interface = TestInterface::default();
+        let server: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&server_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        let service_server = server.serve::<EchoRequest>().unwrap();
+
+        let handle = client.request(NodeID::new(42), EchoRequest{value: 7}, Timestamp::from_micros(1000)).unwrap();
+        client.flush_transmissions();
+        client_interface.transfer_to(&server_interface);
+        server.flush_receptions();
+
+        let request = service_server.receive().unwrap().unwrap();
+        assert_eq!(request.body, EchoRequest{value: 7});
+        assert_eq!(request.token.source(), NodeID::new(10));
+        assert_eq!(request.token.transfer_id(), handle.transfer_id());
+        assert!(service_server.receive().is_none());
+
+        assert_eq!(request.token.priority(), EchoRequest::PRIORITY);
+
+        server.respond(request.token, EchoResponse{value: request.body.value + 1}).unwrap();
+        server.flush_transmissions();
+        server_interface.transfer_to(&client_interface);
+        client.flush_receptions();
+
+        assert_eq!(handle.receive(Timestamp::from_micros(0)), Some(Ok(EchoResponse{value: 8})));
+    }
+
+    #[test]
+    fn serve_ignores_other_destinations() {
+        let client_interface = TestInterface::default();
+        let client: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&client_interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+
+        let server_interface = TestInterface::default();
+        let server: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&server_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        let service_server = server.serve::<EchoRequest>().unwrap();
+
+        client.request(NodeID::new(43), EchoRequest{value: 7}, Timestamp::from_micros(1000)).unwrap();
+        client.flush_transmissions();
+        client_interface.transfer_to(&server_interface);
+        server.flush_receptions();
+
+        assert!(service_server.receive().is_none());
+    }
+
+    #[test]
+    fn broadcast_transfer_id_increments() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Heartbeat {
+            value: u8,
+        }
+
+        impl Message for Heartbeat {
+            const TYPE_ID: Option<u16> = Some(20000);
+        }
+
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+
+        for _ in 0..33 {
+            node.broadcast(Heartbeat{value: 0}).unwrap();
+            node.flush_transmissions();
+        }
+
+        let transfer_ids: Vec<u8> = interface.tx.borrow().iter().map(|x| u8::from(x.tail_byte().transfer_id())).collect();
+        assert_eq!(&transfer_ids[0..32], &(0..32).collect::<Vec<u8>>()[..]);
+        assert_eq!(transfer_ids[32], 0);
+    }
+
+    #[test]
+    fn broadcast_priority() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Telemetry {
+            value: u8,
+        }
+
+        impl Message for Telemetry {
+            const TYPE_ID: Option<u16> = Some(20001);
+            const PRIORITY: TransferPriority = TransferPriority::LOW;
+        }
+
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+
+        node.broadcast(Telemetry{value: 0}).unwrap();
+        node.flush_transmissions();
+        assert_eq!(u32::from(interface.tx.borrow_mut().pop_front().unwrap().id()) >> 24, u32::from(TransferPriority::LOW));
+        
+        node.broadcast_with_priority(Telemetry{value: 0}, TransferPriority::HIGHEST).unwrap();
+        node.flush_transmissions();
+        assert_eq!(u32::from(interface.tx.borrow_mut().pop_front().unwrap().id()) >> 24, u32::from(TransferPriority::HIGHEST));
+    }
+
+    #[test]
+    fn receive_transfer() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Telemetry {
+            value: u8,
+        }
+
+        impl Message for Telemetry {
+            const TYPE_ID: Option<u16> = Some(20001);
+        }
+
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe::<Telemetry>().unwrap();
+
+        let remote_interface = TestInterface::default();
+        let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        remote.broadcast_with_priority(Telemetry{value: 3}, TransferPriority::HIGH).unwrap();
+        remote.broadcast(Telemetry{value: 4}).unwrap();
+        remote.flush_transmissions();
+
+        let timestamp = ::time::FrameTimestamp::from_monotonic(Timestamp::from_micros(1234));
+        interface.rx_timestamp.set(Some(timestamp));
+        remote_interface.transfer_to(&interface);
+        node.flush_receptions();
+
+        let transfer = subscriber.receive().unwrap().unwrap();
+        assert_eq!(transfer.body, Telemetry{value: 3});
+        assert_eq!(transfer.source, TransferSource::Node(NodeID::new(42)));
+        assert_eq!(transfer.priority, TransferPriority::HIGH);
+        assert_eq!(transfer.type_id, 20001);
+        assert_eq!(transfer.transfer_id, TransferID::new(0));
+        assert_eq!(transfer.timestamp, Some(timestamp));
+
+        let transfer = subscriber.receive().unwrap().unwrap();
+        assert_eq!(transfer.body, Telemetry{value: 4});
+        assert_eq!(transfer.transfer_id, TransferID::new(1));
+        assert_eq!(subscriber.receive(), None);
+    }
+
+    #[test]
+    fn storage_full() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Telemetry {
+            value: u8,
+        }
+
+        impl Message for Telemetry {
+            const TYPE_ID: Option<u16> = Some(20001);
+        }
+
+        ::static_storage_pool!(Pool: CanFrame, subscribers = 1, subscriber_frames = 2, interfaces = 1, interface_frames = 2);
+
+        let interface = TestInterface::default();
+        let config = NodeConfig{id: Some(NodeID::new(10)), drop_policy: DropPolicy::DropOldest, ..NodeConfig::default()};
+        let node: SimpleNode<TestInterface, &TestInterface, ::storage::StaticStorage<CanFrame, Pool>> = SimpleNode::new(&interface, config);
+        let subscriber = node.subscribe::<Telemetry>().unwrap();
+
+        let remote_interface = TestInterface::default();
+        let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        for value in 0..4 {
+            remote.broadcast(Telemetry{value}).unwrap();
+        }
+        remote.flush_transmissions();
+        remote_interface.transfer_to(&interface);
+        node.flush_receptions();
+
+        assert_eq!(subscriber.receive().unwrap().unwrap().body, Telemetry{value: 2});
+        assert_eq!(subscriber.receive().unwrap().unwrap().body, Telemetry{value: 3});
+        assert_eq!(subscriber.receive(), None);
+
+        // Transmissions never drop queued frames, the new transfer is dropped instead
+        node.broadcast(Telemetry{value: 0}).unwrap();
+        node.broadcast(Telemetry{value: 1}).unwrap();
+        assert_eq!(node.broadcast(Telemetry{value: 2}), Err(TransmitError::Storage(StorageError::OutOfSpace)));
+        node.flush_transmissions();
+        let sent: Vec<u8> = interface.tx.borrow_mut().drain(..).map(|x| x.data()[0]).collect();
+        assert_eq!(sent, vec![0, 1]);
+
+        // The rejected transfer didn't consume a transfer ID
+        node.broadcast(Telemetry{value: 3}).unwrap();
+        node.flush_transmissions();
+        let sent: Vec<TransferID> = interface.tx.borrow_mut().drain(..).map(|x| x.tail_byte().transfer_id()).collect();
+        assert_eq!(sent, vec![TransferID::new(2)]);
+
+        // The frames of a transfer are queued all or none
+        assert_eq!(node.broadcast(Large{a: 1, b: 2}), Err(TransmitError::Storage(StorageError::OutOfSpace)));
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+
+        let stats = node.transport_stats();
+        assert_eq!(stats.rx_frames_dropped, 2);
+        assert_eq!(stats.tx_frames_dropped, 4);
+        assert_eq!(stats.transfers_tx, 3);
+    }
+
+    #[test]
+    fn out_of_subscriber_slots() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Telemetry {
+            value: u8,
+        }
+
+        impl Message for Telemetry {
+            const TYPE_ID: Option<u16> = Some(20001);
+        }
+
+        ::static_storage_pool!(Pool: CanFrame, subscribers = 1, subscriber_frames = 1, interfaces = 1, interface_frames = 1);
+
+        let interface = TestInterface::default();
+        let config = NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()};
+        let node: SimpleNode<TestInterface, &TestInterface, ::storage::StaticStorage<CanFrame, Pool>> = SimpleNode::new(&interface, config);
+
+        let subscriber = node.subscribe::<Telemetry>().unwrap();
+        assert_eq!(node.subscribe::<Telemetry>().err(), Some(StorageError::OutOfSpace));
+        assert_eq!(node.subscribe_anonymous::<Telemetry>().err(), Some(StorageError::OutOfSpace));
+        assert_eq!(node.serve::<EchoRequest>().err(), Some(StorageError::OutOfSpace));
+        assert_eq!(node.request(NodeID::new(42), EchoRequest{value: 0}, Timestamp::from_micros(1000)).err(), Some(TransmitError::Storage(StorageError::OutOfSpace)));
+
+        drop(subscriber);
+        assert!(node.serve::<EchoRequest>().is_ok());
+    }
+
+    #[test]
+    fn rejected_request_keeps_transfer_id() {
+        ::static_storage_pool!(Pool: CanFrame, subscribers = 2, subscriber_frames = 1, interfaces = 1, interface_frames = 1);
+
+        let interface = TestInterface::default();
+        let config = NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()};
+        let node: SimpleNode<TestInterface, &TestInterface, ::storage::StaticStorage<CanFrame, Pool>> = SimpleNode::new(&interface, config);
+
+        let first = node.request(NodeID::new(42), EchoRequest{value: 0}, Timestamp::from_micros(1000)).unwrap();
+        assert_eq!(node.request(NodeID::new(42), EchoRequest{value: 1}, Timestamp::from_micros(1000)).err(), Some(TransmitError::Storage(StorageError::OutOfSpace)));
+        node.flush_transmissions();
+
+        // The rejected request neither consumed a transfer ID nor kept its response subscription
+        let second = node.request(NodeID::new(42), EchoRequest{value: 2}, Timestamp::from_micros(1000)).unwrap();
+        node.flush_transmissions();
+        assert_eq!(first.transfer_id(), TransferID::new(0));
+        assert_eq!(second.transfer_id(), TransferID::new(1));
+
+        let sent: Vec<TransferID> = interface.tx.borrow_mut().drain(..).map(|x| x.tail_byte().transfer_id()).collect();
+        assert_eq!(sent, vec![TransferID::new(0), TransferID::new(1)]);
+    }
+
+    #[test]
+    fn request_without_subscriber_slot_keeps_transfer_id() {
+        ::static_storage_pool!(Pool: CanFrame, subscribers = 1, subscriber_frames = 1, interfaces = 1, interface_frames = 1);
+
+        let interface = TestInterface::default();
+        let config = NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()};
+        let node: SimpleNode<TestInterface, &TestInterface, ::storage::StaticStorage<CanFrame, Pool>> = SimpleNode::new(&interface, config);
+
+        let server = node.serve::<EchoRequest>().unwrap();
+        assert_eq!(node.request(NodeID::new(42), EchoRequest{value: 0}, Timestamp::from_micros(1000)).err(), Some(TransmitError::Storage(StorageError::OutOfSpace)));
+
+        drop(server);
+        let request = node.request(NodeID::new(42), EchoRequest{value: 1}, Timestamp::from_micros(1000)).unwrap();
+        assert_eq!(request.transfer_id(), TransferID::new(0));
+    }
+
+    #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+    struct Large {
+        a: u64,
+        b: u64,
+    }
+
+    impl Message for Large {
+        const TYPE_ID: Option<u16> = Some(20002);
+    }
+
+    #[test]
+    fn partial_transfer_timeout() {
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe::<Large>().unwrap();
+
+        let remote_interface = TestInterface::default();
+        let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        remote.broadcast(Large{a: 1, b: 2}).unwrap();
+        remote.flush_transmissions();
+        let end_frame = remote_interface.tx.borrow_mut().pop_back().unwrap();
+        remote_interface.transfer_to(&interface);
+        node.flush_receptions();
+
+        assert_eq!(subscriber.receive(), None);
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(1_000_000)), None);
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(2_999_999)), None);
+        let error = subscriber.remove_timed_out(Timestamp::from_micros(3_000_000)).unwrap();
+        assert_eq!(error.error_code, ReceiveErrorCode::Timeout);
+        assert_eq!(error.transfer_id, TransferID::new(0));
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(3_000_000)), None);
+
+        // The end of the discarded transfer arrives late
+        interface.rx.borrow_mut().push_back(end_frame);
+        node.flush_receptions();
+        assert_eq!(subscriber.receive().unwrap().unwrap_err().error_code, ReceiveErrorCode::MissingStartFrame);
+        assert_eq!(subscriber.receive(), None);
+    }
+
+    #[test]
+    fn partial_transfer_timeout_uses_frame_timestamp() {
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe::<Large>().unwrap();
+
+        let remote_interface = TestInterface::default();
+        let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        remote.broadcast(Large{a: 1, b: 2}).unwrap();
+        remote.broadcast(Large{a: 3, b: 4}).unwrap();
+        remote.flush_transmissions();
+        remote_interface.tx.borrow_mut().pop_back().unwrap();
+
+        interface.rx_timestamp.set(Some(::time::FrameTimestamp::from_monotonic(Timestamp::from_micros(500_000))));
+        remote_interface.transfer_to(&interface);
+        node.flush_receptions();
+
+        // The complete transfer is never discarded
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(2_500_000)), None);
+        assert_eq!(subscriber.receive().unwrap().unwrap().body, Large{a: 1, b: 2});
+        assert_eq!(subscriber.receive(), None);
+
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(2_499_999)), None);
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(2_500_000)).unwrap().transfer_id, TransferID::new(1));
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(2_500_000)), None);
+    }
+
+    #[test]
+    fn partial_transfers_time_out_independently() {
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe::<Large>().unwrap();
+        let frames = |id: u8| {
+            let remote_interface = TestInterface::default();
+            let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig{id: Some(NodeID::new(id)), ..NodeConfig::default()});
+            remote.broadcast(Large{a: u64::from(id), b: 0}).unwrap();
+            remote.flush_transmissions();
+            remote_interface.tx.replace(Default::default())
+        };
+        let source = |error: ReceiveError| CanId::try_from(error.transfer_frame_id).unwrap().source();
+
+        // Each partial transfer is timed from when it was first seen
+        let mut first = frames(42);
+        first.pop_back();
+        interface.rx.borrow_mut().extend(first);
+        node.flush_receptions();
+        assert_eq!(subscriber.receive(), None);
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(0)), None);
+
+        let mut second = frames(43);
+        second.pop_back();
+        interface.rx.borrow_mut().extend(second);
+        node.flush_receptions();
+        assert_eq!(subscriber.receive(), None);
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(1_000_000)), None);
+
+        // A complete transfer doesn't hold back the timeouts, and continuation frames without a start are discarded
+        let mut orphan = frames(45);
+        orphan.pop_front();
+        interface.rx.borrow_mut().extend(frames(44));
+        interface.rx.borrow_mut().extend(orphan);
+        node.flush_receptions();
+        assert_eq!(subscriber.receive().unwrap().unwrap().body, Large{a: 44, b: 0});
+        assert_eq!(subscriber.receive().unwrap().unwrap_err().error_code, ReceiveErrorCode::MissingStartFrame);
+        assert_eq!(subscriber.receive(), None);
+        assert_eq!(subscriber.storage_handle.find_id(|_| true), None);
+
+        assert_eq!(source(subscriber.remove_timed_out(Timestamp::from_micros(2_000_000)).unwrap()), Some(NodeID::new(42)));
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(2_999_999)), None);
+        assert_eq!(source(subscriber.remove_timed_out(Timestamp::from_micros(3_000_000)).unwrap()), Some(NodeID::new(43)));
+        assert_eq!(subscriber.remove_timed_out(Timestamp::from_micros(3_000_000)), None);
+    }
+
+    #[test]
+    fn interleaved_transfers_are_received_by_priority() {
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe::<Large>().unwrap();
+
+        let mut transfers = Vec::new();
+        for (id, value) in [(42, 1), (43, 2)] {
+            let remote_interface = TestInterface::default();
+            let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig{id: Some(NodeID::new(id)), ..NodeConfig::default()});
+            remote.broadcast(Large{a: value, b: value}).unwrap();
+            remote.flush_transmissions();
+            transfers.push(remote_interface.tx.replace(Default::default()));
+        }
+        let end_frames: Vec<_> = transfers.iter_mut().map(|frames| frames.pop_back().unwrap()).collect();
+
+        // All but the end frames are taken out of storage and fed to the assemblers
+        for frames in transfers {
+            interface.rx.borrow_mut().extend(frames);
+        }
+        node.flush_receptions();
+        assert_eq!(subscriber.receive(), None);
+        assert_eq!(subscriber.storage_handle.find_id(|_| true), None);
+
+        // The transfer from node 42 has the higher priority, even though its end frame arrives last
+        interface.rx.borrow_mut().extend(end_frames.into_iter().rev());
+        node.flush_receptions();
+        let received = subscriber.receive().unwrap().unwrap();
+        assert_eq!(received.source, TransferSource::Node(NodeID::new(42)));
+        assert_eq!(received.body, Large{a: 1, b: 1});
+        assert_eq!(subscriber.receive().unwrap().unwrap().body, Large{a: 2, b: 2});
+        assert_eq!(subscriber.receive(), None);
+    }
+
+    #[test]
+    fn transfers_past_max_partial_transfers_are_counted() {
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe::<Large>().unwrap();
+
+        let mut end_frames = Vec::new();
+        for id in 0..MAX_PARTIAL_TRANSFERS as u8 + 1 {
+            let remote_interface = TestInterface::default();
+            let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig{id: Some(NodeID::new(20 + id)), ..NodeConfig::default()});
+            remote.broadcast(Large{a: u64::from(id), b: 0}).unwrap();
+            remote.flush_transmissions();
+            end_frames.push(remote_interface.tx.borrow_mut().pop_back().unwrap());
+            remote_interface.transfer_to(&interface);
+        }
+        node.flush_receptions();
+        assert_eq!(subscriber.receive(), None);
+        assert_eq!(subscriber.dropped_transfers(), 1);
+
+        // The transfer with the lowest priority didn't get a slot
+        interface.rx.borrow_mut().extend(end_frames);
+        node.flush_receptions();
+        for id in 0..MAX_PARTIAL_TRANSFERS as u64 {
+            assert_eq!(subscriber.receive().unwrap().unwrap().body, Large{a: id, b: 0});
+        }
+        assert_eq!(subscriber.receive().unwrap().unwrap_err().error_code, ReceiveErrorCode::MissingStartFrame);
+        assert_eq!(subscriber.receive(), None);
+    }
+
+    #[test]
+    fn nodes_are_sync() {
+        struct SyncInterface;
+
+        impl TransferInterface for SyncInterface {
+            type Frame = CanFrame;
+
+            fn transmit(&self, _frame: &CanFrame) -> Result<(), IOError> {
+                Ok(())
+            }
+
+            fn receive(&self) -> Option<CanFrame> {
+                None
+            }
+        }
+
+        fn assert_sync<T: Sync>(_: &T) {}
+
+        let interfaces = [SyncInterface, SyncInterface];
+        let node: SimpleNode<SyncInterface, &SyncInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interfaces[0], NodeConfig::default());
+        assert_sync(&node);
+        let node: MultiInterfaceNode<SyncInterface, &SyncInterface, HeapStorage<CanFrame>, 2> = MultiInterfaceNode::new([&interfaces[0], &interfaces[1]], NodeConfig::default());
+        assert_sync(&node);
+    }
+
+    #[test]
+    fn multi_interface_node() {
+        let interfaces = [TestInterface::default(), TestInterface::default()];
+        let node: MultiInterfaceNode<TestInterface, &TestInterface, HeapStorage<CanFrame>, 2> = MultiInterfaceNode::new([&interfaces[0], &interfaces[1]], NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe::<Large>().unwrap();
+
+        // Transmissions go out on all interfaces
+        node.broadcast(Large{a: 1, b: 2}).unwrap();
+        node.flush_transmissions();
+        assert_eq!(interfaces[0].tx.borrow().len(), 3);
+        assert_eq!(*interfaces[0].tx.borrow(), *interfaces[1].tx.borrow());
+
+        // Receptions are only delivered once
+        let remote_interface = TestInterface::default();
+        let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        remote.broadcast(Large{a: 3, b: 4}).unwrap();
+        remote.flush_transmissions();
+        interfaces[1].rx.borrow_mut().extend(remote_interface.tx.borrow().iter().cloned());
+        remote_interface.transfer_to(&interfaces[0]);
+        node.flush_receptions(Timestamp::from_micros(0));
+
+        assert_eq!(subscriber.receive().unwrap().unwrap().body, Large{a: 3, b: 4});
+        assert_eq!(subscriber.receive(), None);
+    }
+
+    #[test]
+    fn multi_interface_more_sources_than_filter_capacity() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Telemetry {
+            value: u8,
+        }
+
+        impl Message for Telemetry {
+            const TYPE_ID: Option<u16> = Some(20001);
+        }
+
+        let interfaces = [TestInterface::default(), TestInterface::default()];
+        let node: MultiInterfaceNode<TestInterface, &TestInterface, HeapStorage<CanFrame>, 2> = MultiInterfaceNode::new([&interfaces[0], &interfaces[1]], NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe::<Telemetry>().unwrap();
+
+        let remote_interface = TestInterface::default();
+        let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig::default());
+        let sources = ::redundancy::REDUNDANCY_FILTER_CAPACITY as u8 + 1;
+        for source in 0..sources {
+            remote.set_id(NodeID::new(20 + source));
+            remote.broadcast(Telemetry{value: source}).unwrap();
+        }
+        remote.flush_transmissions();
+        interfaces[1].rx.borrow_mut().extend(remote_interface.tx.borrow().iter().cloned());
+        remote_interface.transfer_to(&interfaces[0]);
+        node.flush_receptions(Timestamp::from_micros(0));
+
+        for _ in 0..sources {
+            assert!(subscriber.receive().unwrap().is_ok());
+        }
+        assert_eq!(subscriber.receive(), None);
+    }
+
+    #[test]
+    fn multi_interface_dead_bus() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Telemetry {
+            value: u8,
+        }
+
+        impl Message for Telemetry {
+            const TYPE_ID: Option<u16> = Some(20001);
+        }
+
+        ::static_storage_pool!(Pool: CanFrame, subscribers = 1, subscriber_frames = 1, interfaces = 2, interface_frames = 4);
+
+        let interfaces = [TestInterface::default(), TestInterface::default()];
+        interfaces[1].tx_capacity.set(Some(0));
+        let node: MultiInterfaceNode<TestInterface, &TestInterface, ::storage::StaticStorage<CanFrame, Pool>, 2> = MultiInterfaceNode::new([&interfaces[0], &interfaces[1]], NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+
+        // The queue of the dead bus fills up while the healthy bus transmits every transfer
+        for value in 0..10 {
+            node.broadcast(Telemetry{value}).unwrap();
+            node.flush_transmissions();
+        }
+        node.broadcast(Large{a: 1, b: 2}).unwrap();
+        node.flush_transmissions();
+        let sent: Vec<u8> = interfaces[0].tx.borrow_mut().drain(..10).map(|x| x.data()[0]).collect();
+        assert_eq!(sent, (0..10).collect::<Vec<u8>>());
+        assert_eq!(interfaces[0].tx.borrow().len(), 3);
+        assert!(interfaces[1].tx.borrow().is_empty());
+        assert_eq!(node.transport_stats().tx_frames_dropped, 6 + 3);
+
+        // When the bus recovers it transmits the transfers it could hold, none of them truncated
+        interfaces[1].tx_capacity.set(None);
+        node.flush_transmissions();
+        let sent: Vec<u8> = interfaces[1].tx.borrow().iter().map(|x| x.data()[0]).collect();
+        assert_eq!(sent, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn anonymous_broadcast() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Small {
+            value: u32,
+        }
+
+        impl Message for Small {
+            const TYPE_ID: Option<u16> = Some(1);
+        }
+        
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig::default());
+
+        node.broadcast(Small{value: 0x0403_0201}).unwrap();
+        node.flush_transmissions();
+
+        let frame = interface.tx.borrow_mut().pop_front().unwrap();
+        assert_eq!(frame.data(), &[1, 2, 3, 4, TailByte::new(true, true, false, TransferID::new(0)).into()]);
+
+        let mut crc = TransferCRC::from(0xffff);
+        crc.add(&[1, 2, 3, 4]);
+        let id = u32::from(frame.id());
+        assert_eq!(id.get_bits(0..8), 0);
+        assert_eq!(id.get_bits(8..10), 1);
+        assert_eq!(id.get_bits(10..24), u32::from(u16::from(crc) >> 1) & 0x3fff);
+        assert_eq!(id.get_bits(24..29), u32::from(TransferPriority::MEDIUM));
+    }
+
+    #[test]
+    fn subscribe_matches_full_type_id() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Telemetry {
+            value: u8,
+        }
+
+        impl Message for Telemetry {
+            const TYPE_ID: Option<u16> = Some(20001);
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Other {
+            value: u8,
+        }
+
+        impl Message for Other {
+            // Only differs from `Telemetry` in the upper byte
+            const TYPE_ID: Option<u16> = Some(20001 + 0x100);
+        }
+
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe::<Telemetry>().unwrap();
+
+        let remote_interface = TestInterface::default();
+        let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        remote.broadcast(Other{value: 1}).unwrap();
+        remote.flush_transmissions();
+        remote_interface.transfer_to(&interface);
+        node.flush_receptions();
+
+        assert_eq!(subscriber.receive(), None);
+    }
+
+    #[test]
+    fn receive_anonymous() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Small {
+            value: u32,
+        }
+
+        impl Message for Small {
+            const TYPE_ID: Option<u16> = Some(5);
+        }
+
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe_anonymous::<Small>().unwrap();
+
+        let remote_interface = TestInterface::default();
+        let remote: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&remote_interface, NodeConfig::default());
+        remote.broadcast(Small{value: 0x0403_0201}).unwrap();
+        remote.flush_transmissions();
+        remote_interface.transfer_to(&interface);
+        node.flush_receptions();
+
+        let mut crc = TransferCRC::from(0xffff);
+        crc.add(&[1, 2, 3, 4]);
+
+        let transfer = subscriber.receive().unwrap().unwrap();
+        assert_eq!(transfer.body, Small{value: 0x0403_0201});
+        assert_eq!(transfer.source, TransferSource::Anonymous(u14::new((u16::from(crc) >> 1) & 0x3fff)));
+        assert_eq!(transfer.source.node_id(), None);
+        assert_eq!(transfer.type_id, 5);
+    }
+
+    #[test]
+    fn subscribe_discards_anonymous_messages() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Telemetry {
+            value: u8,
+        }
+
+        impl Message for Telemetry {
+            const TYPE_ID: Option<u16> = Some(20001);
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Small {
+            value: u8,
+        }
+
+        impl Message for Small {
+            const TYPE_ID: Option<u16> = Some(1);
+        }
+
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+        let subscriber = node.subscribe::<Telemetry>().unwrap();
+        let anonymous_subscriber = node.subscribe_anonymous::<Small>().unwrap();
+
+        // The discriminator completes the data type ID of `Telemetry`
+        let frame = Frame::from_anonymous_message(Small{value: 1}, TransferPriority::MEDIUM, u14::new(20001 >> 2));
+        let frame: CanFrame = FrameDisassembler::from_uavcan_frame(frame, TransferID::new(0)).next_transfer_frame().unwrap();
+        assert!(CanId::message_filter(20001).is_match(frame.id()));
+        interface.rx.borrow_mut().push_back(frame);
+        node.flush_receptions();
+
+        assert_eq!(subscriber.receive(), None);
+        assert_eq!(anonymous_subscriber.receive().unwrap().unwrap().body, Small{value: 1});
+    }
+
+    #[test]
+    fn anonymous_multi_frame_broadcast() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Large {
+            value: u64,
+        }
+
+        impl Message for Large {
+            const TYPE_ID: Option<u16> = Some(1);
+        }
+
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Text {
+            text: Dynamic<[u8; 16]>,
+        }
+
+        impl Message for Text {
+            const TYPE_ID: Option<u16> = Some(2);
+        }
+        
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig::default());
+
+        assert_eq!(node.broadcast(Large{value: 0}), Err(TransmitError::AnonymousMultiFrameTransfer));
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+
+        // The rejected transfer doesn't consume a transfer ID
+        assert_eq!(node.broadcast(Text{text: Dynamic::<[u8; 16]>::with_data(b"multi frame")}), Err(TransmitError::AnonymousMultiFrameTransfer));
+        node.broadcast(Text{text: Dynamic::<[u8; 16]>::with_data(b"short")}).unwrap();
+        node.flush_transmissions();
+        assert_eq!(interface.tx.borrow_mut().pop_front().unwrap().tail_byte().transfer_id(), TransferID::new(0));
+    }
+
+    #[test]
+    fn failed_transmissions_keep_frame_order() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Large {
+            a: u64,
+            b: u64,
+        }
+
+        impl Message for Large {
+            const TYPE_ID: Option<u16> = Some(20002);
+        }
+
+        let reference_interface = TestInterface::default();
+        let reference: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&reference_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        reference.broadcast(Large{a: 1, b: 2}).unwrap();
+        reference.flush_transmissions();
+
+        // Only one frame fits in the transmit buffer, so every flush ends with a failed transmission
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        interface.tx_capacity.set(Some(1));
+        node.broadcast(Large{a: 1, b: 2}).unwrap();
+        let mut sent = Vec::new();
+        loop {
+            node.flush_transmissions();
+            if interface.tx.borrow().is_empty() {
+                break;
+            }
+            sent.extend(interface.tx.borrow_mut().drain(..));
+        }
+
+        assert_eq!(sent, reference_interface.tx.borrow().iter().cloned().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn anonymous_request() {
+        let interface = TestInterface::default();
+        let node: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&interface, NodeConfig::default());
+
+        assert_eq!(node.request(NodeID::new(42), EchoRequest{value: 7}, Timestamp::from_micros(1000)).err(), Some(TransmitError::AnonymousServiceTransfer));
+    }
+
+    #[test]
+    fn full_node_get_node_info() {
+        use protocol::{
+            GetNodeInfoRequest,
+            Health,
+        };
+
+        let interface = TestInterface::default();
+        let config = NodeConfig {
+            id: Some(NodeID::new(10)),
+            name: "org.uavcan.test",
+            software_version: Version{major: 1, minor: 2},
+            unique_id: [7; 16],
+            ..NodeConfig::default()
+        };
+        let node: FullNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = FullNode::new(&interface, config, Timestamp::from_micros(0)).unwrap();
+        node.status_publisher().set_health(Health::Warning);
+
+        let client_interface = TestInterface::default();
+        let client: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&client_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        let handle = client.request(NodeID::new(10), GetNodeInfoRequest{}, Timestamp::from_micros(1_000_000)).unwrap();
+        client.flush_transmissions();
+        client_interface.transfer_to(&interface);
+
+        node.flush_receptions();
+        node.tick(Timestamp::from_micros(3_000_000)).unwrap();
+        node.flush_transmissions();
+        interface.transfer_to(&client_interface);
+        client.flush_receptions();
+
+        let response = handle.receive(Timestamp::from_micros(0)).unwrap().unwrap();
+        assert_eq!(Health::from(response.status.health), Health::Warning);
+        assert_eq!(response.status.uptime_sec, 3);
+        assert_eq!(response.software_version.major, 1);
+        assert_eq!(response.software_version.minor, 2);
+        assert_eq!(response.hardware_version.unique_id, [7; 16]);
+        assert_eq!(response.name.as_ref(), b"org.uavcan.test");
+    }
+
+    #[test]
+    fn full_node_set_id_in_tight_storage() {
+        use protocol::GetNodeInfoRequest;
+
+        // Exactly the subscriber slots needed by the standard servers of one `FullNode`
+        ::static_storage_pool!(Pool: CanFrame, subscribers = 4, subscriber_frames = 2, interfaces = 1, interface_frames = 16);
+
+        let interface = TestInterface::default();
+        let node: FullNode<TestInterface, &TestInterface, ::storage::StaticStorage<CanFrame, Pool>> = FullNode::new(&interface, NodeConfig::default(), Timestamp::from_micros(0)).unwrap();
+        node.set_id(NodeID::new(10));
+
+        let client_interface = TestInterface::default();
+        let client: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&client_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        let handle = client.request(NodeID::new(10), GetNodeInfoRequest{}, Timestamp::from_micros(1_000_000)).unwrap();
+        client.flush_transmissions();
+        client_interface.transfer_to(&interface);
+
+        node.flush_receptions();
+        node.tick(Timestamp::from_micros(0)).unwrap();
+        node.flush_transmissions();
+        interface.transfer_to(&client_interface);
+        client.flush_receptions();
+
+        assert!(handle.receive(Timestamp::from_micros(0)).unwrap().is_ok());
+    }
+
+    #[test]
+    fn full_node_get_data_type_info() {
+        use protocol::{
+            GetDataTypeInfoRequest,
+            GetDataTypeInfoResponse,
+            DataTypeKind,
+        };
+
+        let interface = TestInterface::default();
+        let node: FullNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = FullNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()}, Timestamp::from_micros(0)).unwrap();
+
+        let client_interface = TestInterface::default();
+        let client: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&client_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        let by_id = client.request(NodeID::new(10), GetDataTypeInfoRequest{id: 341, kind: DataTypeKind{value: DataTypeKind::MESSAGE}, name: Dynamic::<[u8; 80]>::new()}, Timestamp::from_micros(1_000_000)).unwrap();
+        let by_name = client.request(NodeID::new(10), GetDataTypeInfoRequest{id: 0, kind: DataTypeKind{value: DataTypeKind::SERVICE}, name: Dynamic::<[u8; 80]>::with_data(b"uavcan.protocol.RestartNode")}, Timestamp::from_micros(1_000_000)).unwrap();
+        client.flush_transmissions();
+        client_interface.transfer_to(&interface);
+
+        node.flush_receptions();
+        node.tick(Timestamp::from_micros(0)).unwrap();
+        node.flush_transmissions();
+        interface.transfer_to(&client_interface);
+        client.flush_receptions();
+
+        let response = by_id.receive(Timestamp::from_micros(0)).unwrap().unwrap();
+        assert_eq!(response.name.as_ref(), b"uavcan.protocol.NodeStatus");
+        assert_eq!(response.signature, 0x0f08_68d0_c1a7_c6f1);
+        assert_eq!(response.flags, GetDataTypeInfoResponse::FLAG_KNOWN | GetDataTypeInfoResponse::FLAG_PUBLISHING);
+
+        let response = by_name.receive(Timestamp::from_micros(0)).unwrap().unwrap();
+        assert_eq!(response.id, 5);
+        assert_eq!(response.kind.value, DataTypeKind::SERVICE);
+        assert_eq!(response.flags, GetDataTypeInfoResponse::FLAG_KNOWN | GetDataTypeInfoResponse::FLAG_SERVING);
+    }
+
+    #[test]
+    fn full_node_restart_and_transport_stats() {
+        use protocol::{
+            RestartNodeRequest,
+            GetTransportStatsRequest,
+        };
+
+        let interface = TestInterface::default();
+        let node: FullNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = FullNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()}, Timestamp::from_micros(0)).unwrap();
+
+        let client_interface = TestInterface::default();
+        let client: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&client_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        let wrong_magic = client.request(NodeID::new(10), RestartNodeRequest{magic_number: types::u40::new(0x1234)}, Timestamp::from_micros(1_000_000)).unwrap();
+        client.flush_transmissions();
+        client_interface.transfer_to(&interface);
+
+        node.flush_receptions();
+        node.tick(Timestamp::from_micros(0)).unwrap();
+        node.flush_transmissions();
+        interface.transfer_to(&client_interface);
+        client.flush_receptions();
+
+        assert!(!wrong_magic.receive(Timestamp::from_micros(0)).unwrap().unwrap().ok);
+        assert!(!node.restart_requested());
+
+        let restart = client.request(NodeID::new(10), RestartNodeRequest{magic_number: types::u40::new(RestartNodeRequest::MAGIC_NUMBER)}, Timestamp::from_micros(1_000_000)).unwrap();
+        let stats = client.request(NodeID::new(10), GetTransportStatsRequest{}, Timestamp::from_micros(1_000_000)).unwrap();
+        client.flush_transmissions();
+        client_interface.transfer_to(&interface);
+
+        node.flush_receptions();
+        node.tick(Timestamp::from_micros(0)).unwrap();
+        node.flush_transmissions();
+        interface.transfer_to(&client_interface);
+        client.flush_receptions();
+
+        assert!(restart.receive(Timestamp::from_micros(0)).unwrap().unwrap().ok);
+        assert!(node.restart_requested());
+
+        // Status and wrong magic response from the first tick, restart response from the second
+        let stats = stats.receive(Timestamp::from_micros(0)).unwrap().unwrap();
+        assert_eq!(u64::from(stats.transfers_rx), 3);
+        assert_eq!(u64::from(stats.transfers_tx), 3);
+        assert_eq!(stats.can_iface_stats.length(), 1);
+        assert_eq!(u64::from(stats.can_iface_stats[0].frames_rx), 3);
+    }
+
+    #[test]
+    fn full_node_counts_receive_errors() {
+        use protocol::RestartNodeRequest;
+
+        let interface = TestInterface::default();
+        let node: FullNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = FullNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()}, Timestamp::from_micros(0)).unwrap();
+
+        let client_interface = TestInterface::default();
+        let client: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&client_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        client.request(NodeID::new(10), RestartNodeRequest{magic_number: types::u40::new(RestartNodeRequest::MAGIC_NUMBER)}, Timestamp::from_micros(1_000_000)).unwrap();
+        client.flush_transmissions();
+
+        // Clearing the start of transfer bit leaves an end frame without a start frame
+        {
+            let mut tx = client_interface.tx.borrow_mut();
+            let frame = tx.back_mut().unwrap();
+            let tail_byte_index = frame.data().len() - 1;
+            frame.data_as_mut()[tail_byte_index] &= !0x80;
+        }
+        client_interface.transfer_to(&interface);
+
+        node.flush_receptions();
+        node.tick(Timestamp::from_micros(0)).unwrap();
+
+        assert!(!node.restart_requested());
+        assert_eq!(node.transport_stats().transfer_errors, 1);
+    }
+
+    #[test]
+    fn full_node_tick_continues_after_full_queue() {
+        use protocol::RestartNodeRequest;
+
+        ::static_storage_pool!(Pool: CanFrame, subscribers = 4, subscriber_frames = 4, interfaces = 1, interface_frames = 1);
+
+        let interface = TestInterface::default();
+        let node: FullNode<TestInterface, &TestInterface, ::storage::StaticStorage<CanFrame, Pool>> = FullNode::new(&interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()}, Timestamp::from_micros(0)).unwrap();
+
+        // The first status fills the interface queue
+        node.tick(Timestamp::from_micros(0)).unwrap();
+
+        let client_interface = TestInterface::default();
+        let client: SimpleNode<TestInterface, &TestInterface, HeapStorage<CanFrame>> = SimpleNode::new(&client_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        client.request(NodeID::new(10), RestartNodeRequest{magic_number: types::u40::new(RestartNodeRequest::MAGIC_NUMBER)}, Timestamp::from_micros(1_000_000)).unwrap();
+        client.flush_transmissions();
+        client_interface.transfer_to(&interface);
+
+        // The next status is dropped, but the restart request is still handled
+        node.flush_receptions();
+        assert_eq!(node.tick(Timestamp::from_micros(1_000_000)), Err(TransmitError::Storage(StorageError::OutOfSpace)));
+        assert!(node.restart_requested());
+        assert_eq!(node.transport_stats().tx_frames_dropped, 2);
     }
 }
@@ -0,0 +1,323 @@
+use lib::core::cmp;
+
+use {
+    Node,
+    NodeID,
+};
+
+use node::{
+    Subscriber,
+    TransmitError,
+};
+
+use storage::{
+    Storage,
+    StorageError,
+    SubscriberStorageHandle,
+};
+
+use transfer::{
+    TransferInterface,
+    TransferFrame,
+};
+
+use time::{
+    Duration,
+    Timestamp,
+};
+
+use types::*;
+
+use super::{
+    Allocation,
+    Random,
+};
+
+/// The allocatee side of the dynamic node ID allocation protocol.
+///
+/// The client broadcasts the unique ID of the node anonymously until an allocator assigns it a `NodeID`.
+/// When the allocation completes, the `NodeID` of the node is updated by `Node::set_id`.
+///
+/// The client doesn't have any notion of time by itself.
+/// `tick` must be called periodically (preferably more often than every 10ms) and after receptions are flushed.
+///
+/// The randomized request and follow-up delays required by the specification are derived from the unique ID,
+/// which makes it unlikely that two allocatees use the same delays.
+#[derive(Debug)]
+pub struct AllocationClient<F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    subscriber: Subscriber<Allocation, F, H>,
+    anonymous_subscriber: Subscriber<Allocation, F, H>,
+    unique_id: [u8; 16],
+    preferred_id: Option<NodeID>,
+    random: Random,
+    request_time: Timestamp,
+    followup: Option<(Timestamp, Timestamp, usize)>,
+    allocated_id: Option<NodeID>,
+}
+
+impl<F: TransferFrame, H: SubscriberStorageHandle<F>> AllocationClient<F, H> {
+    /// Creates a new allocation client and subscribes to `Allocation` messages through `node`.
+    ///
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage of `node` can't hold the subscriptions.
+    pub fn new<I, S, N>(node: &N, unique_id: [u8; 16], preferred_id: Option<NodeID>, now: Timestamp) -> Result<Self, StorageError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        let mut client = AllocationClient {
+            subscriber: node.subscribe()?,
+            anonymous_subscriber: node.subscribe_anonymous()?,
+            unique_id,
+            preferred_id,
+            random: Random::new(&unique_id),
+            request_time: now,
+            followup: None,
+            allocated_id: None,
+        };
+        client.restart_request_timer(now);
+        Ok(client)
+    }
+
+    /// Returns the allocated `NodeID` or `None` if the allocation is not completed yet.
+    pub fn allocated_id(&self) -> Option<NodeID> {
+        self.allocated_id
+    }
+
+    /// Processes received `Allocation` messages and broadcasts requests when it's time to do so.
+    ///
+    /// Returns the allocated `NodeID` when the allocation is completed.
+    pub fn tick<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<Option<NodeID>, TransmitError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        if self.allocated_id.is_some() {
+            return Ok(self.allocated_id);
+        }
+
+        // Requests from other allocatees
+        while let Some(result) = self.anonymous_subscriber.receive() {
+            if result.is_ok() {
+                self.restart_request_timer(now);
+            }
+        }
+
+        // Responses from allocators
+        while let Some(result) = self.subscriber.receive() {
+            let transfer = match result {
+                Ok(transfer) => transfer,
+                Err(_) => continue,
+            };
+
+            if transfer.source.node_id().is_none() {
+                // Anonymous transfers are received by the anonymous subscriber
+                continue;
+            }
+
+            self.restart_request_timer(now);
+            self.followup = None;
+
+            let received = transfer.body.unique_id.as_ref();
+            if received.is_empty() || received.len() > self.unique_id.len() || received != &self.unique_id[0..received.len()] {
+                continue;
+            }
+
+            if received.len() == self.unique_id.len() {
+                let id = u8::from(transfer.body.node_id);
+                if id != 0 {
+                    node.set_id(NodeID::new(id));
+                    self.allocated_id = Some(NodeID::new(id));
+                    return Ok(self.allocated_id);
+                }
+            } else {
+                let delay = self.random.millis(Allocation::MIN_FOLLOWUP_DELAY_MS, Allocation::MAX_FOLLOWUP_DELAY_MS);
+                self.followup = Some((now + delay, now, received.len()));
+            }
+        }
+
+        match self.followup {
+            Some((time, response_time, offset)) if now >= time => {
+                self.followup = None;
+                // A follow-up sent too late, e.g. because `tick` was delayed, is rejected by the allocators
+                if now.duration_since(response_time) <= Duration::from_millis(u64::from(Allocation::FOLLOWUP_TIMEOUT_MS)) {
+                    self.send_request(node, offset)?;
+                }
+            },
+            _ => if now >= self.request_time {
+                self.restart_request_timer(now);
+                self.send_request(node, 0)?;
+            },
+        }
+
+        Ok(None)
+    }
+
+    fn send_request<I, S, N>(&self, node: &N, offset: usize) -> Result<(), TransmitError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        let end = cmp::min(offset + Allocation::MAX_LENGTH_OF_UNIQUE_ID_IN_REQUEST as usize, self.unique_id.len());
+
+        node.broadcast(Allocation {
+            node_id: u7::new(self.preferred_id.map(u8::from).unwrap_or(Allocation::ANY_NODE_ID)),
+            first_part_of_unique_id: offset == 0,
+            unique_id: Dynamic::<[u8; 16]>::with_data(&self.unique_id[offset..end]),
+        })
+    }
+
+    fn restart_request_timer(&mut self, now: Timestamp) {
+        let period = self.random.millis(Allocation::MIN_REQUEST_PERIOD_MS, Allocation::MAX_REQUEST_PERIOD_MS);
+        self.request_time = now + period;
+    }
+
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use node::*;
+    use time::Timestamp;
+    use types::*;
+    use transfer::TransferFrame;
+
+    use tests::{
+        TestInterface,
+        TestNode,
+        test_node,
+        exchange,
+    };
+
+    use protocol::dynamic_node_id::*;
+
+    const UNIQUE_ID: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
+    fn respond(allocator: &TestNode, unique_id: &[u8], node_id: u8) {
+        allocator.broadcast(Allocation {
+            node_id: u7::new(node_id),
+            first_part_of_unique_id: false,
+            unique_id: Dynamic::<[u8; 16]>::with_data(unique_id),
+        }).unwrap();
+    }
+
+    fn transferred_request(interface: &TestInterface) -> Allocation {
+        let frame = interface.tx.borrow_mut().pop_front().unwrap();
+        assert!(interface.tx.borrow().is_empty());
+        assert!(frame.is_single_frame());
+        assert_eq!(u32::from(frame.id()) & 0xff, 0);
+
+        let mut assembler = ::frame_assembler::FrameAssembler::new();
+        assembler.add_transfer_frame(frame).unwrap();
+        assembler.build().unwrap().body
+    }
+
+    #[test]
+    fn allocation() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, None);
+
+        let allocator_interface = TestInterface::default();
+        let allocator = test_node(&allocator_interface, Some(1));
+
+        let mut client = AllocationClient::new(&node, UNIQUE_ID, Some(NodeID::new(42)), Timestamp::from_micros(0)).unwrap();
+
+        // No requests are sent before the request timer expires
+        assert_eq!(client.tick(&node, Timestamp::from_micros(599_000)), Ok(None));
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_000_000)), Ok(None));
+        node.flush_transmissions();
+        let request = transferred_request(&interface);
+        assert_eq!(request.node_id, u7::new(42));
+        assert!(request.first_part_of_unique_id);
+        assert_eq!(request.unique_id.as_ref(), &UNIQUE_ID[0..6]);
+
+        respond(&allocator, &UNIQUE_ID[0..6], 0);
+        exchange(&[(&allocator, &allocator_interface), (&node, &interface)]);
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_000_000)), Ok(None));
+
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_400_000)), Ok(None));
+        node.flush_transmissions();
+        let request = transferred_request(&interface);
+        assert!(!request.first_part_of_unique_id);
+        assert_eq!(request.unique_id.as_ref(), &UNIQUE_ID[6..12]);
+
+        respond(&allocator, &UNIQUE_ID[0..12], 0);
+        exchange(&[(&allocator, &allocator_interface), (&node, &interface)]);
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_400_000)), Ok(None));
+
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_800_000)), Ok(None));
+        node.flush_transmissions();
+        let request = transferred_request(&interface);
+        assert!(!request.first_part_of_unique_id);
+        assert_eq!(request.unique_id.as_ref(), &UNIQUE_ID[12..16]);
+
+        respond(&allocator, &UNIQUE_ID, 42);
+        exchange(&[(&allocator, &allocator_interface), (&node, &interface)]);
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_800_000)), Ok(Some(NodeID::new(42))));
+        assert_eq!(node.id(), Some(NodeID::new(42)));
+        assert_eq!(client.allocated_id(), Some(NodeID::new(42)));
+    }
+
+    #[test]
+    fn mismatching_response_restarts() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, None);
+
+        let allocator_interface = TestInterface::default();
+        let allocator = test_node(&allocator_interface, Some(1));
+
+        let mut client = AllocationClient::new(&node, UNIQUE_ID, None, Timestamp::from_micros(0)).unwrap();
+
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_000_000)), Ok(None));
+        node.flush_transmissions();
+        assert_eq!(transferred_request(&interface).node_id, u7::new(0));
+
+        // Response to another allocatee
+        respond(&allocator, &[9, 9, 9, 9, 9, 9], 0);
+        exchange(&[(&allocator, &allocator_interface), (&node, &interface)]);
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_000_000)), Ok(None));
+
+        // No follow up is sent, the next request is a first stage request after the request timer expires
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_400_000)), Ok(None));
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+
+        assert_eq!(client.tick(&node, Timestamp::from_micros(2_000_000)), Ok(None));
+        node.flush_transmissions();
+        let request = transferred_request(&interface);
+        assert!(request.first_part_of_unique_id);
+        assert_eq!(request.unique_id.as_ref(), &UNIQUE_ID[0..6]);
+        assert_eq!(node.id(), None);
+    }
+
+    #[test]
+    fn late_followup_is_dropped() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, None);
+
+        let allocator_interface = TestInterface::default();
+        let allocator = test_node(&allocator_interface, Some(1));
+
+        let mut client = AllocationClient::new(&node, UNIQUE_ID, None, Timestamp::from_micros(0)).unwrap();
+
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_000_000)), Ok(None));
+        node.flush_transmissions();
+        transferred_request(&interface);
+
+        respond(&allocator, &UNIQUE_ID[0..6], 0);
+        exchange(&[(&allocator, &allocator_interface), (&node, &interface)]);
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_000_000)), Ok(None));
+
+        // The allocator has given up on the allocation when the follow-up is due, so it's not sent
+        assert_eq!(client.tick(&node, Timestamp::from_micros(1_550_000)), Ok(None));
+        node.flush_transmissions();
+        assert!(interface.tx.borrow().is_empty());
+
+        assert_eq!(client.tick(&node, Timestamp::from_micros(2_000_000)), Ok(None));
+        node.flush_transmissions();
+        assert!(transferred_request(&interface).first_part_of_unique_id);
+    }
+}
@@ -0,0 +1,83 @@
+//! Dynamic node ID allocation
+//!
+//! Nodes without a `NodeID` can request one from an allocator on the network.
+//! The allocation procedure is described in detail in the documentation of `Allocation`.
+
+mod client;
+pub mod server;
+
+pub use self::client::AllocationClient;
+
+use time::Duration;
+
+use types::*;
+
+/// This message is used for dynamic node ID allocation, `uavcan.protocol.dynamic_node_id.Allocation`.
+///
+/// An anonymous node (the allocatee) broadcasts its unique ID in chunks of at most
+/// `MAX_LENGTH_OF_UNIQUE_ID_IN_REQUEST` bytes. The allocator repeats back the part of the unique ID
+/// it has received so far, and when the full unique ID have been received it responds with the allocated node ID.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x0b2a812620a11d40"]
+#[DataTypeSignature = "0x0b2a812620a11d40"]
+pub struct Allocation {
+    /// The allocated node ID when sent by the allocator, or the preferred node ID when sent by the allocatee.
+    ///
+    /// Zero means "any node ID" in requests, and is not a valid value in responses.
+    pub node_id: u7,
+    
+    /// Set when the allocatee sends the first part of its unique ID, always cleared when sent by the allocator.
+    pub first_part_of_unique_id: bool,
+    
+    /// A part of the unique ID of the allocatee.
+    pub unique_id: Dynamic<[u8; 16]>,
+}
+
+impl Allocation {
+    pub const MAX_REQUEST_PERIOD_MS: u16 = 1000;
+    pub const MIN_REQUEST_PERIOD_MS: u16 = 600;
+    
+    pub const MAX_FOLLOWUP_DELAY_MS: u16 = 400;
+    pub const MIN_FOLLOWUP_DELAY_MS: u16 = 0;
+
+    pub const FOLLOWUP_TIMEOUT_MS: u16 = 500;
+
+    pub const MAX_LENGTH_OF_UNIQUE_ID_IN_REQUEST: u8 = 6;
+
+    pub const ANY_NODE_ID: u8 = 0;
+}
+
+impl ::Message for Allocation {
+    const TYPE_ID: Option<u16> = Some(1);
+}
+
+/// A pseudo random generator (xorshift32) for the randomized timing required by the allocation protocols.
+///
+/// The generator is seeded from the unique ID of the node,
+/// which makes it unlikely that two nodes use the same sequence of delays.
+#[derive(Debug)]
+struct Random(u32);
+
+impl Random {
+    fn new(unique_id: &[u8; 16]) -> Self {
+        // FNV-1a hash of the unique ID, a zero seed would make the generator stuck at zero
+        let mut state = 0x811c_9dc5u32;
+        for byte in unique_id.iter() {
+            state = (state ^ u32::from(*byte)).wrapping_mul(0x0100_0193);
+        }
+        if state == 0 {
+            state = 1;
+        }
+        Random(state)
+    }
+
+    /// Returns a pseudo random duration in the interval `[min, max]` milliseconds.
+    fn millis(&mut self, min: u16, max: u16) -> Duration {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+
+        let range = u32::from(max - min) + 1;
+        Duration::from_millis(u64::from(u32::from(min) + self.0 % range))
+    }
+}
@@ -0,0 +1,271 @@
+use {
+    Node,
+    NodeID,
+};
+
+use node::Subscriber;
+
+use storage::{
+    Storage,
+    StorageError,
+    SubscriberStorageHandle,
+};
+
+use transfer::{
+    TransferInterface,
+    TransferFrame,
+};
+
+use time::Timestamp;
+
+use super::{
+    RequestTracker,
+    RequestStatus,
+    ServerError,
+    allocation_response,
+    find_free_node_id,
+};
+
+use super::super::Allocation;
+
+use protocol::node_monitor::NodeMonitor;
+
+/// The persistent allocation table of a `CentralizedServer`.
+///
+/// Implement this trait to store the allocation table in non-volatile memory.
+/// An allocation must never be forgotten, as that could lead to the same node ID being allocated twice.
+pub trait AllocationTable {
+    /// Returns the `NodeID` allocated to the node with `unique_id`.
+    fn node_id(&self, unique_id: &[u8; 16]) -> Option<NodeID>;
+
+    /// Returns true if `node_id` is allocated to a node.
+    fn is_allocated(&self, node_id: NodeID) -> bool;
+
+    /// Adds an allocation to the table.
+    fn insert(&mut self, unique_id: [u8; 16], node_id: NodeID) -> Result<(), StorageError>;
+}
+
+/// An `AllocationTable` kept in memory.
+///
+/// The table is lost when the allocator restarts, and should only be used when that's acceptable.
+pub struct StaticAllocationTable {
+    entries: [Option<[u8; 16]>; 128],
+}
+
+impl StaticAllocationTable {
+    pub fn new() -> Self {
+        StaticAllocationTable {
+            entries: [None; 128],
+        }
+    }
+}
+
+impl Default for StaticAllocationTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ::lib::core::fmt::Debug for StaticAllocationTable {
+    fn fmt(&self, f: &mut ::lib::core::fmt::Formatter) -> ::lib::core::fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().enumerate().filter_map(|(id, unique_id)| unique_id.map(|x| (id, x))))
+            .finish()
+    }
+}
+
+impl AllocationTable for StaticAllocationTable {
+    fn node_id(&self, unique_id: &[u8; 16]) -> Option<NodeID> {
+        self.entries.iter()
+            .position(|x| x.as_ref() == Some(unique_id))
+            .map(|id| NodeID::new(id as u8))
+    }
+
+    fn is_allocated(&self, node_id: NodeID) -> bool {
+        self.entries[u8::from(node_id) as usize].is_some()
+    }
+
+    fn insert(&mut self, unique_id: [u8; 16], node_id: NodeID) -> Result<(), StorageError> {
+        self.entries[u8::from(node_id) as usize] = Some(unique_id);
+        Ok(())
+    }
+}
+
+/// A centralized dynamic node ID allocator.
+///
+/// The server should be the only allocator on the network.
+/// For redundant allocators, use the `DistributedServer` instead.
+///
+/// Node IDs of nodes seen online by the `NodeMonitor` of the server are never allocated,
+/// so nodes with statically configured node IDs are respected.
+///
+/// `tick` must be called periodically and after receptions are flushed.
+#[derive(Debug)]
+pub struct CentralizedServer<F: TransferFrame, H: SubscriberStorageHandle<F>, T: AllocationTable> {
+    subscriber: Subscriber<Allocation, F, H>,
+    monitor: NodeMonitor<F, H>,
+    table: T,
+    requests: RequestTracker,
+}
+
+impl<F: TransferFrame, H: SubscriberStorageHandle<F>, T: AllocationTable> CentralizedServer<F, H, T> {
+    /// Creates a new server allocating node IDs through `node`.
+    ///
+    /// The node itself is added to the allocation table with `unique_id` if it's not there already.
+    /// Fails with `Err(StorageError::OutOfSpace)` if the table or the storage of `node` is full.
+    ///
+    /// # Panics
+    /// Panics if `node` is anonymous.
+    pub fn new<I, S, N>(node: &N, unique_id: [u8; 16], mut table: T) -> Result<Self, StorageError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        let id = node.id().expect("An allocation server must have a node ID");
+        if table.node_id(&unique_id) != Some(id) {
+            table.insert(unique_id, id)?;
+        }
+
+        Ok(CentralizedServer {
+            subscriber: node.subscribe_anonymous()?,
+            monitor: NodeMonitor::new(node)?,
+            table,
+            requests: RequestTracker::new(),
+        })
+    }
+
+    /// Returns the allocation table of the server.
+    pub fn table(&self) -> &T {
+        &self.table
+    }
+
+    /// Returns the monitor keeping track of the nodes online on the network.
+    pub fn monitor(&self) -> &NodeMonitor<F, H> {
+        &self.monitor
+    }
+
+    /// Handles received allocation requests.
+    pub fn tick<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ServerError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        self.monitor.tick(now, |_| ());
+
+        while let Some(result) = self.subscriber.receive() {
+            let request = match result {
+                Ok(request) => request,
+                Err(_) => continue,
+            };
+
+            match self.requests.handle(&request.body, now) {
+                RequestStatus::Ignored => (),
+                RequestStatus::Partial(response) => node.broadcast(response)?,
+                RequestStatus::Complete{unique_id, preferred_id} => {
+                    let node_id = match self.table.node_id(&unique_id) {
+                        Some(node_id) => node_id,
+                        None => {
+                            let table = &self.table;
+                            let monitor = &self.monitor;
+                            match find_free_node_id(preferred_id, |id| !table.is_allocated(id) && !monitor.is_online(id)) {
+                                Some(node_id) => node_id,
+                                None => continue,
+                            }
+                        },
+                    };
+                    self.table.insert(unique_id, node_id)?;
+                    node.broadcast(allocation_response(&unique_id, node_id))?;
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use node::*;
+    use time::Timestamp;
+
+    use tests::{
+        TestInterface,
+        test_node,
+        exchange,
+    };
+
+    use protocol::dynamic_node_id::*;
+    use protocol::dynamic_node_id::server::*;
+
+    const SERVER_UNIQUE_ID: [u8; 16] = [0xaa; 16];
+    const UNIQUE_ID: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
+    #[test]
+    fn allocation() {
+        let server_interface = TestInterface::default();
+        let server_node = test_node(&server_interface, Some(1));
+        let mut server = CentralizedServer::new(&server_node, SERVER_UNIQUE_ID, StaticAllocationTable::new()).unwrap();
+        assert_eq!(server.table().node_id(&SERVER_UNIQUE_ID), Some(NodeID::new(1)));
+
+        let client_interface = TestInterface::default();
+        let client_node = test_node(&client_interface, None);
+        let mut client = AllocationClient::new(&client_node, UNIQUE_ID, Some(NodeID::new(1)), Timestamp::from_micros(0)).unwrap();
+
+        let mut allocated = None;
+        for ms in 0..5000 {
+            let now = Timestamp::from_micros(ms * 1000);
+
+            allocated = client.tick(&client_node, now).unwrap();
+            if allocated.is_some() {
+                break;
+            }
+            exchange(&[(&client_node, &client_interface), (&server_node, &server_interface)]);
+
+            server.tick(&server_node, now).unwrap();
+            exchange(&[(&client_node, &client_interface), (&server_node, &server_interface)]);
+        }
+
+        // The preferred node ID is taken by the server, the next free node ID upwards is allocated
+        assert_eq!(allocated, Some(NodeID::new(2)));
+        assert_eq!(client_node.id(), Some(NodeID::new(2)));
+        assert_eq!(server.table().node_id(&UNIQUE_ID), Some(NodeID::new(2)));
+    }
+
+    #[test]
+    fn online_nodes_are_not_allocated() {
+        let server_interface = TestInterface::default();
+        let server_node = test_node(&server_interface, Some(1));
+        let mut server = CentralizedServer::new(&server_node, SERVER_UNIQUE_ID, StaticAllocationTable::new()).unwrap();
+
+        // A node with a statically configured node ID, never allocated by the server
+        let static_interface = TestInterface::default();
+        let static_node = test_node(&static_interface, Some(2));
+        let mut publisher = ::protocol::NodeStatusPublisher::new(Timestamp::from_micros(0));
+
+        let client_interface = TestInterface::default();
+        let client_node = test_node(&client_interface, None);
+        let mut client = AllocationClient::new(&client_node, UNIQUE_ID, Some(NodeID::new(1)), Timestamp::from_micros(0)).unwrap();
+
+        let nodes = [(&server_node, &server_interface), (&static_node, &static_interface), (&client_node, &client_interface)];
+        let mut allocated = None;
+        for ms in 0..5000 {
+            let now = Timestamp::from_micros(ms * 1000);
+
+            publisher.tick(&static_node, now).unwrap();
+            allocated = client.tick(&client_node, now).unwrap();
+            if allocated.is_some() {
+                break;
+            }
+            exchange(&nodes);
+
+            server.tick(&server_node, now).unwrap();
+            exchange(&nodes);
+        }
+
+        assert!(server.monitor().is_online(NodeID::new(2)));
+        assert_eq!(allocated, Some(NodeID::new(3)));
+        assert_eq!(server.table().node_id(&UNIQUE_ID), Some(NodeID::new(3)));
+    }
+}
@@ -0,0 +1,858 @@
+use lib::core::cmp;
+
+use {
+    Node,
+    NodeID,
+};
+
+use node::{
+    Subscriber,
+    ServiceServer,
+    ResponseHandle,
+    IncomingRequest,
+};
+
+use storage::{
+    Storage,
+    StorageError,
+    SubscriberStorageHandle,
+};
+
+use transfer::{
+    TransferInterface,
+    TransferFrame,
+};
+
+use time::{
+    Duration,
+    Timestamp,
+};
+
+use types::*;
+
+use super::{
+    Entry,
+    AppendEntriesRequest,
+    AppendEntriesResponse,
+    RequestVoteRequest,
+    RequestVoteResponse,
+    Discovery,
+    RequestTracker,
+    RequestStatus,
+    ServerError,
+    allocation_response,
+    find_free_node_id,
+};
+
+use super::super::{
+    Allocation,
+    Random,
+};
+
+use protocol::node_monitor::NodeMonitor;
+
+/// The interval between AppendEntries requests to a follower when there's nothing to replicate.
+const HEARTBEAT_PERIOD_MS: u64 = 500;
+
+/// How long to wait for the response to a RequestVote or AppendEntries request.
+const REQUEST_TIMEOUT_MS: u64 = 500;
+
+/// The persistent state of a `DistributedServer`.
+///
+/// Implement this trait to store the Raft state in non-volatile memory.
+/// The log always contains the default `Entry` at index 0, and can contain at most 256 entries.
+pub trait RaftStorage {
+    /// Returns the latest term this server has seen.
+    fn current_term(&self) -> u32;
+
+    fn set_current_term(&mut self, term: u32) -> Result<(), StorageError>;
+
+    /// Returns the candidate that received the vote of this server in the current term.
+    fn voted_for(&self) -> Option<NodeID>;
+
+    fn set_voted_for(&mut self, node_id: Option<NodeID>) -> Result<(), StorageError>;
+
+    /// Returns the index of the last entry in the log.
+    fn last_index(&self) -> u8;
+
+    /// Returns the log entry at `index`.
+    fn entry(&self, index: u8) -> Option<Entry>;
+
+    /// Appends an entry to the end of the log.
+    fn append(&mut self, entry: Entry) -> Result<(), StorageError>;
+
+    /// Removes the entry at `index` and all entries after it.
+    ///
+    /// The entry at index 0 is never removed.
+    fn truncate(&mut self, index: u8) -> Result<(), StorageError>;
+}
+
+/// A `RaftStorage` kept in memory.
+///
+/// The state is lost when the allocator restarts, and should only be used when that's acceptable.
+pub struct StaticRaftStorage {
+    current_term: u32,
+    voted_for: Option<NodeID>,
+    log: [Entry; 256],
+    log_length: usize,
+}
+
+impl StaticRaftStorage {
+    pub fn new() -> Self {
+        StaticRaftStorage {
+            current_term: 0,
+            voted_for: None,
+            log: [Entry::default(); 256],
+            log_length: 1,
+        }
+    }
+}
+
+impl Default for StaticRaftStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ::lib::core::fmt::Debug for StaticRaftStorage {
+    fn fmt(&self, f: &mut ::lib::core::fmt::Formatter) -> ::lib::core::fmt::Result {
+        f.debug_struct("StaticRaftStorage")
+            .field("current_term", &self.current_term)
+            .field("voted_for", &self.voted_for)
+            .field("log", &&self.log[0..self.log_length])
+            .finish()
+    }
+}
+
+impl RaftStorage for StaticRaftStorage {
+    fn current_term(&self) -> u32 {
+        self.current_term
+    }
+
+    fn set_current_term(&mut self, term: u32) -> Result<(), StorageError> {
+        self.current_term = term;
+        Ok(())
+    }
+
+    fn voted_for(&self) -> Option<NodeID> {
+        self.voted_for
+    }
+
+    fn set_voted_for(&mut self, node_id: Option<NodeID>) -> Result<(), StorageError> {
+        self.voted_for = node_id;
+        Ok(())
+    }
+
+    fn last_index(&self) -> u8 {
+        (self.log_length - 1) as u8
+    }
+
+    fn entry(&self, index: u8) -> Option<Entry> {
+        self.log[0..self.log_length].get(index as usize).cloned()
+    }
+
+    fn append(&mut self, entry: Entry) -> Result<(), StorageError> {
+        if self.log_length == self.log.len() {
+            return Err(StorageError::OutOfSpace);
+        }
+        self.log[self.log_length] = entry;
+        self.log_length += 1;
+        Ok(())
+    }
+
+    fn truncate(&mut self, index: u8) -> Result<(), StorageError> {
+        self.log_length = cmp::min(self.log_length, cmp::max(index as usize, 1));
+        Ok(())
+    }
+}
+
+/// The Raft role of a `DistributedServer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaftState {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+/// Another allocator in the cluster.
+#[derive(Debug)]
+struct Peer<F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    id: NodeID,
+    next_index: u8,
+    match_index: u8,
+    next_heartbeat: Timestamp,
+    /// The pending AppendEntries request together with the number of entries it carries.
+    append_entries: Option<(ResponseHandle<AppendEntriesRequest, F, H>, u8)>,
+    request_vote: Option<ResponseHandle<RequestVoteRequest, F, H>>,
+}
+
+/// One allocator in a cluster of redundant dynamic node ID allocators.
+///
+/// The allocators discover each other with `Discovery` messages and replicate the allocation table
+/// with the Raft consensus algorithm. Only the leader of the cluster responds to allocation requests,
+/// and only when all entries of its log are committed. A new allocation is added to the log,
+/// and the allocatee gets its node ID when it repeats the request after the allocation is committed.
+///
+/// All allocators in the cluster must be configured with the same cluster size.
+/// Node IDs of nodes seen online by the `NodeMonitor` of the allocator are never allocated.
+///
+/// `tick` must be called periodically (preferably more often than every 10ms) and after receptions are flushed.
+#[derive(Debug)]
+pub struct DistributedServer<F: TransferFrame, H: SubscriberStorageHandle<F>, R: RaftStorage> {
+    discovery_subscriber: Subscriber<Discovery, F, H>,
+    allocation_subscriber: Subscriber<Allocation, F, H>,
+    monitor: NodeMonitor<F, H>,
+    append_entries_server: ServiceServer<AppendEntriesRequest, F, H>,
+    request_vote_server: ServiceServer<RequestVoteRequest, F, H>,
+    storage: R,
+    id: NodeID,
+    unique_id: [u8; 16],
+    cluster_size: u8,
+    peers: [Option<Peer<F, H>>; 4],
+    state: RaftState,
+    commit_index: u8,
+    votes: u8,
+    election_deadline: Timestamp,
+    next_discovery: Timestamp,
+    discovery_requested: bool,
+    random: Random,
+    requests: RequestTracker,
+}
+
+impl<F: TransferFrame, H: SubscriberStorageHandle<F>, R: RaftStorage> DistributedServer<F, H, R> {
+    /// Creates a new allocator in a cluster of `cluster_size` allocators.
+    ///
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage of `node` can't hold the subscriptions.
+    ///
+    /// # Panics
+    /// Panics if `node` is anonymous or if `cluster_size` is not in the range `1..=Discovery::MAX_CLUSTER_SIZE`.
+    pub fn new<I, S, N>(node: &N, unique_id: [u8; 16], cluster_size: u8, storage: R, now: Timestamp) -> Result<Self, StorageError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        assert!((1..=Discovery::MAX_CLUSTER_SIZE).contains(&cluster_size), "Invalid cluster size");
+        let id = node.id().expect("An allocation server must have a node ID");
+
+        let mut server = DistributedServer {
+            discovery_subscriber: node.subscribe()?,
+            allocation_subscriber: node.subscribe_anonymous()?,
+            monitor: NodeMonitor::new(node)?,
+            append_entries_server: node.serve()?,
+            request_vote_server: node.serve()?,
+            storage,
+            id,
+            unique_id,
+            cluster_size,
+            peers: [None, None, None, None],
+            state: RaftState::Follower,
+            commit_index: 0,
+            votes: 0,
+            election_deadline: now,
+            next_discovery: now,
+            discovery_requested: false,
+            random: Random::new(&unique_id),
+            requests: RequestTracker::new(),
+        };
+        server.reset_election_timer(now);
+        Ok(server)
+    }
+
+    /// Returns the Raft role of this allocator.
+    pub fn state(&self) -> RaftState {
+        self.state
+    }
+
+    /// Returns the index of the last log entry known to be committed.
+    pub fn commit_index(&self) -> u8 {
+        self.commit_index
+    }
+
+    /// Returns the persistent state of this allocator.
+    pub fn storage(&self) -> &R {
+        &self.storage
+    }
+
+    /// Returns the monitor keeping track of the nodes online on the network.
+    pub fn monitor(&self) -> &NodeMonitor<F, H> {
+        &self.monitor
+    }
+
+    /// Returns the `NodeID` allocated to the node with `unique_id` according to the committed part of the log.
+    pub fn node_id(&self, unique_id: &[u8; 16]) -> Option<NodeID> {
+        (1..=self.commit_index)
+            .filter_map(|index| self.storage.entry(index))
+            .find(|entry| &entry.unique_id == unique_id)
+            .map(|entry| NodeID::new(u8::from(entry.node_id)))
+    }
+
+    /// Runs discovery, the Raft algorithm and handles received allocation requests.
+    pub fn tick<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ServerError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        self.monitor.tick(now, |_| ());
+        self.handle_discovery(node, now)?;
+        self.handle_request_vote(node, now)?;
+        self.handle_append_entries(node, now)?;
+        self.handle_responses(now)?;
+
+        if self.state != RaftState::Leader && now >= self.election_deadline {
+            self.start_election(node, now)?;
+        }
+
+        if self.state == RaftState::Candidate && self.votes >= self.quorum() {
+            self.become_leader(now)?;
+        }
+
+        if self.state == RaftState::Leader {
+            self.replicate(node, now)?;
+            self.update_commit_index();
+        }
+
+        self.handle_allocations(node, now)
+    }
+
+    fn quorum(&self) -> u8 {
+        self.cluster_size / 2 + 1
+    }
+
+    fn reset_election_timer(&mut self, now: Timestamp) {
+        let timeout = self.random.millis(AppendEntriesRequest::DEFAULT_MIN_ELECTION_TIMEOUT_MS, AppendEntriesRequest::DEFAULT_MAX_ELECTION_TIMEOUT_MS);
+        self.election_deadline = now + timeout;
+    }
+
+    fn last_log_term(&self) -> u32 {
+        self.storage.entry(self.storage.last_index()).map(|entry| entry.term).unwrap_or(0)
+    }
+
+    fn add_peer(&mut self, id: NodeID) {
+        if id == self.id || self.peers.iter().flatten().any(|peer| peer.id == id) {
+            return;
+        }
+
+        let known = self.peers.iter().flatten().count();
+        if known + 1 >= self.cluster_size as usize {
+            return;
+        }
+
+        let next_index = self.storage.last_index().saturating_add(1);
+        if let Some(slot) = self.peers.iter_mut().find(|peer| peer.is_none()) {
+            *slot = Some(Peer {
+                id,
+                next_index,
+                match_index: 0,
+                next_heartbeat: Timestamp::default(),
+                append_entries: None,
+                request_vote: None,
+            });
+        }
+    }
+
+    /// Moves to a new term as a follower.
+    fn step_down(&mut self, term: u32, now: Timestamp) -> Result<(), StorageError> {
+        if term > self.storage.current_term() {
+            self.storage.set_current_term(term)?;
+            self.storage.set_voted_for(None)?;
+        }
+
+        if self.state != RaftState::Follower {
+            self.state = RaftState::Follower;
+            for peer in self.peers.iter_mut().flatten() {
+                peer.append_entries = None;
+                peer.request_vote = None;
+            }
+            self.reset_election_timer(now);
+        }
+        Ok(())
+    }
+
+    fn handle_discovery<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ServerError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        while let Some(result) = self.discovery_subscriber.receive() {
+            let transfer = match result {
+                Ok(transfer) => transfer,
+                Err(_) => continue,
+            };
+
+            if transfer.body.configured_cluster_size != self.cluster_size {
+                continue;
+            }
+
+            if let Some(source) = transfer.source.node_id() {
+                self.add_peer(source);
+            }
+            for id in transfer.body.known_nodes.iter() {
+                if *id != 0 && *id <= 127 {
+                    self.add_peer(NodeID::new(*id));
+                }
+            }
+
+            if transfer.body.known_nodes.length() < self.cluster_size as usize {
+                self.discovery_requested = true;
+            }
+        }
+
+        let discovered = self.peers.iter().flatten().count() + 1 == self.cluster_size as usize;
+        if (!discovered || self.discovery_requested) && now >= self.next_discovery {
+            let mut known_nodes = Dynamic::<[u8; 5]>::new();
+            known_nodes.push(u8::from(self.id));
+            for peer in self.peers.iter().flatten() {
+                known_nodes.push(u8::from(peer.id));
+            }
+
+            node.broadcast(Discovery {
+                configured_cluster_size: self.cluster_size,
+                known_nodes,
+            })?;
+
+            self.discovery_requested = false;
+            self.next_discovery = now + Duration::from_millis(u64::from(Discovery::BROADCASTING_PERIOD_MS));
+        }
+
+        Ok(())
+    }
+
+    fn handle_request_vote<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ServerError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        while let Some(result) = self.request_vote_server.receive() {
+            let IncomingRequest{body, token} = match result {
+                Ok(request) => request,
+                Err(_) => continue,
+            };
+
+            self.add_peer(token.source());
+
+            if body.term > self.storage.current_term() {
+                self.step_down(body.term, now)?;
+            }
+
+            let up_to_date = (body.last_log_term, body.last_log_index) >= (self.last_log_term(), self.storage.last_index());
+            let vote_granted = body.term == self.storage.current_term()
+                && up_to_date
+                && self.storage.voted_for().map_or(true, |id| id == token.source());
+
+            if vote_granted {
+                self.storage.set_voted_for(Some(token.source()))?;
+                self.reset_election_timer(now);
+            }
+
+            node.respond(token, RequestVoteResponse {
+                term: self.storage.current_term(),
+                vote_granted,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_append_entries<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ServerError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        while let Some(result) = self.append_entries_server.receive() {
+            let IncomingRequest{body, token} = match result {
+                Ok(request) => request,
+                Err(_) => continue,
+            };
+
+            self.add_peer(token.source());
+
+            let success = if body.term < self.storage.current_term() {
+                false
+            } else {
+                // There's a leader for this term, which means that this server can't be the leader or a candidate
+                self.step_down(body.term, now)?;
+                self.reset_election_timer(now);
+                self.append(&body)?
+            };
+
+            node.respond(token, AppendEntriesResponse {
+                term: self.storage.current_term(),
+                success,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Appends the entries of an AppendEntries request from the leader to the log.
+    ///
+    /// Returns false if the log doesn't contain the entry preceding the new entries.
+    fn append(&mut self, request: &AppendEntriesRequest) -> Result<bool, StorageError> {
+        let prev_log_term = self.storage.entry(request.prev_log_index).map(|entry| entry.term);
+        let last_new_index = request.prev_log_index as usize + request.entries.length();
+        if prev_log_term != Some(request.prev_log_term) || last_new_index > 255 {
+            return Ok(false);
+        }
+
+        for (i, entry) in request.entries.iter().enumerate() {
+            let index = request.prev_log_index + 1 + i as u8;
+            match self.storage.entry(index) {
+                Some(existing) if existing.term == entry.term => (),
+                Some(_) => {
+                    self.storage.truncate(index)?;
+                    self.storage.append(*entry)?;
+                },
+                None => self.storage.append(*entry)?,
+            }
+        }
+
+        if request.leader_commit > self.commit_index {
+            self.commit_index = cmp::min(request.leader_commit, last_new_index as u8);
+        }
+
+        Ok(true)
+    }
+
+    fn handle_responses(&mut self, now: Timestamp) -> Result<(), StorageError> {
+        let term = self.storage.current_term();
+        let mut newer_term = None;
+
+        for peer in self.peers.iter_mut().flatten() {
+            if let Some((handle, entries)) = peer.append_entries.take() {
+                match handle.receive(now) {
+                    None => peer.append_entries = Some((handle, entries)),
+                    Some(Err(_)) => (),
+                    Some(Ok(response)) => if response.term > term {
+                        newer_term = Some(response.term);
+                    } else if response.success {
+                        peer.match_index = peer.next_index - 1 + entries;
+                        peer.next_index = peer.match_index.saturating_add(1);
+                    } else if peer.next_index > 1 {
+                        peer.next_index -= 1;
+                    },
+                }
+            }
+
+            if let Some(handle) = peer.request_vote.take() {
+                match handle.receive(now) {
+                    None => peer.request_vote = Some(handle),
+                    Some(Err(_)) => (),
+                    Some(Ok(response)) => if response.term > term {
+                        newer_term = Some(response.term);
+                    } else if response.term == term && response.vote_granted {
+                        self.votes += 1;
+                    },
+                }
+            }
+        }
+
+        if let Some(term) = newer_term {
+            self.step_down(term, now)?;
+        }
+
+        Ok(())
+    }
+
+    fn start_election<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ServerError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        let term = self.storage.current_term() + 1;
+        self.storage.set_current_term(term)?;
+        self.storage.set_voted_for(Some(self.id))?;
+        self.state = RaftState::Candidate;
+        self.votes = 1;
+        self.reset_election_timer(now);
+
+        let last_log_term = self.last_log_term();
+        let last_log_index = self.storage.last_index();
+        for peer in self.peers.iter_mut().flatten() {
+            peer.append_entries = None;
+            peer.request_vote = Some(node.request(
+                peer.id,
+                RequestVoteRequest {
+                    term,
+                    last_log_term,
+                    last_log_index,
+                },
+                now + Duration::from_millis(REQUEST_TIMEOUT_MS),
+            )?);
+        }
+
+        Ok(())
+    }
+
+    fn become_leader(&mut self, now: Timestamp) -> Result<(), StorageError> {
+        self.state = RaftState::Leader;
+
+        let next_index = self.storage.last_index().saturating_add(1);
+        for peer in self.peers.iter_mut().flatten() {
+            peer.next_index = next_index;
+            peer.match_index = 0;
+            peer.next_heartbeat = now;
+            peer.request_vote = None;
+        }
+
+        // Makes sure the allocation of this server is in the log
+        if self.log_entry(&self.unique_id).is_none() {
+            let term = self.storage.current_term();
+            self.storage.append(Entry::new(term, self.unique_id, self.id))?;
+        }
+
+        Ok(())
+    }
+
+    fn replicate<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ServerError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        let term = self.storage.current_term();
+        let last_index = self.storage.last_index();
+
+        for peer in self.peers.iter_mut().flatten() {
+            let outdated = peer.next_index <= last_index;
+            if peer.append_entries.is_some() || !(outdated || now >= peer.next_heartbeat) {
+                continue;
+            }
+
+            let prev_log_index = peer.next_index - 1;
+            let mut entries = Dynamic::<[Entry; 1]>::new();
+            if outdated {
+                entries.push(self.storage.entry(peer.next_index).unwrap());
+            }
+            let entries_length = entries.length() as u8;
+
+            let handle = node.request(
+                peer.id,
+                AppendEntriesRequest {
+                    term,
+                    prev_log_term: self.storage.entry(prev_log_index).map(|entry| entry.term).unwrap_or(0),
+                    prev_log_index,
+                    leader_commit: self.commit_index,
+                    entries,
+                },
+                now + Duration::from_millis(REQUEST_TIMEOUT_MS),
+            )?;
+            peer.append_entries = Some((handle, entries_length));
+            peer.next_heartbeat = now + Duration::from_millis(HEARTBEAT_PERIOD_MS);
+        }
+
+        Ok(())
+    }
+
+    fn update_commit_index(&mut self) {
+        let term = self.storage.current_term();
+        for index in (self.commit_index + 1..=self.storage.last_index()).rev() {
+            if self.storage.entry(index).map(|entry| entry.term) != Some(term) {
+                continue;
+            }
+
+            let replicas = 1 + self.peers.iter().flatten().filter(|peer| peer.match_index >= index).count();
+            if replicas >= self.quorum() as usize {
+                self.commit_index = index;
+                return;
+            }
+        }
+    }
+
+    /// Returns the last entry for `unique_id`, committed or not.
+    fn log_entry(&self, unique_id: &[u8; 16]) -> Option<Entry> {
+        (1..=self.storage.last_index())
+            .rev()
+            .filter_map(|index| self.storage.entry(index))
+            .find(|entry| &entry.unique_id == unique_id)
+    }
+
+    fn is_free(&self, node_id: NodeID) -> bool {
+        node_id != self.id
+            && !self.monitor.is_online(node_id)
+            && !self.peers.iter().flatten().any(|peer| peer.id == node_id)
+            && !(1..=self.storage.last_index())
+                .filter_map(|index| self.storage.entry(index))
+                .any(|entry| u8::from(entry.node_id) == u8::from(node_id))
+    }
+
+    fn handle_allocations<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ServerError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        while let Some(result) = self.allocation_subscriber.receive() {
+            let request = match result {
+                Ok(request) => request,
+                Err(_) => continue,
+            };
+
+            // Only the leader allocates node IDs, and only after the entries of its own term are committed
+            let term = self.storage.current_term();
+            let pending_in_term = (self.commit_index + 1..=self.storage.last_index())
+                .filter_map(|index| self.storage.entry(index))
+                .any(|entry| entry.term == term);
+            if self.state != RaftState::Leader || pending_in_term {
+                continue;
+            }
+
+            match self.requests.handle(&request.body, now) {
+                RequestStatus::Ignored => (),
+                RequestStatus::Partial(response) => node.broadcast(response)?,
+                RequestStatus::Complete{unique_id, preferred_id} => {
+                    if let Some(node_id) = self.node_id(&unique_id) {
+                        node.broadcast(allocation_response(&unique_id, node_id))?;
+                    } else if let Some(entry) = self.log_entry(&unique_id) {
+                        // Entries from earlier terms can only be committed together with an entry from the current term
+                        self.storage.append(Entry::new(term, unique_id, NodeID::new(u8::from(entry.node_id))))?;
+                    } else if let Some(node_id) = find_free_node_id(preferred_id, |id| self.is_free(id)) {
+                        // The allocatee is responded to when it repeats the request after the entry is committed
+                        self.storage.append(Entry::new(term, unique_id, node_id))?;
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use node::*;
+    use time::Timestamp;
+
+    use tests::{
+        TestInterface,
+        TestNode,
+        test_node,
+        exchange,
+    };
+
+    use protocol::dynamic_node_id::*;
+    use protocol::dynamic_node_id::server::*;
+
+    const UNIQUE_ID: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
+    #[test]
+    fn single_server_allocation() {
+        let interfaces = vec![TestInterface::default(), TestInterface::default()];
+        let server_node = test_node(&interfaces[0], Some(1));
+        let client_node = test_node(&interfaces[1], None);
+
+        let mut server = DistributedServer::new(&server_node, [0xaa; 16], 1, StaticRaftStorage::new(), Timestamp::from_micros(0)).unwrap();
+        let mut client = AllocationClient::new(&client_node, UNIQUE_ID, None, Timestamp::from_micros(0)).unwrap();
+
+        let mut allocated = None;
+        for ms in 0..10_000 {
+            let now = Timestamp::from_micros(ms * 1000);
+            server.tick(&server_node, now).unwrap();
+            allocated = client.tick(&client_node, now).unwrap();
+            if allocated.is_some() {
+                break;
+            }
+
+            exchange(&[(&server_node, &interfaces[0]), (&client_node, &interfaces[1])]);
+        }
+
+        assert_eq!(server.state(), RaftState::Leader);
+        assert_eq!(allocated, Some(NodeID::new(125)));
+        assert_eq!(server.node_id(&[0xaa; 16]), Some(NodeID::new(1)));
+        assert_eq!(server.node_id(&UNIQUE_ID), Some(NodeID::new(125)));
+    }
+
+    #[test]
+    fn online_nodes_are_not_allocated() {
+        let interfaces = vec![TestInterface::default(), TestInterface::default(), TestInterface::default()];
+        let server_node = test_node(&interfaces[0], Some(1));
+        let client_node = test_node(&interfaces[1], None);
+        let static_node = test_node(&interfaces[2], Some(125));
+
+        let mut server = DistributedServer::new(&server_node, [0xaa; 16], 1, StaticRaftStorage::new(), Timestamp::from_micros(0)).unwrap();
+        let mut client = AllocationClient::new(&client_node, UNIQUE_ID, None, Timestamp::from_micros(0)).unwrap();
+        let mut publisher = ::protocol::NodeStatusPublisher::new(Timestamp::from_micros(0));
+
+        let mut allocated = None;
+        for ms in 0..10_000 {
+            let now = Timestamp::from_micros(ms * 1000);
+            publisher.tick(&static_node, now).unwrap();
+            server.tick(&server_node, now).unwrap();
+            allocated = client.tick(&client_node, now).unwrap();
+            if allocated.is_some() {
+                break;
+            }
+
+            exchange(&[(&server_node, &interfaces[0]), (&client_node, &interfaces[1]), (&static_node, &interfaces[2])]);
+        }
+
+        assert!(server.monitor().is_online(NodeID::new(125)));
+        assert_eq!(allocated, Some(NodeID::new(124)));
+        assert_eq!(server.node_id(&UNIQUE_ID), Some(NodeID::new(124)));
+    }
+
+    #[test]
+    fn reelected_leader_appends_own_entry_once() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, Some(1));
+        let mut server = DistributedServer::new(&node, [0xaa; 16], 3, StaticRaftStorage::new(), Timestamp::from_micros(0)).unwrap();
+
+        // Without the rest of the cluster, the entry is never committed
+        for term in 1..4 {
+            server.storage.set_current_term(term).unwrap();
+            server.become_leader(Timestamp::from_micros(0)).unwrap();
+        }
+
+        assert_eq!(server.commit_index(), 0);
+        assert_eq!(server.storage().last_index(), 1);
+        assert_eq!(server.storage().entry(1).unwrap().unique_id, [0xaa; 16]);
+    }
+
+    #[test]
+    fn cluster_allocation() {
+        let interfaces = vec![TestInterface::default(), TestInterface::default(), TestInterface::default(), TestInterface::default()];
+        let nodes: Vec<TestNode> = (0..3)
+            .map(|i| test_node(&interfaces[i], Some(i as u8 + 1)))
+            .chain(Some(test_node(&interfaces[3], None)))
+            .collect();
+
+        let mut servers: Vec<_> = (0..3)
+            .map(|i| DistributedServer::new(&nodes[i], [i as u8 + 0xa0; 16], 3, StaticRaftStorage::new(), Timestamp::from_micros(0)).unwrap())
+            .collect();
+
+        let mut client = None;
+        let mut allocated = None;
+        for ms in 0..20_000 {
+            let now = Timestamp::from_micros(ms * 1000);
+
+            for (server, node) in servers.iter_mut().zip(nodes.iter()) {
+                server.tick(node, now).unwrap();
+            }
+
+            // Let the cluster elect a leader before the allocatee shows up
+            if ms == 10_000 {
+                assert_eq!(servers.iter().filter(|server| server.state() == RaftState::Leader).count(), 1);
+                client = Some(AllocationClient::new(&nodes[3], UNIQUE_ID, Some(NodeID::new(2)), now).unwrap());
+            }
+            if let Some(ref mut client) = client {
+                allocated = client.tick(&nodes[3], now).unwrap();
+                if allocated.is_some() {
+                    break;
+                }
+            }
+
+            exchange(&nodes.iter().zip(interfaces.iter()).collect::<Vec<_>>());
+        }
+
+        // Node ID 2 and 3 belongs to the other allocators
+        assert_eq!(allocated, Some(NodeID::new(4)));
+
+        let leader = servers.iter().find(|server| server.state() == RaftState::Leader).unwrap();
+        assert_eq!(leader.node_id(&UNIQUE_ID), Some(NodeID::new(4)));
+
+        // The allocation is replicated to a majority of the cluster
+        let replicas = servers.iter()
+            .filter(|server| (1..=server.storage().last_index()).any(|i| server.storage().entry(i).unwrap().unique_id == UNIQUE_ID))
+            .count();
+        assert!(replicas >= 2);
+    }
+}
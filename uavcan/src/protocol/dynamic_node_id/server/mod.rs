@@ -0,0 +1,331 @@
+//! Dynamic node ID allocation servers
+//!
+//! Two kinds of allocators are provided:
+//!
+//! - `CentralizedServer` is a single allocator keeping its allocation table in an `AllocationTable`.
+//! - `DistributedServer` is one of up to `Discovery::MAX_CLUSTER_SIZE` redundant allocators
+//!   keeping the allocation table consistent with the Raft consensus algorithm.
+//!
+//! Both kinds of servers store their state through a trait, allowing the application to persist
+//! the state in whatever non-volatile memory is available.
+
+mod centralized;
+mod distributed;
+
+pub use self::centralized::{
+    AllocationTable,
+    StaticAllocationTable,
+    CentralizedServer,
+};
+
+pub use self::distributed::{
+    RaftStorage,
+    StaticRaftStorage,
+    DistributedServer,
+    RaftState,
+};
+
+use lib::core::cmp;
+
+use NodeID;
+
+use node::TransmitError;
+
+use storage::StorageError;
+
+use time::{
+    Duration,
+    Timestamp,
+};
+
+use types::*;
+
+use super::Allocation;
+
+/// The highest `NodeID` an allocator will hand out, 126 and 127 are reserved for diagnostic tools.
+const MAX_ALLOCATABLE_NODE_ID: u8 = 125;
+
+/// One entry in the replicated log, `uavcan.protocol.dynamic_node_id.server.Entry`.
+///
+/// The entry at index 0 of the log is always the default entry (term 0, zero unique ID and node ID 0).
+#[derive(Debug, PartialEq, Clone, Copy, Default, UavcanStruct)]
+#[DSDLSignature = "0x7faa779d64fa75c2"]
+#[DataTypeSignature = "0x7faa779d64fa75c2"]
+pub struct Entry {
+    /// The Raft term this entry was created in.
+    pub term: u32,
+    pub unique_id: [u8; 16],
+    _v0: void1,
+    pub node_id: u7,
+}
+
+impl Entry {
+    pub fn new(term: u32, unique_id: [u8; 16], node_id: NodeID) -> Self {
+        Entry {
+            term,
+            unique_id,
+            _v0: void1{},
+            node_id: u7::new(u8::from(node_id)),
+        }
+    }
+}
+
+/// Raft AppendEntries request, `uavcan.protocol.dynamic_node_id.server.AppendEntries`.
+///
+/// Sent by the leader to replicate log entries, and without entries as a heartbeat.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x8032c7097b48a3cc"]
+#[DataTypeSignature = "0x8032c7097b48a3cc"]
+pub struct AppendEntriesRequest {
+    pub term: u32,
+    pub prev_log_term: u32,
+    pub prev_log_index: u8,
+    pub leader_commit: u8,
+    pub entries: Dynamic<[Entry; 1]>,
+}
+
+impl AppendEntriesRequest {
+    pub const DEFAULT_MIN_ELECTION_TIMEOUT_MS: u16 = 2000;
+    pub const DEFAULT_MAX_ELECTION_TIMEOUT_MS: u16 = 4000;
+}
+
+/// Raft AppendEntries response.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x8032c7097b48a3cc"]
+#[DataTypeSignature = "0x8032c7097b48a3cc"]
+pub struct AppendEntriesResponse {
+    pub term: u32,
+    pub success: bool,
+}
+
+impl ::Request for AppendEntriesRequest {
+    type RESPONSE = AppendEntriesResponse;
+    const TYPE_ID: Option<u8> = Some(30);
+}
+
+impl ::Response for AppendEntriesResponse {
+    type REQUEST = AppendEntriesRequest;
+    const TYPE_ID: Option<u8> = Some(30);
+}
+
+/// Raft RequestVote request, `uavcan.protocol.dynamic_node_id.server.RequestVote`.
+///
+/// Sent by candidates to gather votes.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0xcdde07bb89a56356"]
+#[DataTypeSignature = "0xcdde07bb89a56356"]
+pub struct RequestVoteRequest {
+    pub term: u32,
+    pub last_log_term: u32,
+    pub last_log_index: u8,
+}
+
+/// Raft RequestVote response.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0xcdde07bb89a56356"]
+#[DataTypeSignature = "0xcdde07bb89a56356"]
+pub struct RequestVoteResponse {
+    pub term: u32,
+    pub vote_granted: bool,
+}
+
+impl ::Request for RequestVoteRequest {
+    type RESPONSE = RequestVoteResponse;
+    const TYPE_ID: Option<u8> = Some(31);
+}
+
+impl ::Response for RequestVoteResponse {
+    type REQUEST = RequestVoteRequest;
+    const TYPE_ID: Option<u8> = Some(31);
+}
+
+/// Broadcast by distributed allocators to find the other allocators in the cluster,
+/// `uavcan.protocol.dynamic_node_id.server.Discovery`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x821ae2f525f69f21"]
+#[DataTypeSignature = "0x821ae2f525f69f21"]
+pub struct Discovery {
+    /// The cluster size this allocator is configured with, all allocators must agree on it.
+    pub configured_cluster_size: u8,
+
+    /// The node IDs of all allocators known to the sender, including itself.
+    pub known_nodes: Dynamic<[u8; 5]>,
+}
+
+impl Discovery {
+    pub const BROADCASTING_PERIOD_MS: u16 = 1000;
+    pub const MAX_CLUSTER_SIZE: u8 = 5;
+}
+
+impl ::Message for Discovery {
+    const TYPE_ID: Option<u16> = Some(390);
+}
+
+/// The error kind for a failed allocation server operation
+#[derive(Debug, PartialEq)]
+pub enum ServerError {
+    /// Transmitting a transfer failed.
+    Transmit(TransmitError),
+    /// The persistent state of the server could not be updated.
+    Storage(StorageError),
+}
+
+impl From<TransmitError> for ServerError {
+    fn from(error: TransmitError) -> Self {
+        ServerError::Transmit(error)
+    }
+}
+
+impl From<StorageError> for ServerError {
+    fn from(error: StorageError) -> Self {
+        ServerError::Storage(error)
+    }
+}
+
+
+/// The outcome of handling one allocation request.
+#[derive(Debug, PartialEq)]
+enum RequestStatus {
+    /// The request was not a valid continuation of an allocation and should not be responded to.
+    Ignored,
+    /// A part of the unique ID is received, the allocatee expects the received part to be repeated back.
+    Partial(Allocation),
+    /// The full unique ID is received.
+    Complete {
+        unique_id: [u8; 16],
+        preferred_id: Option<NodeID>,
+    },
+}
+
+/// Collects the unique ID of an allocatee from the staged allocation requests.
+///
+/// Only one allocation is handled at the time. A request that doesn't continue
+/// the allocation in progress within `Allocation::FOLLOWUP_TIMEOUT_MS` starts over.
+#[derive(Debug)]
+struct RequestTracker {
+    unique_id: [u8; 16],
+    received: usize,
+    last_request: Timestamp,
+}
+
+impl RequestTracker {
+    fn new() -> Self {
+        RequestTracker {
+            unique_id: [0; 16],
+            received: 0,
+            last_request: Timestamp::default(),
+        }
+    }
+
+    fn handle(&mut self, request: &Allocation, now: Timestamp) -> RequestStatus {
+        if now.duration_since(self.last_request) > Duration::from_millis(u64::from(Allocation::FOLLOWUP_TIMEOUT_MS)) {
+            self.received = 0;
+        }
+
+        if request.first_part_of_unique_id {
+            self.received = 0;
+        } else if self.received == 0 {
+            return RequestStatus::Ignored;
+        }
+
+        let part = request.unique_id.as_ref();
+        if part.is_empty() || self.received + part.len() > self.unique_id.len() {
+            self.received = 0;
+            return RequestStatus::Ignored;
+        }
+
+        self.unique_id[self.received..self.received + part.len()].copy_from_slice(part);
+        self.received += part.len();
+        self.last_request = now;
+
+        if self.received == self.unique_id.len() {
+            self.received = 0;
+            let preferred_id = u8::from(request.node_id);
+            RequestStatus::Complete {
+                unique_id: self.unique_id,
+                preferred_id: if preferred_id == Allocation::ANY_NODE_ID { None } else { Some(NodeID::new(preferred_id)) },
+            }
+        } else {
+            RequestStatus::Partial(Allocation {
+                node_id: u7::new(0),
+                first_part_of_unique_id: false,
+                unique_id: Dynamic::<[u8; 16]>::with_data(&self.unique_id[0..self.received]),
+            })
+        }
+    }
+}
+
+/// Creates the final allocation response.
+fn allocation_response(unique_id: &[u8; 16], node_id: NodeID) -> Allocation {
+    Allocation {
+        node_id: u7::new(u8::from(node_id)),
+        first_part_of_unique_id: false,
+        unique_id: Dynamic::<[u8; 16]>::with_data(unique_id),
+    }
+}
+
+/// Finds a free node ID according to the specification.
+///
+/// The search starts at the preferred node ID (or the highest allocatable node ID if there's no preference)
+/// and goes upwards. If no free node ID is found, the search continues downwards from the preferred node ID.
+fn find_free_node_id<P: Fn(NodeID) -> bool>(preferred_id: Option<NodeID>, is_free: P) -> Option<NodeID> {
+    let start = cmp::min(preferred_id.map(u8::from).unwrap_or(MAX_ALLOCATABLE_NODE_ID), MAX_ALLOCATABLE_NODE_ID);
+    (start..=MAX_ALLOCATABLE_NODE_ID).chain((1..start).rev())
+        .map(NodeID::new)
+        .find(|id| is_free(*id))
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use protocol::dynamic_node_id::server::*;
+
+    fn request(first_part: bool, unique_id: &[u8]) -> Allocation {
+        Allocation {
+            node_id: u7::new(42),
+            first_part_of_unique_id: first_part,
+            unique_id: Dynamic::<[u8; 16]>::with_data(unique_id),
+        }
+    }
+
+    #[test]
+    fn staged_request() {
+        let unique_id = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        let mut tracker = RequestTracker::new();
+
+        // Follow-ups without a first part are ignored
+        assert_eq!(tracker.handle(&request(false, &unique_id[6..12]), Timestamp::from_micros(0)), RequestStatus::Ignored);
+
+        match tracker.handle(&request(true, &unique_id[0..6]), Timestamp::from_micros(1_000)) {
+            RequestStatus::Partial(response) => assert_eq!(response.unique_id.as_ref(), &unique_id[0..6]),
+            x => panic!("unexpected status {:?}", x),
+        }
+        match tracker.handle(&request(false, &unique_id[6..12]), Timestamp::from_micros(300_000)) {
+            RequestStatus::Partial(response) => assert_eq!(response.unique_id.as_ref(), &unique_id[0..12]),
+            x => panic!("unexpected status {:?}", x),
+        }
+        assert_eq!(
+            tracker.handle(&request(false, &unique_id[12..16]), Timestamp::from_micros(600_000)),
+            RequestStatus::Complete{unique_id, preferred_id: Some(NodeID::new(42))},
+        );
+    }
+
+    #[test]
+    fn staged_request_timeout() {
+        let unique_id = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        let mut tracker = RequestTracker::new();
+
+        tracker.handle(&request(true, &unique_id[0..6]), Timestamp::from_micros(1_000));
+        assert_eq!(tracker.handle(&request(false, &unique_id[6..12]), Timestamp::from_micros(502_000)), RequestStatus::Ignored);
+    }
+
+    #[test]
+    fn free_node_id_search() {
+        assert_eq!(find_free_node_id(None, |_| true), Some(NodeID::new(125)));
+        assert_eq!(find_free_node_id(Some(NodeID::new(127)), |_| true), Some(NodeID::new(125)));
+        assert_eq!(find_free_node_id(Some(NodeID::new(42)), |id| u8::from(id) != 42), Some(NodeID::new(43)));
+        assert_eq!(find_free_node_id(Some(NodeID::new(124)), |id| u8::from(id) < 124), Some(NodeID::new(123)));
+        assert_eq!(find_free_node_id(None, |_| false), None);
+    }
+}
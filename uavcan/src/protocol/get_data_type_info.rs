@@ -0,0 +1,69 @@
+//! Data type introspection
+//!
+//! Allows other nodes to find out what data types a node knows and how it uses them.
+
+use types::*;
+
+/// Kind of a data type, `uavcan.protocol.DataTypeKind`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x9420a73e008e5930"]
+#[DataTypeSignature = "0x9420a73e008e5930"]
+pub struct DataTypeKind {
+    /// One of the `SERVICE` or `MESSAGE` constants.
+    pub value: u8,
+}
+
+impl DataTypeKind {
+    pub const SERVICE: u8 = 0;
+    pub const MESSAGE: u8 = 1;
+}
+
+/// Request for the details of a data type, `uavcan.protocol.GetDataTypeInfo`.
+///
+/// If `name` is empty, the data type is identified by `id` and `kind`, otherwise by `name`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x88c93dc9f68d24fc"]
+#[DataTypeSignature = "0x1b283338a7bed2d8"]
+pub struct GetDataTypeInfoRequest {
+    pub id: u16,
+    pub kind: DataTypeKind,
+
+    /// Full data type name, e.g. "uavcan.protocol.GetDataTypeInfo".
+    pub name: Dynamic<[u8; 80]>,
+}
+
+/// The details of a data type.
+///
+/// `signature`, `id` and `kind` are only valid if `flags` contains `FLAG_KNOWN`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x88c93dc9f68d24fc"]
+#[DataTypeSignature = "0x1b283338a7bed2d8"]
+pub struct GetDataTypeInfoResponse {
+    pub signature: u64,
+    pub id: u16,
+    pub kind: DataTypeKind,
+
+    /// A combination of the `FLAG_*` constants.
+    pub flags: u8,
+
+    pub name: Dynamic<[u8; 80]>,
+}
+
+impl GetDataTypeInfoResponse {
+    pub const FLAG_KNOWN: u8 = 1;
+    pub const FLAG_SUBSCRIBED: u8 = 2;
+    pub const FLAG_PUBLISHING: u8 = 4;
+
+    /// Has the same value as `FLAG_PUBLISHING` in the specification.
+    pub const FLAG_SERVING: u8 = 4;
+}
+
+impl ::Request for GetDataTypeInfoRequest {
+    type RESPONSE = GetDataTypeInfoResponse;
+    const TYPE_ID: Option<u8> = Some(2);
+}
+
+impl ::Response for GetDataTypeInfoResponse {
+    type REQUEST = GetDataTypeInfoRequest;
+    const TYPE_ID: Option<u8> = Some(2);
+}
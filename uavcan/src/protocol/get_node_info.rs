@@ -0,0 +1,257 @@
+//! Node identification
+//!
+//! Every node should respond to `GetNodeInfo` requests with its current status and identity.
+//! The `NodeInfoServer` answers these requests based on the `NodeConfig` of the node.
+
+use {
+    Node,
+    NodeConfig,
+};
+
+use node::{
+    ServiceServer,
+    TransmitError,
+};
+
+use storage::{
+    Storage,
+    StorageError,
+    SubscriberStorageHandle,
+};
+
+use transfer::{
+    TransferInterface,
+    TransferFrame,
+};
+
+use types::*;
+
+use super::node_status::NodeStatus;
+
+/// Generic software version information, `uavcan.protocol.SoftwareVersion`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0xdd46fd376527fea1"]
+#[DataTypeSignature = "0xdd46fd376527fea1"]
+pub struct SoftwareVersion {
+    pub major: u8,
+    pub minor: u8,
+
+    /// Tells which of the optional fields are set, a combination of the `OPTIONAL_FIELD_FLAG_*` constants.
+    pub optional_field_flags: u8,
+
+    /// Version control system commit identifier, e.g. the short git hash.
+    pub vcs_commit: u32,
+
+    /// CRC-64-WE of the firmware image.
+    pub image_crc: u64,
+}
+
+impl SoftwareVersion {
+    pub const OPTIONAL_FIELD_FLAG_VCS_COMMIT: u8 = 1;
+    pub const OPTIONAL_FIELD_FLAG_IMAGE_CRC: u8 = 2;
+}
+
+/// Generic hardware version information, `uavcan.protocol.HardwareVersion`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x0ad5c4c933f4a0c4"]
+#[DataTypeSignature = "0x0ad5c4c933f4a0c4"]
+pub struct HardwareVersion {
+    pub major: u8,
+    pub minor: u8,
+
+    /// Unique ID of the hardware, all zeros if not available.
+    pub unique_id: [u8; 16],
+
+    pub certificate_of_authenticity: Dynamic<[u8; 255]>,
+}
+
+/// Request for full node information, `uavcan.protocol.GetNodeInfo`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GetNodeInfoRequest {}
+
+impl_empty_struct!(GetNodeInfoRequest, 0xa80d_c899_5053_e685, 0xee46_8a81_21c4_6a9e);
+
+/// Full node information.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0xa80dc8995053e685"]
+#[DataTypeSignature = "0xee468a8121c46a9e"]
+pub struct GetNodeInfoResponse {
+    /// Current node status, same as the last published `NodeStatus`.
+    pub status: NodeStatus,
+
+    pub software_version: SoftwareVersion,
+    pub hardware_version: HardwareVersion,
+
+    /// Human readable non-empty ASCII node name, e.g. "com.example.sensor".
+    pub name: Dynamic<[u8; 80]>,
+}
+
+impl ::Request for GetNodeInfoRequest {
+    type RESPONSE = GetNodeInfoResponse;
+    const TYPE_ID: Option<u8> = Some(1);
+}
+
+impl ::Response for GetNodeInfoResponse {
+    type REQUEST = GetNodeInfoRequest;
+    const TYPE_ID: Option<u8> = Some(1);
+}
+
+
+/// Responds to `GetNodeInfo` requests with the identity from a `NodeConfig`.
+///
+/// The server is bound to the `NodeID` the node had when the server was created,
+/// it must be recreated if the `NodeID` changes.
+#[derive(Debug)]
+pub struct NodeInfoServer<F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    server: ServiceServer<GetNodeInfoRequest, F, H>,
+    software_version: SoftwareVersion,
+    hardware_version: HardwareVersion,
+    name: Dynamic<[u8; 80]>,
+}
+
+impl<F: TransferFrame, H: SubscriberStorageHandle<F>> NodeInfoServer<F, H> {
+    /// Creates a server for `node` identifying it as described by `config`.
+    ///
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage of `node` can't hold the subscription.
+    pub fn new<I, S, N>(node: &N, config: &NodeConfig) -> Result<Self, StorageError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        let mut optional_field_flags = 0;
+        if config.vcs_commit.is_some() {
+            optional_field_flags |= SoftwareVersion::OPTIONAL_FIELD_FLAG_VCS_COMMIT;
+        }
+        if config.image_crc.is_some() {
+            optional_field_flags |= SoftwareVersion::OPTIONAL_FIELD_FLAG_IMAGE_CRC;
+        }
+
+        let name = config.name.as_bytes();
+        let certificate = config.certificate_of_authenticity;
+
+        Ok(NodeInfoServer {
+            server: node.serve()?,
+            software_version: SoftwareVersion {
+                major: config.software_version.major,
+                minor: config.software_version.minor,
+                optional_field_flags,
+                vcs_commit: config.vcs_commit.unwrap_or(0),
+                image_crc: config.image_crc.unwrap_or(0),
+            },
+            hardware_version: HardwareVersion {
+                major: config.hardware_version.major,
+                minor: config.hardware_version.minor,
+                unique_id: config.unique_id,
+                certificate_of_authenticity: Dynamic::<[u8; 255]>::with_data(&certificate[..certificate.len().min(255)]),
+            },
+            name: Dynamic::<[u8; 80]>::with_data(&name[..name.len().min(80)]),
+        })
+    }
+
+    /// Returns the server receiving the `GetNodeInfo` requests.
+    pub(crate) fn server(&self) -> &ServiceServer<GetNodeInfoRequest, F, H> {
+        &self.server
+    }
+
+    /// Returns the response describing the node with the given `status`.
+    pub fn response(&self, status: NodeStatus) -> GetNodeInfoResponse {
+        GetNodeInfoResponse {
+            status,
+            software_version: self.software_version.clone(),
+            hardware_version: self.hardware_version.clone(),
+            name: self.name.clone(),
+        }
+    }
+
+    /// Responds to all received requests, reporting `status` as the current status of the node.
+    ///
+    /// A failed response doesn't stop the remaining ones, the first error is returned.
+    pub fn tick<I, S, N>(&self, node: &N, status: NodeStatus) -> Result<(), TransmitError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        let mut result = Ok(());
+        while let Some(received) = self.server.receive() {
+            if let Ok(request) = received {
+                result = result.and(node.respond(request.token, self.response(status.clone())));
+            }
+        }
+        result
+    }
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use node::*;
+    use time::Timestamp;
+
+    use tests::{
+        TestInterface,
+        TestNode,
+        test_node,
+        exchange,
+    };
+
+    use protocol::node_status::*;
+    use protocol::get_node_info::*;
+
+    #[test]
+    fn respond_with_config() {
+        let interface = TestInterface::default();
+        let config = NodeConfig {
+            id: Some(NodeID::new(10)),
+            name: "com.example.sensor",
+            software_version: Version{major: 2, minor: 1},
+            vcs_commit: Some(0xdead_beef),
+            hardware_version: Version{major: 1, minor: 0},
+            unique_id: [3; 16],
+            certificate_of_authenticity: &[1, 2, 3],
+            ..NodeConfig::default()
+        };
+        let node: TestNode = SimpleNode::new(&interface, config.clone());
+        let server = NodeInfoServer::new(&node, &config).unwrap();
+
+        let client_interface = TestInterface::default();
+        let client = test_node(&client_interface, Some(42));
+        let handle = client.request(NodeID::new(10), GetNodeInfoRequest{}, Timestamp::from_micros(1_000_000)).unwrap();
+        exchange(&[(&client, &client_interface), (&node, &interface)]);
+
+        let mut publisher = NodeStatusPublisher::new(Timestamp::from_micros(0));
+        publisher.set_mode(Mode::Operational);
+        server.tick(&node, publisher.status(Timestamp::from_micros(7_000_000))).unwrap();
+        exchange(&[(&client, &client_interface), (&node, &interface)]);
+
+        let response = handle.receive(Timestamp::from_micros(0)).unwrap().unwrap();
+        assert_eq!(response.status.uptime_sec, 7);
+        assert_eq!(Mode::from(response.status.mode), Mode::Operational);
+        assert_eq!(response.software_version, SoftwareVersion {
+            major: 2,
+            minor: 1,
+            optional_field_flags: SoftwareVersion::OPTIONAL_FIELD_FLAG_VCS_COMMIT,
+            vcs_commit: 0xdead_beef,
+            image_crc: 0,
+        });
+        assert_eq!(response.hardware_version.unique_id, [3; 16]);
+        assert_eq!(response.hardware_version.certificate_of_authenticity.as_ref(), &[1, 2, 3]);
+        assert_eq!(response.name.as_ref(), b"com.example.sensor");
+    }
+
+    #[test]
+    fn long_name_is_truncated() {
+        let interface = TestInterface::default();
+        let config = NodeConfig {
+            id: Some(NodeID::new(10)),
+            name: "com.example.a.node.name.that.is.much.longer.than.the.eighty.characters.allowed.by.uavcan",
+            ..NodeConfig::default()
+        };
+        let node: TestNode = SimpleNode::new(&interface, config.clone());
+        let server = NodeInfoServer::new(&node, &config).unwrap();
+
+        let response = server.response(NodeStatusPublisher::new(Timestamp::from_micros(0)).status(Timestamp::from_micros(0)));
+        assert_eq!(response.name.as_ref(), &config.name.as_bytes()[..80]);
+        assert_eq!(response.software_version.optional_field_flags, 0);
+    }
+}
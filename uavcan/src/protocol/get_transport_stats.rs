@@ -0,0 +1,44 @@
+//! Transport layer statistics
+//!
+//! Counters are reset when the node restarts and wrap around on overflow.
+
+use types::*;
+
+/// Statistics of a single CAN interface, `uavcan.protocol.CANIfaceStats`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x13b106f0c44ca350"]
+#[DataTypeSignature = "0x13b106f0c44ca350"]
+pub struct CanIfaceStats {
+    pub frames_tx: u48,
+    pub frames_rx: u48,
+    pub errors: u48,
+}
+
+/// Request for the transport statistics of a node, `uavcan.protocol.GetTransportStats`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GetTransportStatsRequest {}
+
+impl_empty_struct!(GetTransportStatsRequest, 0x31c7_f654_531b_61b3, 0xbe6f_76a7_ec31_2b04);
+
+/// The transport statistics of a node.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x31c7f654531b61b3"]
+#[DataTypeSignature = "0xbe6f76a7ec312b04"]
+pub struct GetTransportStatsResponse {
+    pub transfers_tx: u48,
+    pub transfers_rx: u48,
+    pub transfer_errors: u48,
+
+    /// One entry per interface.
+    pub can_iface_stats: Dynamic<[CanIfaceStats; 3]>,
+}
+
+impl ::Request for GetTransportStatsRequest {
+    type RESPONSE = GetTransportStatsResponse;
+    const TYPE_ID: Option<u8> = Some(4);
+}
+
+impl ::Response for GetTransportStatsResponse {
+    type REQUEST = GetTransportStatsRequest;
+    const TYPE_ID: Option<u8> = Some(4);
+}
@@ -0,0 +1,461 @@
+//! Network-wide time synchronization
+//!
+//! A time sync master broadcasts `GlobalTimeSync` periodically. Every message carries the time
+//! the previous message was transmitted, which together with the time the previous message was received
+//! lets the slaves compute the offset between their own clock and the clock of the master.
+//!
+//! The accuracy of the synchronization depends on the interfaces timestamping transmitted and received frames
+//! (see `TransferInterface::transmission_timestamp` and `TransferFrame::timestamp`).
+
+use lib::core::cmp;
+
+use {
+    Node,
+    NodeID,
+};
+
+use node::{
+    Subscriber,
+    ReceivedTransfer,
+    TransferSource,
+    TransmitError,
+};
+
+use storage::{
+    Storage,
+    StorageError,
+    SubscriberStorageHandle,
+};
+
+use transfer::{
+    TransferInterface,
+    TransferFrame,
+    TransferID,
+    TransferPriority,
+};
+
+use time::{
+    Duration,
+    Timestamp,
+};
+
+use types::*;
+
+/// Global time synchronization message, `uavcan.protocol.GlobalTimeSync`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x20271116a793c2db"]
+#[DataTypeSignature = "0x20271116a793c2db"]
+pub struct GlobalTimeSync {
+    /// The time the previous `GlobalTimeSync` was transmitted by the master, or zero if unknown.
+    pub previous_transmission_timestamp_usec: u56,
+}
+
+impl GlobalTimeSync {
+    pub const MAX_BROADCASTING_PERIOD_MS: u16 = 1100;
+    pub const MIN_BROADCASTING_PERIOD_MS: u16 = 40;
+
+    /// If the master haven't been heard from in this amount of time, another master may take over.
+    pub const RECOMMENDED_BROADCASTER_TIMEOUT_MS: u16 = 2200;
+}
+
+impl ::Message for GlobalTimeSync {
+    const TYPE_ID: Option<u16> = Some(4);
+    const PRIORITY: TransferPriority = TransferPriority::HIGH;
+}
+
+/// Publishes the clock of this node as the network time.
+///
+/// Several masters can exist on the network for redundancy, but only the one with the lowest `NodeID` is active.
+/// A master goes silent as long as it hears from a master with a lower `NodeID`.
+///
+/// `tick` must be called periodically (preferably more often than every 10ms) and after receptions are flushed.
+#[derive(Debug)]
+pub struct GlobalTimeSyncMaster<F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    subscriber: Subscriber<GlobalTimeSync, F, H>,
+    period: Duration,
+    next_broadcast: Timestamp,
+    suppressed_until: Timestamp,
+    published: bool,
+}
+
+impl<F: TransferFrame, H: SubscriberStorageHandle<F>> GlobalTimeSyncMaster<F, H> {
+    /// Creates a new master broadcasting once every second.
+    ///
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage of `node` can't hold the subscription.
+    pub fn new<I, S, N>(node: &N, now: Timestamp) -> Result<Self, StorageError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        Ok(GlobalTimeSyncMaster {
+            subscriber: node.subscribe()?,
+            period: Duration::from_millis(1000),
+            next_broadcast: now,
+            suppressed_until: now,
+            published: false,
+        })
+    }
+
+    /// Returns the broadcasting period.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Changes the broadcasting period.
+    ///
+    /// The period is clamped to the range allowed by the specification.
+    pub fn set_period(&mut self, period: Duration) {
+        let min = Duration::from_millis(u64::from(GlobalTimeSync::MIN_BROADCASTING_PERIOD_MS));
+        let max = Duration::from_millis(u64::from(GlobalTimeSync::MAX_BROADCASTING_PERIOD_MS));
+        self.period = cmp::min(cmp::max(period, min), max);
+    }
+
+    /// Returns true if this master is not suppressed by a master with a lower `NodeID`.
+    pub fn is_active(&self, now: Timestamp) -> bool {
+        now >= self.suppressed_until
+    }
+
+    /// Handles messages from other masters and broadcasts `GlobalTimeSync` when it's time to do so.
+    ///
+    /// Anonymous nodes can't be masters, nothing is broadcast until the node has a `NodeID`.
+    pub fn tick<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), TransmitError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        let id = match node.id() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        while let Some(result) = self.subscriber.receive() {
+            if let Ok(ReceivedTransfer{source: TransferSource::Node(source), ..}) = result {
+                if u8::from(source) < u8::from(id) {
+                    self.suppressed_until = now + Duration::from_millis(u64::from(GlobalTimeSync::RECOMMENDED_BROADCASTER_TIMEOUT_MS));
+                }
+            }
+        }
+
+        if !self.is_active(now) {
+            self.published = false;
+            return Ok(());
+        }
+
+        if now < self.next_broadcast {
+            return Ok(());
+        }
+
+        // The network time is UTC if the master knows it
+        let previous_transmission = if self.published {
+            node.transmission_timestamp::<GlobalTimeSync>().map(|timestamp| timestamp.utc.unwrap_or(timestamp.monotonic))
+        } else {
+            None
+        };
+
+        // Keep a steady rate, but don't try to catch up after the application stalled for more than a period
+        let scheduled = self.next_broadcast + self.period;
+        self.next_broadcast = if scheduled > now {
+            scheduled
+        } else {
+            now + self.period
+        };
+        self.published = true;
+        node.broadcast(GlobalTimeSync {
+            previous_transmission_timestamp_usec: u56::new(previous_transmission.map_or(0, |t| t.as_micros())),
+        })
+    }
+}
+
+/// Tracks the network time published by the active time sync master.
+///
+/// The slave estimates both the offset and the drift between the local clock and the clock of the master.
+/// If the interface doesn't timestamp received frames, the time `tick` is called is used instead,
+/// `tick` should then be called as soon as possible after receptions are flushed.
+#[derive(Debug)]
+pub struct GlobalTimeSyncSlave<F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    subscriber: Subscriber<GlobalTimeSync, F, H>,
+    master: Option<NodeID>,
+    last_master_message: Timestamp,
+    previous: Option<(TransferID, Timestamp)>,
+    sample: Option<(Timestamp, i64)>,
+    drift: Option<f32>,
+}
+
+impl<F: TransferFrame, H: SubscriberStorageHandle<F>> GlobalTimeSyncSlave<F, H> {
+    /// The weight of a new drift measurement in the drift estimate.
+    const DRIFT_GAIN: f32 = 0.25;
+
+    /// Creates a new slave subscribing to `GlobalTimeSync` through `node`.
+    ///
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage of `node` can't hold the subscription.
+    pub fn new<I, S, N>(node: &N) -> Result<Self, StorageError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        Ok(GlobalTimeSyncSlave {
+            subscriber: node.subscribe()?,
+            master: None,
+            last_master_message: Timestamp::default(),
+            previous: None,
+            sample: None,
+            drift: None,
+        })
+    }
+
+    /// Returns the `NodeID` of the master the slave is synchronized to.
+    pub fn master(&self) -> Option<NodeID> {
+        self.master
+    }
+
+    /// Returns true if the slave is synchronized and the master have been heard from recently.
+    pub fn is_active(&self, now: Timestamp) -> bool {
+        self.sample.is_some()
+            && now.duration_since(self.last_master_message) < Duration::from_millis(u64::from(GlobalTimeSync::RECOMMENDED_BROADCASTER_TIMEOUT_MS))
+    }
+
+    /// Returns the estimated difference between the clock of the master and the local clock in microseconds.
+    pub fn offset(&self) -> Option<i64> {
+        Some(self.sample?.1)
+    }
+
+    /// Returns the estimated drift of the clock of the master relative to the local clock in parts per million.
+    pub fn drift_ppm(&self) -> Option<f32> {
+        Some(self.drift? * 1_000_000.0)
+    }
+
+    /// Converts a local timestamp to network time.
+    ///
+    /// Returns `None` until the slave is synchronized to a master.
+    pub fn network_time(&self, local: Timestamp) -> Option<Timestamp> {
+        let (sample_time, offset) = self.sample?;
+        let elapsed = local.as_micros() as i64 - sample_time.as_micros() as i64;
+        let correction = self.drift.map_or(0, |drift| (drift * elapsed as f32) as i64);
+        let time = local.as_micros() as i64 + offset + correction;
+        Some(Timestamp::from_micros(cmp::max(time, 0) as u64))
+    }
+
+    /// Processes received `GlobalTimeSync` messages.
+    pub fn tick(&mut self, now: Timestamp) {
+        let timeout = Duration::from_millis(u64::from(GlobalTimeSync::RECOMMENDED_BROADCASTER_TIMEOUT_MS));
+        let max_period = Duration::from_millis(u64::from(GlobalTimeSync::MAX_BROADCASTING_PERIOD_MS));
+
+        while let Some(result) = self.subscriber.receive() {
+            let transfer = match result {
+                Ok(transfer) => transfer,
+                Err(_) => continue,
+            };
+
+            let source = match transfer.source.node_id() {
+                Some(source) => source,
+                None => continue,
+            };
+            let transfer_id = transfer.transfer_id;
+            let received = transfer.timestamp.map_or(now, |timestamp| timestamp.monotonic);
+
+            match self.master {
+                Some(master) if master == source => (),
+                Some(master) if u8::from(source) > u8::from(master) && received.duration_since(self.last_master_message) < timeout => continue,
+                _ => {
+                    // The clock of a new master has nothing to do with the clock of the previous one
+                    self.master = Some(source);
+                    self.previous = None;
+                    self.sample = None;
+                    self.drift = None;
+                },
+            }
+            self.last_master_message = received;
+
+            let previous_transmission = u64::from(transfer.body.previous_transmission_timestamp_usec);
+            if let Some((previous_id, previous_received)) = self.previous {
+                // The timestamp is only usable if it belongs to the previous message received
+                if previous_id.next() == transfer_id && received.duration_since(previous_received) < max_period && previous_transmission != 0 {
+                    self.add_sample(previous_received, previous_transmission as i64 - previous_received.as_micros() as i64);
+                }
+            }
+            self.previous = Some((transfer_id, received));
+        }
+    }
+
+    fn add_sample(&mut self, time: Timestamp, offset: i64) {
+        if let Some((last_time, last_offset)) = self.sample {
+            let elapsed = time.as_micros() as i64 - last_time.as_micros() as i64;
+            if elapsed > 0 {
+                let measured = (offset - last_offset) as f32 / elapsed as f32;
+                self.drift = Some(match self.drift {
+                    Some(drift) => drift + Self::DRIFT_GAIN * (measured - drift),
+                    None => measured,
+                });
+            }
+        }
+        self.sample = Some((time, offset));
+    }
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use node::*;
+    use time::{
+        Timestamp,
+        FrameTimestamp,
+    };
+    use types::*;
+
+    use tests::{
+        TestInterface,
+        TestNode,
+        test_node,
+    };
+
+    use protocol::global_time_sync::*;
+
+    fn broadcasted_sync(interface: &TestInterface) -> Option<GlobalTimeSync> {
+        let frame = interface.tx.borrow_mut().pop_front()?;
+        let mut assembler = ::frame_assembler::FrameAssembler::new();
+        assembler.add_transfer_frame(frame).unwrap();
+        Some(assembler.build().unwrap().body)
+    }
+
+    fn sync(master: &TestNode, previous_transmission: u64) {
+        master.broadcast(GlobalTimeSync {
+            previous_transmission_timestamp_usec: u56::new(previous_transmission),
+        }).unwrap();
+        master.flush_transmissions();
+    }
+
+    #[test]
+    fn master_broadcast() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, Some(10));
+        let mut master = GlobalTimeSyncMaster::new(&node, Timestamp::from_micros(0)).unwrap();
+
+        master.tick(&node, Timestamp::from_micros(0)).unwrap();
+        node.flush_transmissions();
+        assert_eq!(u64::from(broadcasted_sync(&interface).unwrap().previous_transmission_timestamp_usec), 0);
+
+        interface.tx_timestamp.set(Some(FrameTimestamp::from_monotonic(Timestamp::from_micros(1_234))));
+        master.tick(&node, Timestamp::from_micros(999_999)).unwrap();
+        node.flush_transmissions();
+        assert_eq!(broadcasted_sync(&interface), None);
+
+        master.tick(&node, Timestamp::from_micros(1_000_000)).unwrap();
+        node.flush_transmissions();
+        assert_eq!(u64::from(broadcasted_sync(&interface).unwrap().previous_transmission_timestamp_usec), 1_234);
+
+        // After a stall the next broadcast is a full period later
+        master.tick(&node, Timestamp::from_micros(3_500_000)).unwrap();
+        node.flush_transmissions();
+        assert!(broadcasted_sync(&interface).is_some());
+        master.tick(&node, Timestamp::from_micros(3_500_001)).unwrap();
+        master.tick(&node, Timestamp::from_micros(4_499_999)).unwrap();
+        node.flush_transmissions();
+        assert_eq!(broadcasted_sync(&interface), None);
+
+        master.tick(&node, Timestamp::from_micros(4_500_000)).unwrap();
+        node.flush_transmissions();
+        assert!(broadcasted_sync(&interface).is_some());
+    }
+
+    #[test]
+    fn master_arbitration() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, Some(10));
+        let mut master = GlobalTimeSyncMaster::new(&node, Timestamp::from_micros(0)).unwrap();
+
+        let other_interface = TestInterface::default();
+        let other = test_node(&other_interface, Some(5));
+
+        sync(&other, 0);
+        other_interface.transfer_to(&interface);
+        node.flush_receptions();
+
+        master.tick(&node, Timestamp::from_micros(0)).unwrap();
+        node.flush_transmissions();
+        assert!(!master.is_active(Timestamp::from_micros(0)));
+        assert_eq!(broadcasted_sync(&interface), None);
+
+        // The other master goes silent
+        master.tick(&node, Timestamp::from_micros(2_200_000)).unwrap();
+        node.flush_transmissions();
+        assert!(master.is_active(Timestamp::from_micros(2_200_000)));
+        assert!(broadcasted_sync(&interface).is_some());
+    }
+
+    #[test]
+    fn slave_synchronization() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, Some(10));
+        let mut slave = GlobalTimeSyncSlave::new(&node).unwrap();
+
+        let master_interface = TestInterface::default();
+        let master = test_node(&master_interface, Some(5));
+
+        // The master clock is 50s ahead, and runs 100ppm faster than the local clock
+        for &(local, previous_transmission) in [(1_000_000, 0), (2_000_000, 51_000_000), (3_000_000, 52_000_100)].iter() {
+            sync(&master, previous_transmission);
+            master_interface.transfer_to(&interface);
+            node.flush_receptions();
+            slave.tick(Timestamp::from_micros(local));
+
+            if local == 2_000_000 {
+                assert_eq!(slave.offset(), Some(50_000_000));
+                assert_eq!(slave.network_time(Timestamp::from_micros(2_500_000)), Some(Timestamp::from_micros(52_500_000)));
+            }
+        }
+
+        assert_eq!(slave.master(), Some(NodeID::new(5)));
+        assert!(slave.is_active(Timestamp::from_micros(3_000_000)));
+        assert_eq!(slave.offset(), Some(50_000_100));
+        assert!((slave.drift_ppm().unwrap() - 100.0).abs() < 0.01);
+
+        let network_time = slave.network_time(Timestamp::from_micros(3_000_000)).unwrap().as_micros();
+        assert!((53_000_199..=53_000_200).contains(&network_time));
+    }
+
+    #[test]
+    fn slave_requires_consecutive_messages() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, Some(10));
+        let mut slave = GlobalTimeSyncSlave::new(&node).unwrap();
+
+        let master_interface = TestInterface::default();
+        let master = test_node(&master_interface, Some(5));
+
+        sync(&master, 0);
+        master_interface.transfer_to(&interface);
+        node.flush_receptions();
+        slave.tick(Timestamp::from_micros(1_000_000));
+
+        // A lost message
+        sync(&master, 51_000_000);
+        master_interface.tx.borrow_mut().clear();
+
+        sync(&master, 52_000_000);
+        master_interface.transfer_to(&interface);
+        node.flush_receptions();
+        slave.tick(Timestamp::from_micros(3_000_000));
+        assert_eq!(slave.offset(), None);
+        assert_eq!(slave.network_time(Timestamp::from_micros(3_000_000)), None);
+    }
+
+    #[test]
+    fn slave_master_arbitration() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, Some(10));
+        let mut slave = GlobalTimeSyncSlave::new(&node).unwrap();
+
+        let masters: Vec<(TestInterface, u8)> = vec![(TestInterface::default(), 5), (TestInterface::default(), 7), (TestInterface::default(), 3)];
+        for &(ref master_interface, id) in masters.iter() {
+            let master = test_node(master_interface, Some(id));
+            sync(&master, 0);
+            master_interface.transfer_to(&interface);
+            node.flush_receptions();
+            slave.tick(Timestamp::from_micros(1_000_000));
+
+            // A master with higher node ID than the current master is ignored
+            assert_eq!(slave.master(), Some(NodeID::new(if id == 7 { 5 } else { id })));
+        }
+    }
+}
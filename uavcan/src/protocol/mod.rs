@@ -0,0 +1,205 @@
+//! Application level functionality defined by the Uavcan specification
+//!
+//! The module structure mirrors the `uavcan.protocol` DSDL namespace.
+//! As the core crate can't depend on the compiled DSDL definitions,
+//! the data types needed to implement the protocol features are defined here as well.
+
+/// Implements `Struct` for a data type without fields.
+///
+/// `#[derive(UavcanStruct)]` requires at least one field, which most service requests don't have.
+macro_rules! impl_empty_struct {
+    ($name:ident, $dsdl_signature:expr, $data_type_signature:expr) => {
+        impl ::Struct for $name {
+            const DSDL_SIGNATURE: u64 = $dsdl_signature;
+            const DATA_TYPE_SIGNATURE: u64 = $data_type_signature;
+        }
+
+        impl ::Serializable for $name {
+            const BIT_LENGTH_MIN: usize = 0;
+            const FLATTENED_FIELDS_NUMBER: usize = 0;
+
+            fn serialize(&self, _flattened_field: &mut usize, _bit: &mut usize, _last_field: bool, _buffer: &mut ::SerializationBuffer) -> ::SerializationResult {
+                ::SerializationResult::Finished
+            }
+
+            fn deserialize(&mut self, _flattened_field: &mut usize, _bit: &mut usize, _last_field: bool, _buffer: &mut ::DeserializationBuffer) -> ::DeserializationResult {
+                ::DeserializationResult::Finished
+            }
+        }
+    };
+}
+
+/// Implements `Struct` for a DSDL union represented as an enum with one single field variant per union field.
+///
+/// The derive macro doesn't support enums yet. A union is encoded as a tag of `$tag_bits` bits
+/// followed by the selected field, the tag of each variant is given explicitly.
+///
+/// While the tag is being decoded, the bits read so far are kept in the flattened field index,
+/// so the fields of the selected variant start at flattened field `1 << $tag_bits`.
+/// Unknown tags are decoded as the first variant.
+macro_rules! impl_union {
+    ($name:ident, $tag_bits:expr, $dsdl_signature:expr, $data_type_signature:expr, {$($tag:expr => $variant:ident($field_type:ty)),+ $(,)*}) => {
+        impl ::Struct for $name {
+            const DSDL_SIGNATURE: u64 = $dsdl_signature;
+            const DATA_TYPE_SIGNATURE: u64 = $data_type_signature;
+        }
+
+        impl ::Serializable for $name {
+            const BIT_LENGTH_MIN: usize = {
+                let lengths = [$(<$field_type as ::Serializable>::BIT_LENGTH_MIN),+];
+                let mut min = lengths[0];
+                let mut i = 1;
+                while i < lengths.len() {
+                    if lengths[i] < min {
+                        min = lengths[i];
+                    }
+                    i += 1;
+                }
+                $tag_bits + min
+            };
+
+            const FLATTENED_FIELDS_NUMBER: usize = {
+                let fields = [$(<$field_type as ::Serializable>::FLATTENED_FIELDS_NUMBER),+];
+                let mut max = 0;
+                let mut i = 0;
+                while i < fields.len() {
+                    if fields[i] > max {
+                        max = fields[i];
+                    }
+                    i += 1;
+                }
+                (1 << $tag_bits) + max
+            };
+
+            fn serialize(&self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut ::SerializationBuffer) -> ::SerializationResult {
+                let first_field = 1 << $tag_bits;
+
+                if *flattened_field < first_field {
+                    let tag: u64 = match *self {
+                        $($name::$variant(_) => $tag,)+
+                    };
+                    let tag_bits_remaining = $tag_bits - *bit;
+                    let buffer_bits_remaining = buffer.bits_remaining();
+                    if buffer_bits_remaining < tag_bits_remaining {
+                        if buffer_bits_remaining > 0 {
+                            buffer.push_bits(buffer_bits_remaining, tag >> *bit);
+                            *bit += buffer_bits_remaining;
+                        }
+                        return ::SerializationResult::BufferFull;
+                    }
+                    buffer.push_bits(tag_bits_remaining, tag >> *bit);
+                    *flattened_field = first_field;
+                    *bit = 0;
+                }
+
+                let mut field = *flattened_field - first_field;
+                let result = match *self {
+                    $($name::$variant(ref value) => ::Serializable::serialize(value, &mut field, bit, last_field, buffer),)+
+                };
+
+                if result == ::SerializationResult::Finished {
+                    *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+                    *bit = 0;
+                } else {
+                    *flattened_field = first_field + field;
+                }
+                result
+            }
+
+            fn deserialize(&mut self, flattened_field: &mut usize, bit: &mut usize, last_field: bool, buffer: &mut ::DeserializationBuffer) -> ::DeserializationResult {
+                let first_field = 1 << $tag_bits;
+
+                if *flattened_field < first_field {
+                    let tag_bits_remaining = $tag_bits - *bit;
+                    let buffer_len = buffer.bit_length();
+                    if buffer_len < tag_bits_remaining {
+                        if buffer_len > 0 {
+                            *flattened_field |= (buffer.pop_bits(buffer_len) << *bit) as usize;
+                            *bit += buffer_len;
+                        }
+                        return ::DeserializationResult::BufferInsufficient;
+                    }
+                    let tag = *flattened_field as u64 | (buffer.pop_bits(tag_bits_remaining) << *bit);
+                    // Fields are deserialized into zeroed memory, like in `Deserializer::new`
+                    *self = match tag {
+                        $($tag => $name::$variant(unsafe { ::lib::core::mem::zeroed() }),)+
+                        _ => impl_union!(@first $name, $($variant),+),
+                    };
+                    *flattened_field = first_field;
+                    *bit = 0;
+                }
+
+                let mut field = *flattened_field - first_field;
+                let result = match *self {
+                    $($name::$variant(ref mut value) => ::Serializable::deserialize(value, &mut field, bit, last_field, buffer),)+
+                };
+
+                if result == ::DeserializationResult::Finished {
+                    *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+                    *bit = 0;
+                } else {
+                    *flattened_field = first_field + field;
+                }
+                result
+            }
+        }
+    };
+    (@first $name:ident, $first:ident $(, $rest:ident)*) => {
+        $name::$first(unsafe { ::lib::core::mem::zeroed() })
+    };
+}
+
+pub mod dynamic_node_id;
+pub mod node_status;
+pub mod node_monitor;
+pub mod global_time_sync;
+pub mod get_node_info;
+pub mod get_data_type_info;
+pub mod restart_node;
+pub mod get_transport_stats;
+pub mod param;
+
+pub use self::node_status::{
+    NodeStatus,
+    NodeStatusPublisher,
+    Health,
+    Mode,
+    ReservedMode,
+};
+
+pub use self::node_monitor::{
+    NodeMonitor,
+    MonitoredNode,
+    NodeEvent,
+};
+
+pub use self::global_time_sync::{
+    GlobalTimeSync,
+    GlobalTimeSyncMaster,
+    GlobalTimeSyncSlave,
+};
+
+pub use self::get_node_info::{
+    GetNodeInfoRequest,
+    GetNodeInfoResponse,
+    SoftwareVersion,
+    HardwareVersion,
+    NodeInfoServer,
+};
+
+pub use self::get_data_type_info::{
+    GetDataTypeInfoRequest,
+    GetDataTypeInfoResponse,
+    DataTypeKind,
+};
+
+pub use self::restart_node::{
+    RestartNodeRequest,
+    RestartNodeResponse,
+};
+
+pub use self::get_transport_stats::{
+    GetTransportStatsRequest,
+    GetTransportStatsResponse,
+    CanIfaceStats,
+};
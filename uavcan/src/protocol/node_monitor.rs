@@ -0,0 +1,216 @@
+//! Network discovery
+//!
+//! All nodes with a `NodeID` broadcast `NodeStatus` periodically.
+//! The `NodeMonitor` listens to these broadcasts to keep track of which nodes are present on the network.
+
+use {
+    Node,
+    NodeID,
+};
+
+use node::Subscriber;
+
+use storage::{
+    Storage,
+    StorageError,
+    SubscriberStorageHandle,
+};
+
+use transfer::{
+    TransferInterface,
+    TransferFrame,
+};
+
+use time::{
+    Duration,
+    Timestamp,
+};
+
+use types::*;
+
+use super::node_status::{
+    NodeStatus,
+    Health,
+    Mode,
+};
+
+/// What the `NodeMonitor` knows about a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitoredNode {
+    /// When the last `NodeStatus` from the node was received.
+    pub last_seen: Timestamp,
+
+    /// False if no `NodeStatus` have been received in `NodeStatus::OFFLINE_TIMEOUT_MS`.
+    pub online: bool,
+
+    pub uptime_sec: u32,
+    pub health: Health,
+    pub mode: Mode,
+    pub sub_mode: u3,
+    pub vendor_specific_status_code: u16,
+}
+
+/// A change in the presence of a node on the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeEvent {
+    /// A node was seen for the first time, or came back after going offline.
+    Online(NodeID),
+    /// A node stopped publishing `NodeStatus`.
+    Offline(NodeID),
+    /// The uptime of a node went backwards, meaning that it have been restarted.
+    Restarted(NodeID),
+}
+
+/// Keeps a table of all nodes on the network by listening to `NodeStatus` broadcasts.
+///
+/// `tick` must be called periodically and after receptions are flushed.
+#[derive(Debug)]
+pub struct NodeMonitor<F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    subscriber: Subscriber<NodeStatus, F, H>,
+    nodes: [Option<MonitoredNode>; 128],
+}
+
+impl<F: TransferFrame, H: SubscriberStorageHandle<F>> NodeMonitor<F, H> {
+    /// Creates a new monitor subscribing to `NodeStatus` through `node`.
+    ///
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage of `node` can't hold the subscription.
+    pub fn new<I, S, N>(node: &N) -> Result<Self, StorageError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        Ok(NodeMonitor {
+            subscriber: node.subscribe()?,
+            nodes: [None; 128],
+        })
+    }
+
+    /// Returns what is known about the node with `id`, or `None` if it have never been seen.
+    pub fn get(&self, id: NodeID) -> Option<&MonitoredNode> {
+        self.nodes[u8::from(id) as usize].as_ref()
+    }
+
+    /// Returns true if the node with `id` is currently online.
+    pub fn is_online(&self, id: NodeID) -> bool {
+        self.get(id).map_or(false, |node| node.online)
+    }
+
+    /// Returns an iterator over all nodes that have been seen, including the ones that are offline.
+    pub fn nodes(&self) -> impl Iterator<Item=(NodeID, &MonitoredNode)> {
+        self.nodes.iter()
+            .enumerate()
+            .filter_map(|(id, node)| node.as_ref().map(|node| (NodeID::new(id as u8), node)))
+    }
+
+    /// Processes received `NodeStatus` messages and marks nodes offline when they time out.
+    ///
+    /// `on_event` is called for every change in the presence of a node.
+    pub fn tick<E: FnMut(NodeEvent)>(&mut self, now: Timestamp, mut on_event: E) {
+        while let Some(result) = self.subscriber.receive() {
+            let transfer = match result {
+                Ok(transfer) => transfer,
+                Err(_) => continue,
+            };
+
+            let id = match transfer.source.node_id() {
+                Some(id) => id,
+                // Anonymous nodes can't publish NodeStatus
+                None => continue,
+            };
+
+            let status = transfer.body;
+            let entry = &mut self.nodes[u8::from(id) as usize];
+            match *entry {
+                Some(ref previous) if previous.online => if status.uptime_sec < previous.uptime_sec {
+                    on_event(NodeEvent::Restarted(id));
+                },
+                _ => on_event(NodeEvent::Online(id)),
+            }
+
+            *entry = Some(MonitoredNode {
+                last_seen: now,
+                online: true,
+                uptime_sec: status.uptime_sec,
+                health: status.health.into(),
+                mode: status.mode.into(),
+                sub_mode: status.sub_mode,
+                vendor_specific_status_code: status.vendor_specific_status_code,
+            });
+        }
+
+        let timeout = Duration::from_millis(u64::from(NodeStatus::OFFLINE_TIMEOUT_MS));
+        for (id, entry) in self.nodes.iter_mut().enumerate() {
+            if let Some(ref mut node) = *entry {
+                if node.online && now.duration_since(node.last_seen) > timeout {
+                    node.online = false;
+                    on_event(NodeEvent::Offline(NodeID::new(id as u8)));
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use node::*;
+    use time::Timestamp;
+
+    use tests::{
+        TestInterface,
+        test_node,
+        exchange,
+    };
+
+    use protocol::node_status::*;
+    use protocol::node_monitor::*;
+
+    #[test]
+    fn node_presence() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, Some(10));
+        let mut monitor = NodeMonitor::new(&node).unwrap();
+
+        let remote_interface = TestInterface::default();
+        let remote = test_node(&remote_interface, Some(42));
+
+        let mut events = Vec::new();
+        let mut publisher = NodeStatusPublisher::new(Timestamp::from_micros(0));
+        publisher.set_health(Health::Error);
+
+        for &time in [5_000_000, 6_000_000].iter() {
+            publisher.tick(&remote, Timestamp::from_micros(time)).unwrap();
+            exchange(&[(&remote, &remote_interface), (&node, &interface)]);
+            monitor.tick(Timestamp::from_micros(time), |event| events.push(event));
+        }
+
+        assert_eq!(events, vec![NodeEvent::Online(NodeID::new(42))]);
+        assert!(monitor.is_online(NodeID::new(42)));
+        assert!(!monitor.is_online(NodeID::new(43)));
+
+        let status = monitor.get(NodeID::new(42)).unwrap();
+        assert_eq!(status.uptime_sec, 6);
+        assert_eq!(status.health, Health::Error);
+        assert_eq!(status.mode, Mode::Initialization);
+        assert_eq!(status.last_seen, Timestamp::from_micros(6_000_000));
+        assert_eq!(monitor.nodes().count(), 1);
+
+        // The remote restarts
+        let mut publisher = NodeStatusPublisher::new(Timestamp::from_micros(6_500_000));
+        publisher.tick(&remote, Timestamp::from_micros(7_000_000)).unwrap();
+        exchange(&[(&remote, &remote_interface), (&node, &interface)]);
+        events.clear();
+        monitor.tick(Timestamp::from_micros(7_000_000), |event| events.push(event));
+        assert_eq!(events, vec![NodeEvent::Restarted(NodeID::new(42))]);
+
+        // And goes silent
+        events.clear();
+        monitor.tick(Timestamp::from_micros(10_000_000), |event| events.push(event));
+        assert!(events.is_empty());
+        monitor.tick(Timestamp::from_micros(10_000_001), |event| events.push(event));
+        assert_eq!(events, vec![NodeEvent::Offline(NodeID::new(42))]);
+        assert!(!monitor.is_online(NodeID::new(42)));
+        assert_eq!(monitor.nodes().count(), 1);
+    }
+}
@@ -0,0 +1,384 @@
+//! Node status reporting
+//!
+//! Every node with a `NodeID` must broadcast `NodeStatus` at least every `NodeStatus::MAX_BROADCASTING_PERIOD_MS`.
+//! The `NodeStatusPublisher` keeps track of the status of the node and takes care of the broadcasting.
+
+use lib::core::cmp;
+
+use Node;
+
+use node::TransmitError;
+
+use storage::Storage;
+
+use transfer::TransferInterface;
+
+use time::{
+    Duration,
+    Timestamp,
+};
+
+use types::*;
+
+/// Abstract node status information, `uavcan.protocol.NodeStatus`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x0f0868d0c1a7c6f1"]
+#[DataTypeSignature = "0x0f0868d0c1a7c6f1"]
+pub struct NodeStatus {
+    /// Uptime counter, should never overflow.
+    pub uptime_sec: u32,
+
+    /// Abstract node health, one of the `HEALTH_*` constants.
+    pub health: u2,
+
+    /// Current mode, one of the `MODE_*` constants.
+    pub mode: u3,
+
+    /// Not used currently, keep zero when publishing, ignore when receiving.
+    pub sub_mode: u3,
+
+    /// Optional, vendor-specific node status code.
+    pub vendor_specific_status_code: u16,
+}
+
+impl NodeStatus {
+    pub const MAX_BROADCASTING_PERIOD_MS: u16 = 1000;
+    pub const MIN_BROADCASTING_PERIOD_MS: u16 = 2;
+
+    /// If a node fails to publish this message in this amount of time, it should be considered offline.
+    pub const OFFLINE_TIMEOUT_MS: u16 = 3000;
+
+    pub const HEALTH_OK: u8 = 0;
+    pub const HEALTH_WARNING: u8 = 1;
+    pub const HEALTH_ERROR: u8 = 2;
+    pub const HEALTH_CRITICAL: u8 = 3;
+
+    pub const MODE_OPERATIONAL: u8 = 0;
+    pub const MODE_INITIALIZATION: u8 = 1;
+    pub const MODE_MAINTENANCE: u8 = 2;
+    pub const MODE_SOFTWARE_UPDATE: u8 = 3;
+    pub const MODE_OFFLINE: u8 = 7;
+}
+
+impl ::Message for NodeStatus {
+    const TYPE_ID: Option<u16> = Some(341);
+}
+
+/// Abstract node health.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    /// The node is functioning properly.
+    Ok,
+    /// A critical parameter went out of range or the node encountered a minor failure.
+    Warning,
+    /// The node encountered a major failure.
+    Error,
+    /// The node suffered a fatal malfunction.
+    Critical,
+}
+
+impl From<u2> for Health {
+    fn from(health: u2) -> Self {
+        match u8::from(health) {
+            NodeStatus::HEALTH_OK => Health::Ok,
+            NodeStatus::HEALTH_WARNING => Health::Warning,
+            NodeStatus::HEALTH_ERROR => Health::Error,
+            _ => Health::Critical,
+        }
+    }
+}
+
+impl From<Health> for u2 {
+    fn from(health: Health) -> Self {
+        u2::new(match health {
+            Health::Ok => NodeStatus::HEALTH_OK,
+            Health::Warning => NodeStatus::HEALTH_WARNING,
+            Health::Error => NodeStatus::HEALTH_ERROR,
+            Health::Critical => NodeStatus::HEALTH_CRITICAL,
+        })
+    }
+}
+
+/// The operating mode of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Normal operating mode.
+    Operational,
+    /// Initialization is in progress, this mode is entered immediately after startup.
+    Initialization,
+    /// E.g. calibration, the bootloader is running, etc.
+    Maintenance,
+    /// New software/firmware is being loaded.
+    SoftwareUpdate,
+    /// The node is no longer available.
+    Offline,
+    /// A mode value reserved by the specification.
+    Reserved(ReservedMode),
+}
+
+/// A mode value reserved by the specification, one of 4, 5 or 6.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReservedMode(u8);
+
+impl ReservedMode {
+    /// Creates a `ReservedMode`, or returns `None` if `value` is not a reserved mode value.
+    pub fn new(value: u8) -> Option<Self> {
+        match value {
+            4..=6 => Some(ReservedMode(value)),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw mode value.
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl From<u3> for Mode {
+    fn from(mode: u3) -> Self {
+        match u8::from(mode) {
+            NodeStatus::MODE_OPERATIONAL => Mode::Operational,
+            NodeStatus::MODE_INITIALIZATION => Mode::Initialization,
+            NodeStatus::MODE_MAINTENANCE => Mode::Maintenance,
+            NodeStatus::MODE_SOFTWARE_UPDATE => Mode::SoftwareUpdate,
+            NodeStatus::MODE_OFFLINE => Mode::Offline,
+            x => Mode::Reserved(ReservedMode(x)),
+        }
+    }
+}
+
+impl From<Mode> for u3 {
+    fn from(mode: Mode) -> Self {
+        u3::new(match mode {
+            Mode::Operational => NodeStatus::MODE_OPERATIONAL,
+            Mode::Initialization => NodeStatus::MODE_INITIALIZATION,
+            Mode::Maintenance => NodeStatus::MODE_MAINTENANCE,
+            Mode::SoftwareUpdate => NodeStatus::MODE_SOFTWARE_UPDATE,
+            Mode::Offline => NodeStatus::MODE_OFFLINE,
+            Mode::Reserved(x) => x.value(),
+        })
+    }
+}
+
+/// Keeps track of the status of the local node and broadcasts it as `NodeStatus`.
+///
+/// The publisher doesn't have any notion of time by itself.
+/// `tick` must be called periodically, at least as often as the broadcasting period.
+///
+/// A node starts out in `Mode::Initialization` with `Health::Ok`,
+/// the application should change the mode to `Mode::Operational` when it's done initializing.
+#[derive(Debug)]
+pub struct NodeStatusPublisher {
+    start: Timestamp,
+    period: Duration,
+    next_broadcast: Timestamp,
+    health: Health,
+    mode: Mode,
+    sub_mode: u3,
+    vendor_specific_status_code: u16,
+}
+
+impl NodeStatusPublisher {
+    /// Creates a new publisher for a node that started at `start`.
+    ///
+    /// The status is broadcast every `NodeStatus::MAX_BROADCASTING_PERIOD_MS`, starting at the first `tick`.
+    pub fn new(start: Timestamp) -> Self {
+        NodeStatusPublisher {
+            start,
+            period: Duration::from_millis(u64::from(NodeStatus::MAX_BROADCASTING_PERIOD_MS)),
+            next_broadcast: start,
+            health: Health::Ok,
+            mode: Mode::Initialization,
+            sub_mode: u3::new(0),
+            vendor_specific_status_code: 0,
+        }
+    }
+
+    /// Returns the broadcasting period.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Changes the broadcasting period.
+    ///
+    /// The period is clamped to the range allowed by the specification.
+    pub fn set_period(&mut self, period: Duration) {
+        let min = Duration::from_millis(u64::from(NodeStatus::MIN_BROADCASTING_PERIOD_MS));
+        let max = Duration::from_millis(u64::from(NodeStatus::MAX_BROADCASTING_PERIOD_MS));
+        self.period = cmp::min(cmp::max(period, min), max);
+    }
+
+    pub fn health(&self) -> Health {
+        self.health
+    }
+
+    pub fn set_health(&mut self, health: Health) {
+        self.health = health;
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    pub fn sub_mode(&self) -> u3 {
+        self.sub_mode
+    }
+
+    pub fn set_sub_mode(&mut self, sub_mode: u3) {
+        self.sub_mode = sub_mode;
+    }
+
+    pub fn vendor_specific_status_code(&self) -> u16 {
+        self.vendor_specific_status_code
+    }
+
+    pub fn set_vendor_specific_status_code(&mut self, code: u16) {
+        self.vendor_specific_status_code = code;
+    }
+
+    /// Returns the time elapsed since the node started.
+    pub fn uptime(&self, now: Timestamp) -> Duration {
+        now.duration_since(self.start)
+    }
+
+    /// Returns the `NodeStatus` message describing the node at `now`.
+    pub fn status(&self, now: Timestamp) -> NodeStatus {
+        NodeStatus {
+            uptime_sec: self.uptime(now).as_secs() as u32,
+            health: self.health.into(),
+            mode: self.mode.into(),
+            sub_mode: self.sub_mode,
+            vendor_specific_status_code: self.vendor_specific_status_code,
+        }
+    }
+
+    /// Broadcasts the `NodeStatus` if the broadcasting period have elapsed since the last broadcast.
+    ///
+    /// Anonymous nodes are not allowed to publish `NodeStatus`, nothing is broadcast until the node has a `NodeID`.
+    pub fn tick<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), TransmitError>
+        where I: TransferInterface,
+              S: Storage<I::Frame>,
+              N: Node<I, S>,
+    {
+        if now < self.next_broadcast || node.id().is_none() {
+            return Ok(());
+        }
+
+        // Keep a steady rate, but don't try to catch up after the application stalled for more than a period
+        let scheduled = self.next_broadcast + self.period;
+        self.next_broadcast = if scheduled > now {
+            scheduled
+        } else {
+            now + self.period
+        };
+        node.broadcast(self.status(now))
+    }
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use time::{
+        Duration,
+        Timestamp,
+    };
+    use types::*;
+
+    use tests::{
+        TestInterface,
+        test_node,
+    };
+
+    use protocol::node_status::*;
+
+    fn broadcasted_status(interface: &TestInterface) -> Option<NodeStatus> {
+        let frame = interface.tx.borrow_mut().pop_front()?;
+        let mut assembler = ::frame_assembler::FrameAssembler::new();
+        assembler.add_transfer_frame(frame).unwrap();
+        Some(assembler.build().unwrap().body)
+    }
+
+    #[test]
+    fn periodic_broadcast() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, Some(10));
+        let mut publisher = NodeStatusPublisher::new(Timestamp::from_micros(500_000));
+
+        publisher.tick(&node, Timestamp::from_micros(500_000)).unwrap();
+        node.flush_transmissions();
+        let status = broadcasted_status(&interface).unwrap();
+        assert_eq!(status.uptime_sec, 0);
+        assert_eq!(Health::from(status.health), Health::Ok);
+        assert_eq!(Mode::from(status.mode), Mode::Initialization);
+
+        publisher.tick(&node, Timestamp::from_micros(1_499_999)).unwrap();
+        node.flush_transmissions();
+        assert_eq!(broadcasted_status(&interface), None);
+
+        publisher.set_mode(Mode::Operational);
+        publisher.set_health(Health::Warning);
+        publisher.set_vendor_specific_status_code(0x1234);
+        publisher.tick(&node, Timestamp::from_micros(2_600_000)).unwrap();
+        node.flush_transmissions();
+        let status = broadcasted_status(&interface).unwrap();
+        assert_eq!(status.uptime_sec, 2);
+        assert_eq!(Health::from(status.health), Health::Warning);
+        assert_eq!(Mode::from(status.mode), Mode::Operational);
+        assert_eq!(status.vendor_specific_status_code, 0x1234);
+
+        // After a stall the next broadcast is a full period later
+        publisher.tick(&node, Timestamp::from_micros(2_600_001)).unwrap();
+        publisher.tick(&node, Timestamp::from_micros(3_599_999)).unwrap();
+        node.flush_transmissions();
+        assert_eq!(broadcasted_status(&interface), None);
+
+        publisher.tick(&node, Timestamp::from_micros(3_600_000)).unwrap();
+        node.flush_transmissions();
+        assert_eq!(broadcasted_status(&interface).unwrap().uptime_sec, 3);
+    }
+
+    #[test]
+    fn anonymous_node_is_silent() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, None);
+        let mut publisher = NodeStatusPublisher::new(Timestamp::from_micros(0));
+
+        publisher.tick(&node, Timestamp::from_micros(0)).unwrap();
+        node.flush_transmissions();
+        assert_eq!(broadcasted_status(&interface), None);
+    }
+
+    #[test]
+    fn period_is_clamped() {
+        let mut publisher = NodeStatusPublisher::new(Timestamp::from_micros(0));
+
+        publisher.set_period(Duration::from_secs(5));
+        assert_eq!(publisher.period(), Duration::from_millis(1000));
+
+        publisher.set_period(Duration::from_millis(0));
+        assert_eq!(publisher.period(), Duration::from_millis(2));
+    }
+
+    #[test]
+    fn mode_conversion() {
+        for mode in 0..8 {
+            assert_eq!(u8::from(u3::from(Mode::from(u3::new(mode)))), mode);
+        }
+        assert_eq!(Mode::from(u3::new(5)), Mode::Reserved(ReservedMode::new(5).unwrap()));
+    }
+
+    #[test]
+    fn reserved_mode_values() {
+        assert_eq!(ReservedMode::new(3), None);
+        assert_eq!(ReservedMode::new(4).map(|x| x.value()), Some(4));
+        assert_eq!(ReservedMode::new(6).map(|x| x.value()), Some(6));
+        assert_eq!(ReservedMode::new(7), None);
+        assert_eq!(ReservedMode::new(0x0f), None);
+    }
+}
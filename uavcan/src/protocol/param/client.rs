@@ -0,0 +1,471 @@
+use {
+    Node,
+    NodeID,
+};
+
+use node::{
+    ResponseHandle,
+    ReceiveError,
+    TransmitError,
+};
+
+use storage::{
+    Storage,
+    SubscriberStorageHandle,
+};
+
+use transfer::{
+    TransferInterface,
+    TransferFrame,
+};
+
+use time::{
+    Duration,
+    Timestamp,
+};
+
+use types::*;
+
+use super::{
+    Value,
+    GetSetRequest,
+    GetSetResponse,
+    ExecuteOpcodeRequest,
+};
+
+/// The highest parameter index that can be requested through `GetSet`.
+const MAX_INDEX: u16 = 8191;
+
+/// The result of an operation started on a `ParamClient`.
+#[derive(Debug, PartialEq)]
+pub enum ParamEvent {
+    /// A parameter was read, `index` is only known when enumerating.
+    Read {
+        index: Option<u16>,
+        param: GetSetResponse,
+    },
+    /// All parameters have been enumerated, `count` is the number of `Read` events reported.
+    Enumerated {
+        count: u16,
+    },
+    /// A value was written, `param` is the parameter as reported by the remote node afterwards.
+    Written(GetSetResponse),
+    /// An `ExecuteOpcode` request was answered, `ok` tells whether the remote node succeeded.
+    Executed {
+        opcode: u8,
+        ok: bool,
+    },
+    /// The operation failed, no more events are reported for it.
+    Failed(ParamClientError),
+}
+
+/// The reasons a `ParamClient` operation may fail.
+#[derive(Debug, PartialEq)]
+pub enum ParamClientError {
+    /// Another operation is in progress.
+    Busy,
+    Transmit(TransmitError),
+    /// No valid response was received, including timeouts.
+    Receive(ReceiveError),
+    /// The remote node doesn't have a parameter with the requested name.
+    NotFound,
+    /// The value to write is of another type than the parameter.
+    TypeMismatch,
+    /// The remote node didn't apply the value, usually because it's out of range.
+    Rejected,
+}
+
+#[derive(Debug)]
+enum Operation<F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    Enumerate {
+        index: u16,
+        handle: ResponseHandle<GetSetRequest, F, H>,
+    },
+    Read(ResponseHandle<GetSetRequest, F, H>),
+    Lookup {
+        value: Value,
+        handle: ResponseHandle<GetSetRequest, F, H>,
+    },
+    Write {
+        value: Value,
+        handle: ResponseHandle<GetSetRequest, F, H>,
+    },
+    Execute {
+        opcode: u8,
+        handle: ResponseHandle<ExecuteOpcodeRequest, F, H>,
+    },
+}
+
+/// Reads and writes the parameters of a remote node, the counterpart of a `ParamServer`.
+///
+/// One operation is in progress at a time. It's started by calling `enumerate`, `read`, `write`, `save` or `erase`,
+/// and its results are reported through `tick`, which must be called periodically and after receptions are flushed.
+///
+/// `write` reads the parameter before setting it, so values of the wrong type are never sent.
+#[derive(Debug)]
+pub struct ParamClient<F: TransferFrame, H: SubscriberStorageHandle<F>> {
+    server: NodeID,
+    timeout: Duration,
+    operation: Option<Operation<F, H>>,
+}
+
+impl<F: TransferFrame, H: SubscriberStorageHandle<F>> ParamClient<F, H> {
+    /// How long to wait for every response unless changed with `set_timeout`.
+    pub const DEFAULT_TIMEOUT_MS: u16 = 1000;
+
+    /// Creates a client for the parameters of the node with id `server`.
+    pub fn new(server: NodeID) -> Self {
+        ParamClient {
+            server,
+            timeout: Duration::from_millis(u64::from(Self::DEFAULT_TIMEOUT_MS)),
+            operation: None,
+        }
+    }
+
+    pub fn server(&self) -> NodeID {
+        self.server
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets how long to wait for each response, taking effect from the next request.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns true if an operation is in progress.
+    pub fn is_busy(&self) -> bool {
+        self.operation.is_some()
+    }
+
+    /// Abandons the operation in progress, any response arriving later is ignored.
+    pub fn cancel(&mut self) {
+        self.operation = None;
+    }
+
+    /// Reads all parameters by index, starting from zero.
+    ///
+    /// A `Read` event is reported for every parameter, followed by `Enumerated` when the remote node reports an unknown index.
+    pub fn enumerate<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ParamClientError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        self.ensure_idle()?;
+        let handle = self.get_set(node, 0, &[], Value::default(), now)?;
+        self.operation = Some(Operation::Enumerate{index: 0, handle});
+        Ok(())
+    }
+
+    /// Reads the parameter named `name`, reported as a `Read` event.
+    pub fn read<I, S, N>(&mut self, node: &N, name: &[u8], now: Timestamp) -> Result<(), ParamClientError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        self.ensure_idle()?;
+        let handle = self.get_set(node, 0, name, Value::default(), now)?;
+        self.operation = Some(Operation::Read(handle));
+        Ok(())
+    }
+
+    /// Sets the parameter named `name` to `value`, reported as a `Written` event.
+    ///
+    /// Fails with `TypeMismatch` if the parameter is of another type, or `Rejected` if the remote node didn't apply the value.
+    pub fn write<I, S, N>(&mut self, node: &N, name: &[u8], value: Value, now: Timestamp) -> Result<(), ParamClientError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        self.ensure_idle()?;
+        if value.is_empty() {
+            return Err(ParamClientError::TypeMismatch);
+        }
+        let handle = self.get_set(node, 0, name, Value::default(), now)?;
+        self.operation = Some(Operation::Lookup{value, handle});
+        Ok(())
+    }
+
+    /// Asks the remote node to save its parameters to non-volatile storage, reported as an `Executed` event.
+    pub fn save<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ParamClientError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        self.execute(node, ExecuteOpcodeRequest::OPCODE_SAVE, now)
+    }
+
+    /// Asks the remote node to erase its stored parameters, reported as an `Executed` event.
+    pub fn erase<I, S, N>(&mut self, node: &N, now: Timestamp) -> Result<(), ParamClientError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        self.execute(node, ExecuteOpcodeRequest::OPCODE_ERASE, now)
+    }
+
+    /// Handles received responses, sending follow-up requests as needed.
+    ///
+    /// `on_event` is called for every result of the operation in progress.
+    pub fn tick<I, S, N, E>(&mut self, node: &N, now: Timestamp, mut on_event: E)
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+              E: FnMut(ParamEvent),
+    {
+        let operation = match self.operation.take() {
+            Some(operation) => operation,
+            None => return,
+        };
+
+        match operation {
+            Operation::Enumerate{index, handle} => match handle.receive(now) {
+                None => self.operation = Some(Operation::Enumerate{index, handle}),
+                Some(Err(error)) => on_event(ParamEvent::Failed(ParamClientError::Receive(error))),
+                Some(Ok(param)) => {
+                    if param.name.length() == 0 {
+                        on_event(ParamEvent::Enumerated{count: index});
+                        return;
+                    }
+
+                    on_event(ParamEvent::Read{index: Some(index), param});
+                    if index == MAX_INDEX {
+                        on_event(ParamEvent::Enumerated{count: index + 1});
+                        return;
+                    }
+
+                    match self.get_set(node, index + 1, &[], Value::default(), now) {
+                        Ok(handle) => self.operation = Some(Operation::Enumerate{index: index + 1, handle}),
+                        Err(error) => on_event(ParamEvent::Failed(error)),
+                    }
+                },
+            },
+            Operation::Read(handle) => match handle.receive(now) {
+                None => self.operation = Some(Operation::Read(handle)),
+                Some(Err(error)) => on_event(ParamEvent::Failed(ParamClientError::Receive(error))),
+                Some(Ok(param)) if param.name.length() == 0 => on_event(ParamEvent::Failed(ParamClientError::NotFound)),
+                Some(Ok(param)) => on_event(ParamEvent::Read{index: None, param}),
+            },
+            Operation::Lookup{value, handle} => match handle.receive(now) {
+                None => self.operation = Some(Operation::Lookup{value, handle}),
+                Some(Err(error)) => on_event(ParamEvent::Failed(ParamClientError::Receive(error))),
+                Some(Ok(param)) if param.name.length() == 0 => on_event(ParamEvent::Failed(ParamClientError::NotFound)),
+                Some(Ok(ref param)) if !param.value.is_same_type(&value) => on_event(ParamEvent::Failed(ParamClientError::TypeMismatch)),
+                Some(Ok(param)) => match self.get_set(node, 0, param.name.as_ref(), value.clone(), now) {
+                    Ok(handle) => self.operation = Some(Operation::Write{value, handle}),
+                    Err(error) => on_event(ParamEvent::Failed(error)),
+                },
+            },
+            Operation::Write{value, handle} => match handle.receive(now) {
+                None => self.operation = Some(Operation::Write{value, handle}),
+                Some(Err(error)) => on_event(ParamEvent::Failed(ParamClientError::Receive(error))),
+                Some(Ok(param)) if param.name.length() == 0 => on_event(ParamEvent::Failed(ParamClientError::NotFound)),
+                Some(Ok(param)) if param.value != value => on_event(ParamEvent::Failed(ParamClientError::Rejected)),
+                Some(Ok(param)) => on_event(ParamEvent::Written(param)),
+            },
+            Operation::Execute{opcode, handle} => match handle.receive(now) {
+                None => self.operation = Some(Operation::Execute{opcode, handle}),
+                Some(Err(error)) => on_event(ParamEvent::Failed(ParamClientError::Receive(error))),
+                Some(Ok(response)) => on_event(ParamEvent::Executed{opcode, ok: response.ok}),
+            },
+        }
+    }
+
+    fn ensure_idle(&self) -> Result<(), ParamClientError> {
+        if self.is_busy() {
+            Err(ParamClientError::Busy)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn get_set<I, S, N>(&self, node: &N, index: u16, name: &[u8], value: Value, now: Timestamp) -> Result<ResponseHandle<GetSetRequest, F, H>, ParamClientError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        let request = GetSetRequest {
+            index: u13::new(index),
+            value,
+            name: Dynamic::<[u8; 92]>::with_data(&name[..name.len().min(92)]),
+        };
+        node.request(self.server, request, now + self.timeout).map_err(ParamClientError::Transmit)
+    }
+
+    fn execute<I, S, N>(&mut self, node: &N, opcode: u8, now: Timestamp) -> Result<(), ParamClientError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        self.ensure_idle()?;
+        let request = ExecuteOpcodeRequest {
+            opcode,
+            argument: i48::new(0),
+        };
+        let handle = node.request(self.server, request, now + self.timeout).map_err(ParamClientError::Transmit)?;
+        self.operation = Some(Operation::Execute{opcode, handle});
+        Ok(())
+    }
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use node::*;
+    use storage::HeapStorage;
+    use time::Timestamp;
+
+    use tests::{
+        CanFrame,
+        TestInterface,
+        TestNode,
+        test_node,
+        exchange,
+    };
+
+    use protocol::param::*;
+
+    type Handle = <HeapStorage<CanFrame> as ::storage::Storage<CanFrame>>::SubscriberStorageHandle;
+
+    #[derive(Debug, Default)]
+    struct NoStorage;
+
+    impl ParamStorage for NoStorage {
+        type Error = ();
+
+        fn save<R: ParamRegistry>(&mut self, _registry: &R) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn load<F: FnMut(&str, Value)>(&mut self, _apply: F) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn erase(&mut self) -> Result<(), ()> {
+            Err(())
+        }
+    }
+
+    struct Setup<'a> {
+        client_interface: &'a TestInterface,
+        client_node: TestNode<'a>,
+        client: ParamClient<CanFrame, Handle>,
+        server_interface: &'a TestInterface,
+        server_node: TestNode<'a>,
+        server: ParamServer<CanFrame, Handle, [Param; 3], NoStorage>,
+    }
+
+    impl<'a> Setup<'a> {
+        fn new(client_interface: &'a TestInterface, server_interface: &'a TestInterface) -> Self {
+            let client_node = test_node(client_interface, Some(42));
+            let server_node = test_node(server_interface, Some(10));
+            let server = ParamServer::new(&server_node, [
+                Param::integer("gain", 10, 0, 100),
+                Param::real("offset", 0.0, -1.0, 1.0),
+                Param::string("label", b"left"),
+            ], NoStorage).unwrap();
+
+            Setup {
+                client_interface,
+                client_node,
+                client: ParamClient::new(NodeID::new(10)),
+                server_interface,
+                server_node,
+                server,
+            }
+        }
+
+        /// Exchanges requests and responses until the client is done, returning the reported events.
+        fn run(&mut self) -> Vec<ParamEvent> {
+            let mut events = Vec::new();
+            while self.client.is_busy() {
+                exchange(&[(&self.client_node, self.client_interface), (&self.server_node, self.server_interface)]);
+                self.server.tick(&self.server_node).unwrap();
+                exchange(&[(&self.client_node, self.client_interface), (&self.server_node, self.server_interface)]);
+
+                self.client.tick(&self.client_node, Timestamp::from_micros(0), |event| events.push(event));
+            }
+            events
+        }
+    }
+
+    #[test]
+    fn enumerate() {
+        let (client_interface, server_interface) = (TestInterface::default(), TestInterface::default());
+        let mut setup = Setup::new(&client_interface, &server_interface);
+
+        setup.client.enumerate(&setup.client_node, Timestamp::from_micros(0)).unwrap();
+        assert_eq!(setup.client.read(&setup.client_node, b"gain", Timestamp::from_micros(0)), Err(ParamClientError::Busy));
+
+        let events = setup.run();
+        assert_eq!(events.len(), 4);
+        for (index, name) in [&b"gain"[..], b"offset", b"label"].iter().enumerate() {
+            match events[index] {
+                ParamEvent::Read{index: Some(i), ref param} => {
+                    assert_eq!(usize::from(i), index);
+                    assert_eq!(param.name.as_ref(), *name);
+                },
+                ref event => panic!("unexpected event {:?}", event),
+            }
+        }
+        assert_eq!(events[3], ParamEvent::Enumerated{count: 3});
+    }
+
+    #[test]
+    fn read_and_write() {
+        let (client_interface, server_interface) = (TestInterface::default(), TestInterface::default());
+        let mut setup = Setup::new(&client_interface, &server_interface);
+        let now = Timestamp::from_micros(0);
+
+        setup.client.read(&setup.client_node, b"offset", now).unwrap();
+        match setup.run()[..] {
+            [ParamEvent::Read{index: None, ref param}] => assert_eq!(param.value, Value::RealValue(0.0)),
+            ref events => panic!("unexpected events {:?}", events),
+        }
+
+        setup.client.write(&setup.client_node, b"gain", Value::IntegerValue(20), now).unwrap();
+        match setup.run()[..] {
+            [ParamEvent::Written(ref param)] => assert_eq!(param.value, Value::IntegerValue(20)),
+            ref events => panic!("unexpected events {:?}", events),
+        }
+        assert_eq!(setup.server.registry()[0].value, Value::IntegerValue(20));
+
+        setup.client.write(&setup.client_node, b"gain", Value::RealValue(0.5), now).unwrap();
+        assert_eq!(setup.run(), vec![ParamEvent::Failed(ParamClientError::TypeMismatch)]);
+
+        setup.client.write(&setup.client_node, b"gain", Value::IntegerValue(200), now).unwrap();
+        assert_eq!(setup.run(), vec![ParamEvent::Failed(ParamClientError::Rejected)]);
+
+        setup.client.write(&setup.client_node, b"missing", Value::IntegerValue(1), now).unwrap();
+        assert_eq!(setup.run(), vec![ParamEvent::Failed(ParamClientError::NotFound)]);
+        assert_eq!(setup.server.registry()[0].value, Value::IntegerValue(20));
+    }
+
+    #[test]
+    fn save_erase_and_timeout() {
+        let (client_interface, server_interface) = (TestInterface::default(), TestInterface::default());
+        let mut setup = Setup::new(&client_interface, &server_interface);
+        let now = Timestamp::from_micros(0);
+
+        setup.client.save(&setup.client_node, now).unwrap();
+        assert_eq!(setup.run(), vec![ParamEvent::Executed{opcode: ExecuteOpcodeRequest::OPCODE_SAVE, ok: true}]);
+
+        setup.client.erase(&setup.client_node, now).unwrap();
+        assert_eq!(setup.run(), vec![ParamEvent::Executed{opcode: ExecuteOpcodeRequest::OPCODE_ERASE, ok: false}]);
+
+        // Nobody answers
+        let mut events = Vec::new();
+        setup.client.read(&setup.client_node, b"gain", now).unwrap();
+        setup.client.tick(&setup.client_node, now, |event| events.push(event));
+        assert!(events.is_empty());
+        setup.client.tick(&setup.client_node, now + setup.client.timeout(), |event| events.push(event));
+        match events[..] {
+            [ParamEvent::Failed(ParamClientError::Receive(ref error))] => assert_eq!(error.error_code, ReceiveErrorCode::Timeout),
+            ref events => panic!("unexpected events {:?}", events),
+        }
+        assert!(!setup.client.is_busy());
+    }
+}
@@ -0,0 +1,160 @@
+use std::fs;
+use std::io;
+use std::io::{
+    BufRead,
+    BufReader,
+    Write,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use types::*;
+
+use super::{
+    Value,
+    ParamRegistry,
+    ParamStorage,
+};
+
+/// A `ParamStorage` keeping the parameter values in a text file.
+///
+/// Every parameter is stored on its own line as `<name> <type> <value>`, separated by tabs.
+/// Strings are stored hex encoded as they are not required to be valid UTF-8.
+///
+/// The file is replaced atomically when saving, by writing a temporary file next to it and renaming it.
+#[derive(Debug)]
+pub struct FileParamStorage {
+    path: PathBuf,
+}
+
+impl FileParamStorage {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        FileParamStorage {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl ParamStorage for FileParamStorage {
+    type Error = io::Error;
+
+    fn save<R: ParamRegistry>(&mut self, registry: &R) -> Result<(), io::Error> {
+        let mut temporary_path = self.path.clone().into_os_string();
+        temporary_path.push(".tmp");
+
+        {
+            let mut file = io::BufWriter::new(fs::File::create(&temporary_path)?);
+            for param in (0..registry.count()).filter_map(|index| registry.param(index)) {
+                match param.value {
+                    Value::IntegerValue(value) => writeln!(file, "{}\tinteger\t{}", param.name, value)?,
+                    Value::RealValue(value) => writeln!(file, "{}\treal\t{}", param.name, value)?,
+                    Value::BooleanValue(value) => writeln!(file, "{}\tboolean\t{}", param.name, value)?,
+                    Value::StringValue(ref value) => {
+                        write!(file, "{}\tstring\t", param.name)?;
+                        for byte in value.iter() {
+                            write!(file, "{:02x}", byte)?;
+                        }
+                        writeln!(file)?;
+                    },
+                    Value::Empty(_) => (),
+                }
+            }
+            file.into_inner()?.sync_all()?;
+        }
+
+        fs::rename(&temporary_path, &self.path)
+    }
+
+    fn load<F: FnMut(&str, Value)>(&mut self, mut apply: F) -> Result<(), io::Error> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let mut fields = line.splitn(3, '\t');
+            let (name, kind, value) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(name), Some(kind), Some(value)) => (name, kind, value),
+                _ => continue,
+            };
+
+            if let Some(value) = parse_value(kind, value) {
+                apply(name, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn erase(&mut self) -> Result<(), io::Error> {
+        match fs::remove_file(&self.path) {
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+fn parse_value(kind: &str, value: &str) -> Option<Value> {
+    match kind {
+        "integer" => value.parse().ok().map(Value::IntegerValue),
+        "real" => value.parse().ok().map(Value::RealValue),
+        "boolean" => value.parse().ok().map(Value::BooleanValue),
+        "string" => {
+            if value.len() % 2 != 0 || value.len() > 2*128 {
+                return None;
+            }
+            let mut string = Dynamic::<[u8; 128]>::new();
+            for i in (0..value.len()).step_by(2) {
+                string.push(u8::from_str_radix(value.get(i..i+2)?, 16).ok()?);
+            }
+            Some(Value::StringValue(string))
+        },
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs;
+
+    use protocol::param::*;
+
+    #[test]
+    fn save_load_erase() {
+        let path = env::temp_dir().join(format!("uavcan-param-test-{}", ::std::process::id()));
+        let mut storage = FileParamStorage::new(&path);
+
+        let mut params = [
+            Param::integer("gain", 10, 0, 100),
+            Param::real("offset", 0.5, -1.0, 1.0),
+            Param::boolean("enabled", false),
+            Param::string("label", b"\xffrear\tleft"),
+        ];
+        params[0].value = Value::IntegerValue(42);
+        params[1].value = Value::RealValue(-0.25);
+        params[2].value = Value::BooleanValue(1);
+        storage.save(&params).unwrap();
+
+        let mut loaded = Vec::new();
+        storage.load(|name, value| loaded.push((name.to_string(), value))).unwrap();
+        assert_eq!(loaded, params.iter().map(|param| (param.name.to_string(), param.value.clone())).collect::<Vec<_>>());
+
+        storage.erase().unwrap();
+        assert!(fs::metadata(&path).is_err());
+
+        // A missing file is the same as no stored values
+        storage.load(|_, _| panic!("nothing should be loaded")).unwrap();
+        storage.erase().unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+//! Configuration parameters
+//!
+//! Nodes expose their configuration as named parameters that can be read and written by other nodes
+//! through `GetSet`, and saved to or erased from non-volatile memory through `ExecuteOpcode`.
+//! The `ParamServer` answers these requests based on a `ParamRegistry`,
+//! and the `ParamClient` sends them to configure a remote node.
+
+mod server;
+mod client;
+
+#[cfg(feature="std")]
+mod file_storage;
+
+pub use self::server::{
+    Param,
+    ParamRegistry,
+    ParamStorage,
+    ParamServer,
+};
+
+pub use self::client::{
+    ParamClient,
+    ParamClientError,
+    ParamEvent,
+};
+
+#[cfg(feature="std")]
+pub use self::file_storage::FileParamStorage;
+
+use lib::core::mem;
+
+use types::*;
+
+/// Placeholder for an undefined value, `uavcan.protocol.param.Empty`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Empty {}
+
+impl_empty_struct!(Empty, 0x6c4d_0e8e_f373_61df, 0x6c4d_0e8e_f373_61df);
+
+/// Single parameter value, `uavcan.protocol.param.Value`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    /// Undefined value, used in requests that only reads a parameter.
+    Empty(Empty),
+    IntegerValue(i64),
+    RealValue(f32),
+
+    /// Zero is false, everything else is true.
+    BooleanValue(u8),
+    StringValue(Dynamic<[u8; 128]>),
+}
+
+impl_union!(Value, 3, 0xc3d9_6f44_8f2b_00a1, 0x29f1_4bf4_8472_7267, {
+    0 => Empty(Empty),
+    1 => IntegerValue(i64),
+    2 => RealValue(f32),
+    3 => BooleanValue(u8),
+    4 => StringValue(Dynamic<[u8; 128]>),
+});
+
+impl Value {
+    pub fn is_empty(&self) -> bool {
+        matches!(*self, Value::Empty(_))
+    }
+
+    /// Returns true if both values are of the same type, e.g. both are integers.
+    pub fn is_same_type(&self, other: &Value) -> bool {
+        mem::discriminant(self) == mem::discriminant(other)
+    }
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value::Empty(Empty{})
+    }
+}
+
+/// Numeric parameter value used for limits, `uavcan.protocol.param.NumericValue`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum NumericValue {
+    /// No limit.
+    Empty(Empty),
+    IntegerValue(i64),
+    RealValue(f32),
+}
+
+impl_union!(NumericValue, 2, 0x1222_eea5_96ad_701c, 0x0da6_d6fe_a22e_3587, {
+    0 => Empty(Empty),
+    1 => IntegerValue(i64),
+    2 => RealValue(f32),
+});
+
+impl Default for NumericValue {
+    fn default() -> Self {
+        NumericValue::Empty(Empty{})
+    }
+}
+
+/// Get or set a parameter by name or index, `uavcan.protocol.param.GetSet`.
+///
+/// The parameter is looked up by `name` if it's not empty, otherwise by `index`.
+/// If `value` is not empty, the parameter is set to it before the response is sent.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0xb7d14152f13221ed"]
+#[DataTypeSignature = "0xa7b622f939d1a4d5"]
+pub struct GetSetRequest {
+    pub index: u13,
+    pub value: Value,
+    pub name: Dynamic<[u8; 92]>,
+}
+
+/// The parameter after the request was processed.
+///
+/// An unknown parameter is reported with an empty `name` and empty values.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0xb7d14152f13221ed"]
+#[DataTypeSignature = "0xa7b622f939d1a4d5"]
+pub struct GetSetResponse {
+    _v0: void5,
+    pub value: Value,
+    _v1: void5,
+    pub default_value: Value,
+    _v2: void6,
+    pub max_value: NumericValue,
+    _v3: void6,
+    pub min_value: NumericValue,
+    pub name: Dynamic<[u8; 92]>,
+}
+
+impl GetSetResponse {
+    pub fn new(value: Value, default_value: Value, max_value: NumericValue, min_value: NumericValue, name: Dynamic<[u8; 92]>) -> Self {
+        GetSetResponse {
+            _v0: void5{},
+            value,
+            _v1: void5{},
+            default_value,
+            _v2: void6{},
+            max_value,
+            _v3: void6{},
+            min_value,
+            name,
+        }
+    }
+}
+
+impl ::Request for GetSetRequest {
+    type RESPONSE = GetSetResponse;
+    const TYPE_ID: Option<u8> = Some(11);
+}
+
+impl ::Response for GetSetResponse {
+    type REQUEST = GetSetRequest;
+    const TYPE_ID: Option<u8> = Some(11);
+}
+
+/// Operations on all parameters at once, `uavcan.protocol.param.ExecuteOpcode`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x3b131ac5eb69d2cd"]
+#[DataTypeSignature = "0x3b131ac5eb69d2cd"]
+pub struct ExecuteOpcodeRequest {
+    /// One of the `OPCODE_*` constants.
+    pub opcode: u8,
+
+    /// Reserved, should be zero.
+    pub argument: i48,
+}
+
+impl ExecuteOpcodeRequest {
+    /// Save all parameters to non-volatile storage.
+    pub const OPCODE_SAVE: u8 = 0;
+
+    /// Clear the non-volatile storage, the defaults are used after the next restart.
+    pub const OPCODE_ERASE: u8 = 1;
+}
+
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x3b131ac5eb69d2cd"]
+#[DataTypeSignature = "0x3b131ac5eb69d2cd"]
+pub struct ExecuteOpcodeResponse {
+    /// Reserved, should be zero.
+    pub argument: i48,
+
+    /// True if the operation succeeded.
+    pub ok: bool,
+}
+
+impl ::Request for ExecuteOpcodeRequest {
+    type RESPONSE = ExecuteOpcodeResponse;
+    const TYPE_ID: Option<u8> = Some(10);
+}
+
+impl ::Response for ExecuteOpcodeResponse {
+    type REQUEST = ExecuteOpcodeRequest;
+    const TYPE_ID: Option<u8> = Some(10);
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use serializer::*;
+    use deserializer::*;
+
+    use types::*;
+
+    use protocol::param::*;
+
+    fn serialize<T: ::Struct>(structure: T) -> ([u8; 256], usize) {
+        let mut buffer = [0u8; 256];
+        let length = {
+            let mut serializer = Serializer::from_structure(structure);
+            let mut serialization_buffer = SerializationBuffer::with_empty_buffer(&mut buffer);
+            assert_eq!(serializer.serialize(&mut serialization_buffer), SerializationResult::Finished);
+            (serialization_buffer.bit_length() + 7) / 8
+        };
+        (buffer, length)
+    }
+
+    #[test]
+    fn serialize_value() {
+        let request = GetSetRequest {
+            index: u13::new(5),
+            value: Value::IntegerValue(-2),
+            name: Dynamic::<[u8; 92]>::with_data(b"ab"),
+        };
+
+        let (buffer, length) = serialize(request);
+        // 13 bit index, 3 bit tag, 64 bit value and the tail array optimized name
+        assert_eq!(length, 12);
+        assert_eq!(&buffer[..2], &[5, 1]);
+        assert_eq!(&buffer[2..10], &[0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let response = GetSetResponse::new(
+            Value::StringValue(Dynamic::<[u8; 128]>::with_data(b"left")),
+            Value::BooleanValue(1),
+            NumericValue::RealValue(2.5),
+            NumericValue::Empty(Empty{}),
+            Dynamic::<[u8; 92]>::with_data(b"mount.side"),
+        );
+
+        let (mut buffer, length) = serialize(response.clone());
+
+        // Feed the data one byte at a time to make sure decoding can be resumed anywhere
+        let mut deserializer: Deserializer<GetSetResponse> = Deserializer::new();
+        for byte in buffer[..length].chunks_mut(1) {
+            deserializer.deserialize(byte);
+        }
+        assert_eq!(deserializer.into_structure().unwrap(), response);
+    }
+
+    #[test]
+    fn split_tag() {
+        #[derive(Debug, PartialEq, Clone, UavcanStruct)]
+        struct Misaligned {
+            pad: u7,
+            value: NumericValue,
+        }
+
+        let (mut buffer, length) = serialize(Misaligned{pad: u7::new(0), value: NumericValue::IntegerValue(3)});
+        assert_eq!(length, 10);
+
+        let mut deserializer: Deserializer<Misaligned> = Deserializer::new();
+        let (first, second) = buffer[..length].split_at_mut(1);
+        deserializer.deserialize(first);
+        deserializer.deserialize(second);
+        assert_eq!(deserializer.into_structure().unwrap().value, NumericValue::IntegerValue(3));
+    }
+}
@@ -0,0 +1,417 @@
+use Node;
+
+use node::{
+    ServiceServer,
+    TransmitError,
+};
+
+use storage::{
+    Storage,
+    StorageError,
+    SubscriberStorageHandle,
+};
+
+use transfer::{
+    TransferInterface,
+    TransferFrame,
+};
+
+use types::*;
+
+use super::{
+    Value,
+    NumericValue,
+    GetSetRequest,
+    GetSetResponse,
+    ExecuteOpcodeRequest,
+    ExecuteOpcodeResponse,
+};
+
+/// A configuration parameter as exposed through `GetSet`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Param {
+    /// Name of the parameter, at most 92 bytes long.
+    pub name: &'static str,
+
+    pub value: Value,
+    pub default_value: Value,
+
+    /// Limits for numeric parameters, `NumericValue::Empty` means no limit.
+    pub min_value: NumericValue,
+    pub max_value: NumericValue,
+}
+
+impl Param {
+    /// Creates an integer parameter limited to `[min, max]`, the value starts out as `default`.
+    pub fn integer(name: &'static str, default: i64, min: i64, max: i64) -> Self {
+        Param {
+            name,
+            value: Value::IntegerValue(default),
+            default_value: Value::IntegerValue(default),
+            min_value: NumericValue::IntegerValue(min),
+            max_value: NumericValue::IntegerValue(max),
+        }
+    }
+
+    /// Creates a real parameter limited to `[min, max]`, the value starts out as `default`.
+    pub fn real(name: &'static str, default: f32, min: f32, max: f32) -> Self {
+        Param {
+            name,
+            value: Value::RealValue(default),
+            default_value: Value::RealValue(default),
+            min_value: NumericValue::RealValue(min),
+            max_value: NumericValue::RealValue(max),
+        }
+    }
+
+    /// Creates a boolean parameter, the value starts out as `default`.
+    pub fn boolean(name: &'static str, default: bool) -> Self {
+        Param {
+            name,
+            value: Value::BooleanValue(default as u8),
+            default_value: Value::BooleanValue(default as u8),
+            min_value: NumericValue::default(),
+            max_value: NumericValue::default(),
+        }
+    }
+
+    /// Creates a string parameter, the value starts out as the first 128 bytes of `default`.
+    pub fn string(name: &'static str, default: &[u8]) -> Self {
+        let default = Value::StringValue(Dynamic::<[u8; 128]>::with_data(&default[..default.len().min(128)]));
+        Param {
+            name,
+            value: default.clone(),
+            default_value: default,
+            min_value: NumericValue::default(),
+            max_value: NumericValue::default(),
+        }
+    }
+
+    /// Returns true if `value` is of the same type as the current value and within the limits.
+    pub fn accepts(&self, value: &Value) -> bool {
+        let above_min = match (&self.min_value, value) {
+            (&NumericValue::IntegerValue(min), &Value::IntegerValue(value)) => value >= min,
+            (&NumericValue::RealValue(min), &Value::RealValue(value)) => value >= min,
+            _ => true,
+        };
+
+        let below_max = match (&self.max_value, value) {
+            (&NumericValue::IntegerValue(max), &Value::IntegerValue(value)) => value <= max,
+            (&NumericValue::RealValue(max), &Value::RealValue(value)) => value <= max,
+            _ => true,
+        };
+
+        !value.is_empty() && self.value.is_same_type(value) && above_min && below_max
+    }
+}
+
+/// The set of parameters exposed by a `ParamServer`.
+///
+/// The parameters are indexed from `0` to `count() - 1`.
+/// An array of `Param` is the simplest registry, but the parameters may as well be backed by application state.
+pub trait ParamRegistry {
+    /// Returns the number of parameters.
+    fn count(&self) -> usize;
+
+    /// Returns the parameter at `index`.
+    fn param(&self, index: usize) -> Option<Param>;
+
+    /// Changes the value of the parameter at `index`.
+    ///
+    /// The `ParamServer` only sets values accepted by `Param::accepts`.
+    fn set_value(&mut self, index: usize, value: Value);
+
+    /// Returns the index of the parameter named `name`.
+    fn find(&self, name: &[u8]) -> Option<usize> {
+        (0..self.count()).find(|&index| self.param(index).map_or(false, |param| param.name.as_bytes() == name))
+    }
+}
+
+impl<const N: usize> ParamRegistry for [Param; N] {
+    fn count(&self) -> usize {
+        N
+    }
+
+    fn param(&self, index: usize) -> Option<Param> {
+        self.get(index).cloned()
+    }
+
+    fn set_value(&mut self, index: usize, value: Value) {
+        if let Some(param) = self.get_mut(index) {
+            param.value = value;
+        }
+    }
+}
+
+/// Non-volatile storage for parameter values.
+pub trait ParamStorage {
+    type Error;
+
+    /// Stores the current values of all parameters in `registry`, replacing what was stored before.
+    fn save<R: ParamRegistry>(&mut self, registry: &R) -> Result<(), Self::Error>;
+
+    /// Calls `apply` with the name and value of every stored parameter.
+    fn load<F: FnMut(&str, Value)>(&mut self, apply: F) -> Result<(), Self::Error>;
+
+    /// Removes all stored values.
+    fn erase(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Responds to `GetSet` and `ExecuteOpcode` requests for the parameters in a `ParamRegistry`.
+///
+/// Values received through `GetSet` are only applied if accepted by `Param::accepts`,
+/// the response always contains the value the parameter has after the request was processed.
+#[derive(Debug)]
+pub struct ParamServer<F: TransferFrame, H: SubscriberStorageHandle<F>, R: ParamRegistry, P: ParamStorage> {
+    get_set: ServiceServer<GetSetRequest, F, H>,
+    execute_opcode: ServiceServer<ExecuteOpcodeRequest, F, H>,
+    registry: R,
+    storage: P,
+}
+
+impl<F: TransferFrame, H: SubscriberStorageHandle<F>, R: ParamRegistry, P: ParamStorage> ParamServer<F, H, R, P> {
+    /// Creates a server for the parameters in `registry`, using `storage` to save them.
+    ///
+    /// The stored values are not applied before `load` is called.
+    /// Fails with `Err(StorageError::OutOfSpace)` if the storage of `node` can't hold the subscriptions.
+    pub fn new<I, S, N>(node: &N, registry: R, storage: P) -> Result<Self, StorageError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        Ok(ParamServer {
+            get_set: node.serve()?,
+            execute_opcode: node.serve()?,
+            registry,
+            storage,
+        })
+    }
+
+    pub fn registry(&self) -> &R {
+        &self.registry
+    }
+
+    pub fn registry_mut(&mut self) -> &mut R {
+        &mut self.registry
+    }
+
+    /// Sets the parameters to the values in the storage.
+    ///
+    /// Stored values for unknown parameters, or values not accepted by the parameter, are ignored.
+    pub fn load(&mut self) -> Result<(), P::Error> {
+        let registry = &mut self.registry;
+        self.storage.load(|name, value| {
+            if let Some(index) = registry.find(name.as_bytes()) {
+                if registry.param(index).map_or(false, |param| param.accepts(&value)) {
+                    registry.set_value(index, value);
+                }
+            }
+        })
+    }
+
+    /// Responds to all received requests.
+    ///
+    /// A failed response doesn't stop the remaining ones, the first error is returned.
+    pub fn tick<I, S, N>(&mut self, node: &N) -> Result<(), TransmitError>
+        where I: TransferInterface<Frame=F>,
+              S: Storage<F, SubscriberStorageHandle=H>,
+              N: Node<I, S>,
+    {
+        let mut result = Ok(());
+        while let Some(received) = self.get_set.receive() {
+            if let Ok(request) = received {
+                let response = self.get_set(&request.body);
+                result = result.and(node.respond(request.token, response));
+            }
+        }
+
+        while let Some(received) = self.execute_opcode.receive() {
+            if let Ok(request) = received {
+                let response = self.execute_opcode(&request.body);
+                result = result.and(node.respond(request.token, response));
+            }
+        }
+
+        result
+    }
+
+    fn get_set(&mut self, request: &GetSetRequest) -> GetSetResponse {
+        let index = if request.name.length() > 0 {
+            self.registry.find(request.name.as_ref())
+        } else {
+            Some(usize::from(u16::from(request.index)))
+        };
+
+        let param = match index.and_then(|index| self.registry.param(index).map(|param| (index, param))) {
+            Some((index, param)) => {
+                if param.accepts(&request.value) {
+                    self.registry.set_value(index, request.value.clone());
+                    self.registry.param(index).unwrap_or(param)
+                } else {
+                    param
+                }
+            },
+            None => return GetSetResponse::new(Value::default(), Value::default(), NumericValue::default(), NumericValue::default(), Dynamic::<[u8; 92]>::new()),
+        };
+
+        let name = param.name.as_bytes();
+        GetSetResponse::new(param.value, param.default_value, param.max_value, param.min_value, Dynamic::<[u8; 92]>::with_data(&name[..name.len().min(92)]))
+    }
+
+    fn execute_opcode(&mut self, request: &ExecuteOpcodeRequest) -> ExecuteOpcodeResponse {
+        let ok = match request.opcode {
+            ExecuteOpcodeRequest::OPCODE_SAVE => self.storage.save(&self.registry).is_ok(),
+            ExecuteOpcodeRequest::OPCODE_ERASE => self.storage.erase().is_ok(),
+            _ => false,
+        };
+
+        ExecuteOpcodeResponse {
+            argument: i48::new(0),
+            ok,
+        }
+    }
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use node::*;
+    use storage::HeapStorage;
+    use time::Timestamp;
+    use types::*;
+
+    use tests::{
+        CanFrame,
+        TestInterface,
+        TestNode,
+        test_node,
+        exchange,
+    };
+
+    use protocol::param::*;
+
+    type Handle = <HeapStorage<CanFrame> as ::storage::Storage<CanFrame>>::SubscriberStorageHandle;
+
+    #[derive(Debug, Default)]
+    struct MemoryStorage {
+        values: Vec<(&'static str, Value)>,
+    }
+
+    impl ParamStorage for MemoryStorage {
+        type Error = ();
+
+        fn save<R: ParamRegistry>(&mut self, registry: &R) -> Result<(), ()> {
+            self.values = (0..registry.count()).filter_map(|index| registry.param(index)).map(|param| (param.name, param.value)).collect();
+            Ok(())
+        }
+
+        fn load<F: FnMut(&str, Value)>(&mut self, mut apply: F) -> Result<(), ()> {
+            for &(name, ref value) in self.values.iter() {
+                apply(name, value.clone());
+            }
+            Ok(())
+        }
+
+        fn erase(&mut self) -> Result<(), ()> {
+            self.values.clear();
+            Ok(())
+        }
+    }
+
+    fn get_set(index: u16, name: &[u8], value: Value) -> GetSetRequest {
+        GetSetRequest {
+            index: u13::new(index),
+            value,
+            name: Dynamic::<[u8; 92]>::with_data(name),
+        }
+    }
+
+    /// Sends `requests` from a client node to the server and returns the responses.
+    fn send_requests<T: ::Struct + ::Request + Clone>(server: &mut ParamServer<CanFrame, Handle, [Param; 3], MemoryStorage>,
+                                                       node: &TestNode,
+                                                       interface: &TestInterface,
+                                                       requests: &[T]) -> Vec<T::RESPONSE> {
+        let client_interface = TestInterface::default();
+        let client = test_node(&client_interface, Some(42));
+
+        let mut responses = Vec::new();
+        for request in requests {
+            let handle = client.request(NodeID::new(10), request.clone(), Timestamp::from_micros(1_000_000)).unwrap();
+            exchange(&[(&client, &client_interface), (node, interface)]);
+            server.tick(node).unwrap();
+            exchange(&[(&client, &client_interface), (node, interface)]);
+
+            responses.push(handle.receive(Timestamp::from_micros(0)).unwrap().unwrap());
+        }
+        responses
+    }
+
+    fn params() -> [Param; 3] {
+        [
+            Param::integer("gain", 10, 0, 100),
+            Param::real("offset", 0.0, -1.0, 1.0),
+            Param::string("label", b"left"),
+        ]
+    }
+
+    #[test]
+    fn get_and_set() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, Some(10));
+        let mut server = ParamServer::new(&node, params(), MemoryStorage::default()).unwrap();
+
+        let responses = send_requests(&mut server, &node, &interface, &[
+            get_set(1, b"", Value::default()),
+            get_set(0, b"gain", Value::IntegerValue(50)),
+            get_set(0, b"gain", Value::IntegerValue(101)),
+            get_set(0, b"gain", Value::RealValue(0.5)),
+            get_set(3, b"", Value::default()),
+        ]);
+
+        assert_eq!(responses[0].name.as_ref(), b"offset");
+        assert_eq!(responses[0].value, Value::RealValue(0.0));
+        assert_eq!(responses[0].min_value, NumericValue::RealValue(-1.0));
+        assert_eq!(responses[0].max_value, NumericValue::RealValue(1.0));
+
+        assert_eq!(responses[1].value, Value::IntegerValue(50));
+        assert_eq!(responses[1].default_value, Value::IntegerValue(10));
+
+        // Out of range and wrong type are not applied
+        assert_eq!(responses[2].value, Value::IntegerValue(50));
+        assert_eq!(responses[3].value, Value::IntegerValue(50));
+
+        // Unknown parameter
+        assert_eq!(responses[4].name.length(), 0);
+        assert_eq!(responses[4].value, Value::default());
+
+        assert_eq!(server.registry()[0].value, Value::IntegerValue(50));
+    }
+
+    #[test]
+    fn save_and_erase() {
+        let interface = TestInterface::default();
+        let node = test_node(&interface, Some(10));
+        let mut server = ParamServer::new(&node, params(), MemoryStorage::default()).unwrap();
+
+        server.registry_mut().set_value(2, Value::StringValue(Dynamic::<[u8; 128]>::with_data(b"right")));
+        let responses = send_requests(&mut server, &node, &interface, &[
+            ExecuteOpcodeRequest{opcode: ExecuteOpcodeRequest::OPCODE_SAVE, argument: i48::new(0)},
+            ExecuteOpcodeRequest{opcode: 7, argument: i48::new(0)},
+        ]);
+        assert!(responses[0].ok);
+        assert!(!responses[1].ok);
+
+        // The saved values are applied when loading
+        let storage = MemoryStorage{values: server.storage.values.clone()};
+        let mut restarted = ParamServer::new(&node, params(), storage).unwrap();
+        restarted.load().unwrap();
+        assert_eq!(restarted.registry()[2].value, Value::StringValue(Dynamic::<[u8; 128]>::with_data(b"right")));
+
+        let responses = send_requests(&mut server, &node, &interface, &[
+            ExecuteOpcodeRequest{opcode: ExecuteOpcodeRequest::OPCODE_ERASE, argument: i48::new(0)},
+        ]);
+        assert!(responses[0].ok);
+        assert!(server.storage.values.is_empty());
+    }
+}
@@ -0,0 +1,36 @@
+//! Remote restart
+//!
+//! A node receiving a `RestartNodeRequest` with the correct magic number should restart as soon as possible.
+
+use types::*;
+
+/// Request for restarting the node, `uavcan.protocol.RestartNode`.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x569e05394a3017f0"]
+#[DataTypeSignature = "0x569e05394a3017f0"]
+pub struct RestartNodeRequest {
+    /// Must be `MAGIC_NUMBER`, other requests are to be rejected.
+    pub magic_number: u40,
+}
+
+impl RestartNodeRequest {
+    pub const MAGIC_NUMBER: u64 = 0x00ac_ce55_1b1e;
+}
+
+/// Response telling if the node will restart.
+#[derive(Debug, PartialEq, Clone, UavcanStruct)]
+#[DSDLSignature = "0x569e05394a3017f0"]
+#[DataTypeSignature = "0x569e05394a3017f0"]
+pub struct RestartNodeResponse {
+    pub ok: bool,
+}
+
+impl ::Request for RestartNodeRequest {
+    type RESPONSE = RestartNodeResponse;
+    const TYPE_ID: Option<u8> = Some(5);
+}
+
+impl ::Response for RestartNodeResponse {
+    type REQUEST = RestartNodeRequest;
+    const TYPE_ID: Option<u8> = Some(5);
+}
@@ -0,0 +1,241 @@
+//! Handling of redundant interfaces
+//!
+//! A node connected to several redundant buses receives every transfer once per bus.
+//! The `RedundancyFilter` makes sure only one copy of each transfer is let through to the subscribers.
+
+use transfer::{
+    TransferFrame,
+    TransferID,
+};
+
+use time::{
+    Duration,
+    Timestamp,
+};
+
+use node::TRANSFER_TIMEOUT_MS;
+
+/// The default number of transfer kinds a `RedundancyFilter` can keep track of.
+pub const REDUNDANCY_FILTER_CAPACITY: usize = 32;
+
+/// Transfers are only accepted from a new interface if nothing have been received on the current one for this long.
+pub const INTERFACE_TIMEOUT_MS: u16 = 1000;
+
+/// Only the priority bits are not part of what distinguishes one kind of transfer from another.
+const KEY_MASK: u32 = 0x00ff_ffff;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    key: u32,
+    interface: usize,
+    transfer_id: TransferID,
+    last_transfer: Timestamp,
+}
+
+/// Drops copies of transfers received on more than one redundant interface.
+///
+/// For every kind of transfer (data type, source and destination) the filter remembers the interface
+/// and `TransferID` of the last accepted transfer. Frames from other interfaces are dropped until the
+/// current interface have been silent for `INTERFACE_TIMEOUT_MS`, after which the filter switches over.
+///
+/// The filter keeps track of `CAPACITY` kinds of transfers. Once it's full, the least recently used entry
+/// is replaced by the next kind accepted. Kinds without an entry are only accepted from one interface at the time,
+/// switching over by the same rules, so a kind whose entry was replaced is not let through twice.
+#[derive(Debug)]
+pub struct RedundancyFilter<const CAPACITY: usize = REDUNDANCY_FILTER_CAPACITY> {
+    entries: [Option<Entry>; CAPACITY],
+    untracked: Option<(usize, Timestamp)>,
+}
+
+impl<const CAPACITY: usize> RedundancyFilter<CAPACITY> {
+    pub fn new() -> Self {
+        RedundancyFilter {
+            entries: [None; CAPACITY],
+            untracked: None,
+        }
+    }
+
+    /// Returns true if `frame`, received on the interface with index `interface`, should be passed on to the subscribers.
+    ///
+    /// The reception timestamp of the frame is used if present, otherwise `now`.
+    pub fn accept<F: TransferFrame>(&mut self, frame: &F, interface: usize, now: Timestamp) -> bool {
+        let key = u32::from(frame.id()) & KEY_MASK;
+        let transfer_id = frame.tail_byte().transfer_id();
+        let now = frame.timestamp().map_or(now, |timestamp| timestamp.monotonic);
+
+        let entry = match self.entries.iter_mut().filter_map(|x| x.as_mut()).find(|x| x.key == key) {
+            Some(entry) => entry,
+            None => {
+                let accepted = frame.is_start_frame() && self.accept_untracked(interface, now);
+                if accepted {
+                    self.insert(Entry{key, interface, transfer_id, last_transfer: now});
+                }
+                return accepted;
+            },
+        };
+
+        if !frame.is_start_frame() {
+            return entry.interface == interface && entry.transfer_id == transfer_id;
+        }
+
+        if entry.interface == interface {
+            entry.transfer_id = transfer_id;
+            entry.last_transfer = now;
+            return true;
+        }
+
+        let interface_timeout = Duration::from_millis(u64::from(INTERFACE_TIMEOUT_MS));
+        let transfer_timeout = Duration::from_millis(u64::from(TRANSFER_TIMEOUT_MS));
+        let silence = now.duration_since(entry.last_transfer);
+        if (silence >= interface_timeout && transfer_id != entry.transfer_id) || silence >= transfer_timeout {
+            entry.interface = interface;
+            entry.transfer_id = transfer_id;
+            entry.last_transfer = now;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Decides if a start frame of a kind without an entry should be accepted.
+    ///
+    /// While there are free entries the kind have never been received before. Once the filter is full,
+    /// the kind may have had its entry replaced, and the copies from other interfaces must still be dropped.
+    fn accept_untracked(&mut self, interface: usize, now: Timestamp) -> bool {
+        if self.entries.iter().any(|x| x.is_none()) {
+            return true;
+        }
+
+        let interface_timeout = Duration::from_millis(u64::from(INTERFACE_TIMEOUT_MS));
+        match self.untracked {
+            Some((current, last_transfer)) if current != interface && now.duration_since(last_transfer) < interface_timeout => false,
+            _ => {
+                self.untracked = Some((interface, now));
+                true
+            },
+        }
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        let index = if let Some(index) = self.entries.iter().position(|x| x.is_none()) {
+            index
+        } else {
+            let mut least_recent: Option<(usize, Timestamp)> = None;
+            for (index, last_transfer) in self.entries.iter().enumerate().filter_map(|(index, x)| Some((index, x.as_ref()?.last_transfer))) {
+                match least_recent {
+                    Some((_, least_recent_transfer)) if least_recent_transfer <= last_transfer => (),
+                    _ => least_recent = Some((index, last_transfer)),
+                }
+            }
+            match least_recent {
+                Some((index, _)) => index,
+                None => return,
+            }
+        };
+        self.entries[index] = Some(entry);
+    }
+}
+
+impl<const CAPACITY: usize> Default for RedundancyFilter<CAPACITY> {
+    fn default() -> Self {
+        RedundancyFilter::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use redundancy::*;
+
+    use tests::CanFrame;
+
+    use transfer::{
+        TailByte,
+        TransferFrameID,
+    };
+
+    fn frame(id: u32, start: bool, end: bool, transfer_id: u8) -> CanFrame {
+        let mut frame = CanFrame::new(TransferFrameID::new(id));
+        frame.set_data_length(1);
+        frame.data_as_mut()[0] = TailByte::new(start, end, !start, TransferID::new(transfer_id)).into();
+        frame
+    }
+
+    fn at(millis: u64) -> Timestamp {
+        Timestamp::from_micros(millis * 1000)
+    }
+
+    #[test]
+    fn drops_copies_from_other_interfaces() {
+        let mut filter: RedundancyFilter = RedundancyFilter::new();
+        let id = 0x1015_552a;
+
+        assert!(filter.accept(&frame(id, true, true, 0), 0, at(0)));
+        assert!(!filter.accept(&frame(id, true, true, 0), 1, at(1)));
+
+        assert!(filter.accept(&frame(id, true, true, 1), 0, at(100)));
+        assert!(!filter.accept(&frame(id, true, true, 1), 1, at(101)));
+
+        // Other sources are tracked independently
+        assert!(filter.accept(&frame(id + 1, true, true, 1), 1, at(101)));
+    }
+
+    #[test]
+    fn multi_frame_transfers() {
+        let mut filter: RedundancyFilter = RedundancyFilter::new();
+        let id = 0x1015_552a;
+
+        assert!(filter.accept(&frame(id, true, false, 3), 1, at(0)));
+        assert!(!filter.accept(&frame(id, true, false, 3), 0, at(0)));
+        assert!(filter.accept(&frame(id, false, true, 3), 1, at(1)));
+        assert!(!filter.accept(&frame(id, false, true, 3), 0, at(1)));
+
+        // Continuation of a transfer that was never started
+        assert!(!filter.accept(&frame(id + 1, false, true, 3), 0, at(1)));
+    }
+
+    #[test]
+    fn switches_interface_after_timeout() {
+        let mut filter: RedundancyFilter = RedundancyFilter::new();
+        let id = 0x1015_552a;
+
+        assert!(filter.accept(&frame(id, true, true, 0), 0, at(0)));
+        assert!(!filter.accept(&frame(id, true, true, 1), 1, at(999)));
+        assert!(filter.accept(&frame(id, true, true, 1), 1, at(1000)));
+        assert!(!filter.accept(&frame(id, true, true, 1), 0, at(1001)));
+        assert!(filter.accept(&frame(id, true, true, 2), 1, at(1100)));
+    }
+
+    #[test]
+    fn more_kinds_than_capacity() {
+        let mut filter: RedundancyFilter = RedundancyFilter::new();
+        let ids = (0..REDUNDANCY_FILTER_CAPACITY as u32 + 1).map(|source| 0x1015_5500 + source);
+
+        // All transfers are received on one interface before the copies on the other
+        for id in ids.clone() {
+            assert!(filter.accept(&frame(id, true, true, 0), 0, at(0)));
+        }
+        for id in ids.clone() {
+            assert!(!filter.accept(&frame(id, true, true, 0), 1, at(1)));
+        }
+
+        // The kinds keep being filtered in either order
+        for id in ids.clone() {
+            assert!(filter.accept(&frame(id, true, true, 1), 0, at(100)));
+            assert!(!filter.accept(&frame(id, true, true, 1), 1, at(100)));
+        }
+        for id in ids.clone().rev() {
+            assert!(!filter.accept(&frame(id, true, true, 2), 1, at(200)));
+            assert!(filter.accept(&frame(id, true, true, 2), 0, at(200)));
+        }
+
+        // Kinds without an entry switch interface after the timeout as well
+        for id in ids.clone() {
+            assert!(filter.accept(&frame(id, true, true, 3), 1, at(1200)));
+        }
+        for id in ids.clone() {
+            assert!(!filter.accept(&frame(id, true, true, 3), 0, at(1201)));
+        }
+    }
+}
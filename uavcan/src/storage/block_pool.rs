@@ -0,0 +1,619 @@
+use lib::core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use transfer::TransferFrame;
+use transfer::TransferFrameID;
+use transfer::FullTransferID;
+use transfer::TransferFrameIDFilter;
+use transfer::Priority;
+
+use storage::StorageError;
+use storage::DropPolicy;
+use storage::Inserted;
+use storage::FramePool;
+
+/// Marks the end of a list of blocks.
+const NIL: u16 = u16::MAX;
+
+/// Usage statistics for a `BlockPool`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolStats {
+    /// The number of blocks in the pool.
+    pub capacity: usize,
+
+    /// The number of blocks currently holding a frame.
+    pub used: usize,
+
+    /// The highest number of blocks that have been in use at once.
+    pub peak: usize,
+
+    /// The number of times a frame didn't get a block because the pool was exhausted or a quota was reached,
+    /// each causing a frame to be dropped.
+    pub allocation_failures: u32,
+}
+
+/// Usage statistics for a single subscriber or interface queue in a `BlockPool`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsumerStats {
+    /// The filter of a subscriber, or `None` for an interface queue.
+    pub filter: Option<TransferFrameIDFilter>,
+
+    /// The highest number of blocks this consumer may use at once.
+    pub quota: usize,
+
+    /// The number of blocks currently used.
+    pub used: usize,
+
+    /// The highest number of blocks that have been in use at once.
+    pub peak: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConsumerKind {
+    Free,
+    Subscriber(TransferFrameIDFilter),
+    Interface,
+}
+
+/// A consumer owns a list of blocks, in the order the frames were inserted.
+#[derive(Debug, Clone, Copy)]
+struct Consumer {
+    kind: ConsumerKind,
+    head: u16,
+    tail: u16,
+    quota: usize,
+    used: usize,
+    peak: usize,
+}
+
+impl Consumer {
+    const FREE: Consumer = Consumer {
+        kind: ConsumerKind::Free,
+        head: NIL,
+        tail: NIL,
+        quota: 0,
+        used: 0,
+        peak: 0,
+    };
+}
+
+struct Blocks<F, const BLOCKS: usize, const CONSUMERS: usize> {
+    frames: [Option<F>; BLOCKS],
+    next: [u16; BLOCKS],
+    free: u16,
+    consumers: [Consumer; CONSUMERS],
+    subscriber_quota: usize,
+    interface_quota: usize,
+    stats: PoolStats,
+}
+
+impl<F: TransferFrame, const BLOCKS: usize, const CONSUMERS: usize> Blocks<F, BLOCKS, CONSUMERS> {
+    fn add_consumer(&mut self, kind: ConsumerKind) -> Option<usize> {
+        let index = self.consumers.iter().position(|x| x.kind == ConsumerKind::Free)?;
+        let quota = match kind {
+            ConsumerKind::Interface => self.interface_quota,
+            _ => self.subscriber_quota,
+        };
+        self.consumers[index] = Consumer{kind, quota, ..Consumer::FREE};
+        Some(index)
+    }
+
+    fn remove_consumer(&mut self, consumer: usize) {
+        self.retain(consumer, &mut |_| false);
+        self.consumers[consumer] = Consumer::FREE;
+    }
+
+    /// Appends `frame` to the list of `consumer`, dropping a frame according to `policy` if no block is available.
+    ///
+    /// Only frames of `consumer` itself are dropped to make room.
+    fn push(&mut self, consumer: usize, frame: F, policy: DropPolicy) -> Result<Inserted, StorageError> {
+        let mut inserted = Inserted::Queued;
+        if self.free == NIL || self.consumers[consumer].used >= self.consumers[consumer].quota {
+            self.stats.allocation_failures = self.stats.allocation_failures.saturating_add(1);
+            let victim = policy.victim(self.frames(consumer), &frame).and_then(|index| self.nth(consumer, index));
+            let (previous, block) = victim.ok_or(StorageError::OutOfSpace)?;
+            self.unlink(consumer, previous, block);
+            inserted = Inserted::DroppedOther;
+        }
+
+        self.link(consumer, frame);
+        Ok(inserted)
+    }
+
+    fn push_front(&mut self, consumer: usize, frame: F) -> Result<(), StorageError> {
+        if self.free == NIL || self.consumers[consumer].used >= self.consumers[consumer].quota {
+            self.stats.allocation_failures = self.stats.allocation_failures.saturating_add(1);
+            return Err(StorageError::OutOfSpace);
+        }
+
+        let block = self.allocate(consumer, frame);
+        let consumer = &mut self.consumers[consumer];
+        self.next[block as usize] = consumer.head;
+        consumer.head = block;
+        if consumer.tail == NIL {
+            consumer.tail = block;
+        }
+        Ok(())
+    }
+
+    /// Moves `frame` into a free block appended to the list of `consumer`, there must be a free block.
+    fn link(&mut self, consumer: usize, frame: F) {
+        let block = self.allocate(consumer, frame);
+        self.next[block as usize] = NIL;
+
+        let consumer = &mut self.consumers[consumer];
+        if consumer.tail == NIL {
+            consumer.head = block;
+        } else {
+            self.next[consumer.tail as usize] = block;
+        }
+        consumer.tail = block;
+    }
+
+    /// Moves `frame` into a free block accounted to `consumer` and returns the block, there must be a free block.
+    fn allocate(&mut self, consumer: usize, frame: F) -> u16 {
+        let block = self.free;
+        self.free = self.next[block as usize];
+        self.frames[block as usize] = Some(frame);
+
+        let consumer = &mut self.consumers[consumer];
+        consumer.used += 1;
+        consumer.peak = consumer.peak.max(consumer.used);
+
+        self.stats.used += 1;
+        self.stats.peak = self.stats.peak.max(self.stats.used);
+        block
+    }
+
+    /// Returns the frames of `consumer` in the order they were inserted.
+    fn frames(&self, consumer: usize) -> impl Iterator<Item=&F> {
+        let mut block = self.consumers[consumer].head;
+        ::lib::core::iter::from_fn(move || {
+            if block == NIL {
+                return None;
+            }
+            let current = block as usize;
+            block = self.next[current];
+            self.frames[current].as_ref()
+        })
+    }
+
+    /// Returns block number `n` of `consumer`, and the block before it.
+    fn nth(&self, consumer: usize, n: usize) -> Option<(u16, u16)> {
+        let mut previous = NIL;
+        let mut block = self.consumers[consumer].head;
+        for _ in 0..n {
+            if block == NIL {
+                return None;
+            }
+            previous = block;
+            block = self.next[block as usize];
+        }
+        if block == NIL {
+            None
+        } else {
+            Some((previous, block))
+        }
+    }
+
+    /// Unlinks `block` from the list of `consumer` and returns its frame, `previous` is the block before it in the list.
+    fn unlink(&mut self, consumer: usize, previous: u16, block: u16) -> Option<F> {
+        let next = self.next[block as usize];
+        let list = &mut self.consumers[consumer];
+        if previous == NIL {
+            list.head = next;
+        } else {
+            self.next[previous as usize] = next;
+        }
+        if list.tail == block {
+            list.tail = previous;
+        }
+        list.used -= 1;
+
+        self.next[block as usize] = self.free;
+        self.free = block;
+        self.stats.used -= 1;
+        self.frames[block as usize].take()
+    }
+
+    /// Returns the first block of `consumer` holding a frame matching `predicate`, and the block before it.
+    fn find<P: FnMut(&F) -> bool>(&self, consumer: usize, mut predicate: P) -> Option<(u16, u16)> {
+        let mut previous = NIL;
+        let mut block = self.consumers[consumer].head;
+        while block != NIL {
+            if self.frames[block as usize].as_ref().map_or(false, &mut predicate) {
+                return Some((previous, block));
+            }
+            previous = block;
+            block = self.next[block as usize];
+        }
+        None
+    }
+
+    fn retain(&mut self, consumer: usize, predicate: &mut dyn FnMut(&F) -> bool) {
+        let mut previous = NIL;
+        let mut block = self.consumers[consumer].head;
+        while block != NIL {
+            let next = self.next[block as usize];
+            if self.frames[block as usize].as_ref().map_or(false, &mut *predicate) {
+                previous = block;
+            } else {
+                self.unlink(consumer, previous, block);
+            }
+            block = next;
+        }
+    }
+
+    /// Returns the block with the highest priority frame of `consumer`, the first one inserted if several have the same priority.
+    fn max_priority(&self, consumer: usize) -> Option<(u16, u16)> {
+        let mut max: Option<(u16, u16, TransferFrameID)> = None;
+        let mut previous = NIL;
+        let mut block = self.consumers[consumer].head;
+        while block != NIL {
+            if let Some(ref frame) = self.frames[block as usize] {
+                if max.map_or(true, |(_, _, id)| Priority(frame.id()) > Priority(id)) {
+                    max = Some((previous, block, frame.id()));
+                }
+            }
+            previous = block;
+            block = self.next[block as usize];
+        }
+        max.map(|(previous, block, _)| (previous, block))
+    }
+}
+
+/// A fixed pool of `BLOCKS` frame sized blocks shared by up to `CONSUMERS` subscribers and interface queues.
+///
+/// Unlike the `StoragePool`, which reserves room for a fixed number of frames for every queue,
+/// the blocks are handed out to whichever queue needs them. A quota limits how many blocks a single queue can hold,
+/// so that one busy subscription can't starve the others.
+///
+/// Access is serialized with critical sections. The pool is normally declared with `static_block_pool!`
+/// and used through a `StaticStorage`.
+pub struct BlockPool<F, const BLOCKS: usize, const CONSUMERS: usize> {
+    blocks: Mutex<RefCell<Blocks<F, BLOCKS, CONSUMERS>>>,
+}
+
+impl<F, const BLOCKS: usize, const CONSUMERS: usize> BlockPool<F, BLOCKS, CONSUMERS> {
+    const NO_FRAME: Option<F> = None;
+
+    /// Creates a pool where every queue may use all blocks.
+    ///
+    /// # Panics
+    /// Panics if `BLOCKS` is `u16::MAX` or more.
+    pub const fn new() -> Self {
+        assert!(BLOCKS < NIL as usize, "A BlockPool can have at most 65534 blocks");
+
+        let mut next = [NIL; BLOCKS];
+        let mut i = 0;
+        while i + 1 < BLOCKS {
+            next[i] = (i + 1) as u16;
+            i += 1;
+        }
+
+        BlockPool {
+            blocks: Mutex::new(RefCell::new(Blocks {
+                frames: [Self::NO_FRAME; BLOCKS],
+                next,
+                free: if BLOCKS == 0 { NIL } else { 0 },
+                consumers: [Consumer::FREE; CONSUMERS],
+                subscriber_quota: BLOCKS,
+                interface_quota: BLOCKS,
+                stats: PoolStats {
+                    capacity: BLOCKS,
+                    used: 0,
+                    peak: 0,
+                    allocation_failures: 0,
+                },
+            })),
+        }
+    }
+
+    /// Sets the number of blocks each subscriber and each interface queue may use.
+    ///
+    /// The quotas only apply to queues created afterwards.
+    pub fn set_quotas(&self, subscriber: usize, interface: usize) {
+        critical_section::with(|cs| {
+            let mut blocks = self.blocks.borrow(cs).borrow_mut();
+            blocks.subscriber_quota = subscriber;
+            blocks.interface_quota = interface;
+        })
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        critical_section::with(|cs| self.blocks.borrow(cs).borrow().stats)
+    }
+
+    /// Returns the statistics of every subscriber and interface queue, indexed by slot.
+    pub fn consumer_stats(&self) -> [Option<ConsumerStats>; CONSUMERS] {
+        critical_section::with(|cs| {
+            let blocks = self.blocks.borrow(cs).borrow();
+            let mut stats = [None; CONSUMERS];
+            for (stats, consumer) in stats.iter_mut().zip(blocks.consumers.iter()) {
+                let filter = match consumer.kind {
+                    ConsumerKind::Free => continue,
+                    ConsumerKind::Subscriber(filter) => Some(filter),
+                    ConsumerKind::Interface => None,
+                };
+                *stats = Some(ConsumerStats {
+                    filter,
+                    quota: consumer.quota,
+                    used: consumer.used,
+                    peak: consumer.peak,
+                });
+            }
+            stats
+        })
+    }
+}
+
+impl<F, const BLOCKS: usize, const CONSUMERS: usize> Default for BlockPool<F, BLOCKS, CONSUMERS> {
+    fn default() -> Self {
+        BlockPool::new()
+    }
+}
+
+impl<F: TransferFrame + Clone + Send, const BLOCKS: usize, const CONSUMERS: usize> FramePool<F> for BlockPool<F, BLOCKS, CONSUMERS> {
+    fn subscribe(&self, filter: TransferFrameIDFilter) -> Option<usize> {
+        critical_section::with(|cs| self.blocks.borrow(cs).borrow_mut().add_consumer(ConsumerKind::Subscriber(filter)))
+    }
+
+    fn unsubscribe(&self, subscriber: usize) {
+        critical_section::with(|cs| self.blocks.borrow(cs).borrow_mut().remove_consumer(subscriber))
+    }
+
+    fn new_interface(&self) -> Option<usize> {
+        critical_section::with(|cs| self.blocks.borrow(cs).borrow_mut().add_consumer(ConsumerKind::Interface))
+    }
+
+    fn remove_interface(&self, interface: usize) {
+        critical_section::with(|cs| self.blocks.borrow(cs).borrow_mut().remove_consumer(interface))
+    }
+
+    fn insert_subscriber_queue(&self, frame: F, policy: DropPolicy) -> Result<Inserted, StorageError> {
+        critical_section::with(|cs| {
+            let mut blocks = self.blocks.borrow(cs).borrow_mut();
+            let mut result = Ok(Inserted::Queued);
+            for consumer in 0..CONSUMERS {
+                let matches = match blocks.consumers[consumer].kind {
+                    ConsumerKind::Subscriber(filter) => filter.is_match(frame.id()),
+                    _ => false,
+                };
+                if matches {
+                    result = Inserted::merge(result, blocks.push(consumer, frame.clone(), policy));
+                }
+            }
+            result
+        })
+    }
+
+    fn insert_interface_queue(&self, frame: F) -> Result<(), StorageError> {
+        critical_section::with(|cs| {
+            let mut blocks = self.blocks.borrow(cs).borrow_mut();
+            let mut result = Ok(());
+            for consumer in 0..CONSUMERS {
+                if blocks.consumers[consumer].kind == ConsumerKind::Interface {
+                    if let Err(error) = blocks.push(consumer, frame.clone(), DropPolicy::DropNewest) {
+                        result = Err(error);
+                    }
+                }
+            }
+            result
+        })
+    }
+
+    fn subscriber_pop(&self, subscriber: usize) -> Option<F> {
+        critical_section::with(|cs| {
+            let mut blocks = self.blocks.borrow(cs).borrow_mut();
+            let (previous, block) = blocks.max_priority(subscriber)?;
+            blocks.unlink(subscriber, previous, block)
+        })
+    }
+
+    fn subscriber_remove(&self, subscriber: usize, identifier: &TransferFrameID) -> Option<F> {
+        critical_section::with(|cs| {
+            let mut blocks = self.blocks.borrow(cs).borrow_mut();
+            let (previous, block) = blocks.find(subscriber, |x| x.id() == *identifier)?;
+            blocks.unlink(subscriber, previous, block)
+        })
+    }
+
+    fn subscriber_find_id(&self, subscriber: usize, predicate: &mut dyn FnMut(&F) -> bool) -> Option<FullTransferID> {
+        critical_section::with(|cs| {
+            let blocks = self.blocks.borrow(cs).borrow();
+            let (_, block) = blocks.find(subscriber, predicate)?;
+            let id = blocks.frames[block as usize].as_ref().map(|x| x.full_id());
+            id
+        })
+    }
+
+    fn subscriber_retain(&self, subscriber: usize, predicate: &mut dyn FnMut(&F) -> bool) {
+        critical_section::with(|cs| self.blocks.borrow(cs).borrow_mut().retain(subscriber, predicate))
+    }
+
+    fn interface_pop(&self, interface: usize) -> Option<F> {
+        critical_section::with(|cs| {
+            let mut blocks = self.blocks.borrow(cs).borrow_mut();
+            let (previous, block) = blocks.max_priority(interface)?;
+            blocks.unlink(interface, previous, block)
+        })
+    }
+
+    fn interface_max_priority(&self, interface: usize) -> Option<TransferFrameID> {
+        critical_section::with(|cs| {
+            let blocks = self.blocks.borrow(cs).borrow();
+            let (_, block) = blocks.max_priority(interface)?;
+            let id = blocks.frames[block as usize].as_ref().map(|x| x.id());
+            id
+        })
+    }
+
+    fn interface_push(&self, interface: usize, frame: F) -> Result<(), StorageError> {
+        critical_section::with(|cs| self.blocks.borrow(cs).borrow_mut().push(interface, frame, DropPolicy::DropNewest).map(|_| ()))
+    }
+
+    fn interface_push_front(&self, interface: usize, frame: F) -> Result<(), StorageError> {
+        critical_section::with(|cs| self.blocks.borrow(cs).borrow_mut().push_front(interface, frame))
+    }
+
+    fn interface_remove_transfer(&self, interface: usize, full_id: &FullTransferID) {
+        critical_section::with(|cs| self.blocks.borrow(cs).borrow_mut().retain(interface, &mut |x| x.full_id() != *full_id))
+    }
+}
+
+/// Declares a type implementing `StaticPool` for a `BlockPool` with the given capacities.
+///
+/// # Examples
+/// ```
+/// # #[macro_use] extern crate uavcan;
+/// # extern crate embedded_types;
+/// # use embedded_types::can::ExtendedDataFrame;
+/// use uavcan::storage::StaticStorage;
+///
+/// static_block_pool!(CanPool: ExtendedDataFrame, blocks = 512, consumers = 64);
+///
+/// type CanStorage = StaticStorage<ExtendedDataFrame, CanPool>;
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! static_block_pool {
+    ($(#[$attr:meta])* $vis:vis $name:ident: $frame:ty, blocks = $blocks:expr, consumers = $consumers:expr) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        $vis struct $name;
+
+        impl $crate::storage::StaticPool<$frame> for $name {
+            type Pool = $crate::storage::BlockPool<$frame, {$blocks}, {$consumers}>;
+
+            fn pool() -> &'static Self::Pool {
+                static POOL: $crate::storage::BlockPool<$frame, {$blocks}, {$consumers}> = $crate::storage::BlockPool::new();
+                &POOL
+            }
+        }
+    };
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use storage::*;
+
+    use transfer::{
+        TransferFrame,
+        TransferFrameID,
+        TransferFrameIDFilter,
+    };
+
+    use tests::CanFrame;
+
+    fn frame(id: u32, data: u8) -> CanFrame {
+        let mut frame = CanFrame::new(TransferFrameID::new(id));
+        frame.set_data_length(1);
+        frame.data_as_mut()[0] = data;
+        frame
+    }
+
+    #[test]
+    fn blocks_are_shared() {
+        static_block_pool!(Pool: CanFrame, blocks = 4, consumers = 3);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+
+        let all = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        let odd = storage.subscribe_to(TransferFrameIDFilter::new(1, 1)).unwrap();
+        let interface = storage.new_interface().unwrap();
+
+        storage.insert_subscriber_queue(frame(2, 0), DropPolicy::DropNewest).unwrap();
+        storage.insert_subscriber_queue(frame(2, 1), DropPolicy::DropNewest).unwrap();
+        storage.insert_subscriber_queue(frame(3, 2), DropPolicy::DropNewest).unwrap();
+        assert_eq!(interface.push(frame(1, 3)), Err(StorageError::OutOfSpace));
+        assert_eq!(Pool::pool().stats(), PoolStats{capacity: 4, used: 4, peak: 4, allocation_failures: 1});
+
+        // Removing from the middle of a list frees the block for other queues
+        assert_eq!(all.remove(&TransferFrameID::new(3)).unwrap().data(), &[2]);
+        interface.push(frame(1, 3)).unwrap();
+
+        assert_eq!(all.remove(&TransferFrameID::new(2)).unwrap().data(), &[0]);
+        assert_eq!(all.remove(&TransferFrameID::new(2)).unwrap().data(), &[1]);
+        assert!(all.remove(&TransferFrameID::new(2)).is_none());
+        assert_eq!(interface.pop().unwrap().data(), &[3]);
+
+        let stats = Pool::pool().consumer_stats();
+        assert_eq!(stats[0], Some(ConsumerStats{filter: Some(TransferFrameIDFilter::new(0, 0)), quota: 4, used: 0, peak: 3}));
+        assert_eq!(stats[1].unwrap().used, 1);
+        assert_eq!(stats[2].unwrap().filter, None);
+
+        // Dropping a subscriber returns its blocks
+        drop(odd);
+        assert_eq!(Pool::pool().stats().used, 0);
+        assert_eq!(Pool::pool().consumer_stats()[1], None);
+    }
+
+    #[test]
+    fn quotas() {
+        static_block_pool!(Pool: CanFrame, blocks = 8, consumers = 3);
+        Pool::pool().set_quotas(2, 3);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+
+        let first = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        let second = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        let interface = storage.new_interface().unwrap();
+
+        storage.insert_subscriber_queue(frame(1, 0), DropPolicy::DropNewest).unwrap();
+        storage.insert_subscriber_queue(frame(1, 1), DropPolicy::DropNewest).unwrap();
+        second.retain(|_| false);
+        assert_eq!(storage.insert_subscriber_queue(frame(1, 2), DropPolicy::DropNewest), Err(StorageError::OutOfSpace));
+
+        // The subscriber below its quota still gets the frame
+        assert_eq!(second.find_id(|_| true).map(|x| x.frame_id), Some(TransferFrameID::new(1)));
+        assert_eq!(first.remove(&TransferFrameID::new(1)).unwrap().data(), &[0]);
+
+        for data in 0..3 {
+            interface.push(frame(5 - u32::from(data), data)).unwrap();
+        }
+        assert!(interface.push(frame(1, 3)).is_err());
+        assert_eq!(interface.max_priority(), Some(TransferFrameID::new(3)));
+        assert_eq!(interface.pop().unwrap().data(), &[2]);
+        assert_eq!(interface.pop().unwrap().data(), &[1]);
+        assert_eq!(interface.pop().unwrap().data(), &[0]);
+    }
+
+    #[test]
+    fn push_front_keeps_order() {
+        static_block_pool!(Pool: CanFrame, blocks = 3, consumers = 1);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+        let interface = storage.new_interface().unwrap();
+
+        interface.push(frame(5, 0)).unwrap();
+        interface.push(frame(5, 1)).unwrap();
+        let first = interface.pop().unwrap();
+        interface.push(frame(5, 2)).unwrap();
+        interface.push_front(first).unwrap();
+        assert_eq!(interface.push_front(frame(5, 3)), Err(StorageError::OutOfSpace));
+
+        assert_eq!(interface.pop().unwrap().data(), &[0]);
+        assert_eq!(interface.pop().unwrap().data(), &[1]);
+        assert_eq!(interface.pop().unwrap().data(), &[2]);
+        assert!(interface.pop().is_none());
+        assert_eq!(Pool::pool().stats().used, 0);
+    }
+
+    #[test]
+    fn drop_oldest_when_exhausted() {
+        static_block_pool!(Pool: CanFrame, blocks = 3, consumers = 2);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+
+        let second = storage.subscribe_to(TransferFrameIDFilter::new(2, 2)).unwrap();
+        let first = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        storage.insert_subscriber_queue(frame(1, 0), DropPolicy::DropOldest).unwrap();
+        storage.insert_subscriber_queue(frame(1, 1), DropPolicy::DropOldest).unwrap();
+
+        // The second subscriber takes the last block, the first one makes room by dropping its own oldest frame
+        assert_eq!(storage.insert_subscriber_queue(frame(2, 2), DropPolicy::DropOldest), Ok(Inserted::DroppedOther));
+        assert_eq!(first.remove(&TransferFrameID::new(1)).unwrap().data(), &[1]);
+        assert_eq!(first.remove(&TransferFrameID::new(2)).unwrap().data(), &[2]);
+        assert_eq!(second.remove(&TransferFrameID::new(2)).unwrap().data(), &[2]);
+        assert_eq!(Pool::pool().stats().used, 0);
+    }
+}
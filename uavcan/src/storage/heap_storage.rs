@@ -5,6 +5,7 @@ use std::sync::{
 };
 
 use std::collections::BinaryHeap;
+use std::cmp::Reverse;
 
 use transfer::TransferFrame;
 use transfer::TransferFrameID;
@@ -16,27 +17,94 @@ use storage::Storage;
 use storage::SubscriberStorageHandle;
 use storage::InterfaceStorageHandle;
 use storage::StorageError;
+use storage::DropPolicy;
+use storage::Inserted;
 
+/// A `Storage` keeping the frames in heap allocated queues.
+///
+/// The queues grow as needed, so frames are never dropped and the `DropPolicy` is not used.
+/// Dropping a handle removes its queue from the storage.
 pub struct HeapStorage<F: TransferFrame> {
-    subscriber_list: Mutex<Vec<SubscriberListEntry<F>>>,
-    interface_list: Mutex<Vec<InterfaceListEntry<F>>>,
+    subscriber_list: Arc<Mutex<Vec<SubscriberListEntry<F>>>>,
+    interface_list: Arc<Mutex<Vec<InterfaceListEntry<F>>>>,
 }
 
 struct SubscriberListEntry<F: TransferFrame> {
     filter: TransferFrameIDFilter,
-    storage: Weak<Mutex<Vec<F>>>,
+    storage: Weak<Mutex<FrameQueue<F>>>,
 }
 
 struct InterfaceListEntry<F: TransferFrame> {
-    storage: Weak<Mutex<BinaryHeap<Priority<F>>>>,
+    storage: Weak<Mutex<FrameQueue<F>>>,
 }
 
-pub struct HeapSubscriberStorage<F> {
-    storage: Arc<Mutex<Vec<F>>>,
+/// A priority queue of frames where frames with equal priority are kept in FIFO order.
+///
+/// The frames of a multi frame transfer share the same `TransferFrameID` and must be kept in order.
+/// Frames put back at the front get sequence numbers below all others.
+struct FrameQueue<F: TransferFrame> {
+    heap: BinaryHeap<(Priority<F>, Reverse<i64>)>,
+    next_sequence: i64,
+    front_sequence: i64,
 }
 
-pub struct HeapInterfaceStorage<F> {
-    storage: Arc<Mutex < BinaryHeap < Priority < F > > > >,
+impl<F: TransferFrame> FrameQueue<F> {
+    fn new() -> Self {
+        FrameQueue {
+            heap: BinaryHeap::new(),
+            next_sequence: 0,
+            front_sequence: 0,
+        }
+    }
+
+    fn push(&mut self, frame: F) {
+        self.heap.push((Priority(frame), Reverse(self.next_sequence)));
+        self.next_sequence += 1;
+    }
+
+    fn push_front(&mut self, frame: F) {
+        self.front_sequence -= 1;
+        self.heap.push((Priority(frame), Reverse(self.front_sequence)));
+    }
+
+    fn pop(&mut self) -> Option<F> {
+        Some((self.heap.pop()?.0).0)
+    }
+
+    /// Returns the first inserted frame matching `predicate`.
+    fn find<P: FnMut(&F) -> bool>(&self, mut predicate: P) -> Option<&F> {
+        self.heap.iter()
+            .filter(|x| predicate(&(x.0).0))
+            .max_by_key(|x| x.1)
+            .map(|x| &(x.0).0)
+    }
+
+    /// Removes the first inserted frame with `identifier`.
+    fn remove(&mut self, identifier: &TransferFrameID) -> Option<F> {
+        let mut entries = ::std::mem::take(&mut self.heap).into_vec();
+        let index = entries.iter()
+            .enumerate()
+            .filter(|(_, x)| (x.0).0.id() == *identifier)
+            .max_by_key(|(_, x)| x.1)
+            .map(|(index, _)| index);
+        let frame = index.map(|index| (entries.swap_remove(index).0).0);
+        self.heap = BinaryHeap::from(entries);
+        frame
+    }
+
+    fn retain<P: FnMut(&F) -> bool>(&mut self, mut predicate: P) {
+        self.heap.retain(|x| predicate(&(x.0).0));
+    }
+}
+
+pub struct HeapSubscriberStorage<F: TransferFrame> {
+    storage: Arc<Mutex<FrameQueue<F>>>,
+    subscriber_list: Weak<Mutex<Vec<SubscriberListEntry<F>>>>,
+}
+
+pub struct HeapInterfaceStorage<F: TransferFrame> {
+    storage: Arc<Mutex<FrameQueue<F>>>,
+    interface_list: Weak<Mutex<Vec<InterfaceListEntry<F>>>>,
 }
 
 impl<F: TransferFrame + Clone> Storage<F> for HeapStorage<F> {
@@ -45,13 +113,13 @@ impl<F: TransferFrame + Clone> Storage<F> for HeapStorage<F> {
 
     fn new() -> Self {
         HeapStorage {
-            subscriber_list: Mutex::new(Vec::new()),
-            interface_list: Mutex::new(Vec::new()),
+            subscriber_list: Arc::new(Mutex::new(Vec::new())),
+            interface_list: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
-    fn subscribe_to(&self, filter: TransferFrameIDFilter) -> Self::SubscriberStorageHandle {
-        let storage = Arc::new(Mutex::new(Vec::new()));
+    fn subscribe_to(&self, filter: TransferFrameIDFilter) -> Result<Self::SubscriberStorageHandle, StorageError> {
+        let storage = Arc::new(Mutex::new(FrameQueue::new()));
 
         let subsciber_list_entry = SubscriberListEntry {
             filter,
@@ -60,15 +128,17 @@ impl<F: TransferFrame + Clone> Storage<F> for HeapStorage<F> {
 
         let subscriber_handle = HeapSubscriberStorage {
             storage,
+            subscriber_list: Arc::downgrade(&self.subscriber_list),
         };
 
-        // TODO: attempt to replace old (weak pointers that turns into None) list entries before creating new
-        self.subscriber_list.lock().unwrap().push(subsciber_list_entry);
-        subscriber_handle
+        let mut subscriber_list = self.subscriber_list.lock().unwrap();
+        subscriber_list.retain(|x| x.storage.strong_count() > 0);
+        subscriber_list.push(subsciber_list_entry);
+        Ok(subscriber_handle)
     }
 
-    fn new_interface(&self) -> Self::InterfaceStorageHandle {
-        let storage = Arc::new(Mutex::new(BinaryHeap::new()));
+    fn new_interface(&self) -> Result<Self::InterfaceStorageHandle, StorageError> {
+        let storage = Arc::new(Mutex::new(FrameQueue::new()));
 
         let interface_list_entry = InterfaceListEntry {
             storage: Arc::downgrade(&storage),
@@ -76,38 +146,43 @@ impl<F: TransferFrame + Clone> Storage<F> for HeapStorage<F> {
 
         let interface_handle = HeapInterfaceStorage {
             storage,
+            interface_list: Arc::downgrade(&self.interface_list),
         };
 
-        self.interface_list.lock().unwrap().push(interface_list_entry);
-        interface_handle
+        let mut interface_list = self.interface_list.lock().unwrap();
+        interface_list.retain(|x| x.storage.strong_count() > 0);
+        interface_list.push(interface_list_entry);
+        Ok(interface_handle)
     }
 
 
-    fn insert_subscriber_queue(&self, frame: F) -> Result<(), StorageError> {
+    fn insert_subscriber_queue(&self, frame: F, _policy: DropPolicy) -> Result<Inserted, StorageError> {
         for storage in self.subscriber_list.lock().unwrap().iter().filter(|x| x.filter.is_match(frame.id())).filter_map(|x| x.storage.upgrade()) {
             storage.lock().unwrap().push(frame.clone());
         }
-        Ok(())
+        Ok(Inserted::Queued)
     }
 
     fn insert_interface_queue(&self, frame: F) -> Result<(), StorageError> {
         for storage in self.interface_list.lock().unwrap().iter().filter_map(|x| x.storage.upgrade()) {
-            storage.lock().unwrap().push(Priority(frame.clone()));
+            storage.lock().unwrap().push(frame.clone());
         }
         Ok(())
     }
 }
 
 impl<F: TransferFrame> SubscriberStorageHandle<F> for HeapSubscriberStorage<F> {
+    fn pop(&self) -> Option<F> {
+        self.storage.lock().unwrap().pop()
+    }
+
     fn remove(&self, identifier: &TransferFrameID) -> Option<F> {
-        let mut queue = self.storage.lock().unwrap();
-        let pos = queue.iter().position(|x| x.id() == *identifier)?;
-        Some(queue.remove(pos))
+        self.storage.lock().unwrap().remove(identifier)
     }
 
-    fn find_id<P>(&self, mut predicate: P) -> Option<FullTransferID>
+    fn find_id<P>(&self, predicate: P) -> Option<FullTransferID>
         where P: FnMut(&F) -> bool {
-        Some(self.storage.lock().unwrap().iter().find(|x: &&F| predicate(*x))?.full_id())
+        Some(self.storage.lock().unwrap().find(predicate)?.full_id())
     }
 
     fn retain<P>(&self, predicate: P)
@@ -118,15 +193,143 @@ impl<F: TransferFrame> SubscriberStorageHandle<F> for HeapSubscriberStorage<F> {
 
 impl<F: TransferFrame> InterfaceStorageHandle<F> for HeapInterfaceStorage<F> {
     fn pop(&self) -> Option<F> {
-        Some(self.storage.lock().unwrap().pop()?.0)
+        self.storage.lock().unwrap().pop()
     }
 
     fn max_priority(&self) -> Option<TransferFrameID> {
-        Some(self.storage.lock().unwrap().peek()?.0.id())
+        Some((self.storage.lock().unwrap().heap.peek()?.0).0.id())
     }
 
     fn push(&self, frame: F) -> Result<(), StorageError> {
-        self.storage.lock().unwrap().push(Priority(frame));
+        self.storage.lock().unwrap().push(frame);
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn push_front(&self, frame: F) -> Result<(), StorageError> {
+        self.storage.lock().unwrap().push_front(frame);
+        Ok(())
+    }
+
+    fn remove_transfer(&self, full_id: &FullTransferID) {
+        self.storage.lock().unwrap().retain(|x| x.full_id() != *full_id);
+    }
+}
+
+impl<F: TransferFrame> Drop for HeapSubscriberStorage<F> {
+    fn drop(&mut self) {
+        let own = Arc::as_ptr(&self.storage);
+        if let Some(subscriber_list) = self.subscriber_list.upgrade() {
+            if let Ok(mut subscriber_list) = subscriber_list.lock() {
+                subscriber_list.retain(|x| x.storage.strong_count() > 0 && x.storage.as_ptr() != own);
+            }
+        }
+    }
+}
+
+impl<F: TransferFrame> Drop for HeapInterfaceStorage<F> {
+    fn drop(&mut self) {
+        let own = Arc::as_ptr(&self.storage);
+        if let Some(interface_list) = self.interface_list.upgrade() {
+            if let Ok(mut interface_list) = interface_list.lock() {
+                interface_list.retain(|x| x.storage.strong_count() > 0 && x.storage.as_ptr() != own);
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use storage::*;
+
+    use transfer::{
+        TransferFrame,
+        TransferFrameID,
+        TransferFrameIDFilter,
+    };
+
+    use tests::CanFrame;
+
+    fn frame(id: u32, data: u8) -> CanFrame {
+        let mut frame = CanFrame::new(TransferFrameID::new(id));
+        frame.set_data_length(1);
+        frame.data_as_mut()[0] = data;
+        frame
+    }
+
+    #[test]
+    fn equal_priority_keeps_order() {
+        let storage: HeapStorage<CanFrame> = HeapStorage::new();
+        let interface = storage.new_interface().unwrap();
+
+        for data in 0..4 {
+            storage.insert_interface_queue(frame(5, data)).unwrap();
+        }
+        storage.insert_interface_queue(frame(2, 4)).unwrap();
+
+        assert_eq!(interface.pop().unwrap().data(), &[4]);
+        for data in 0..4 {
+            assert_eq!(interface.pop().unwrap().data(), &[data]);
+        }
+        assert!(interface.pop().is_none());
+    }
+
+    #[test]
+    fn push_front_keeps_order() {
+        let storage: HeapStorage<CanFrame> = HeapStorage::new();
+        let interface = storage.new_interface().unwrap();
+
+        storage.insert_interface_queue(frame(5, 0)).unwrap();
+        storage.insert_interface_queue(frame(5, 1)).unwrap();
+        let first = interface.pop().unwrap();
+        storage.insert_interface_queue(frame(5, 2)).unwrap();
+        interface.push_front(first).unwrap();
+
+        assert_eq!(interface.pop().unwrap().data(), &[0]);
+        assert_eq!(interface.pop().unwrap().data(), &[1]);
+        assert_eq!(interface.pop().unwrap().data(), &[2]);
+        assert!(interface.pop().is_none());
+    }
+
+    #[test]
+    fn subscriber_pop_by_priority() {
+        let storage: HeapStorage<CanFrame> = HeapStorage::new();
+        let subscriber = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+
+        for (id, data) in [(5, 0), (2, 1), (5, 2), (2, 3)] {
+            storage.insert_subscriber_queue(frame(id, data), DropPolicy::DropNewest).unwrap();
+        }
+        assert_eq!(subscriber.remove(&TransferFrameID::new(5)).unwrap().data(), &[0]);
+
+        assert_eq!(subscriber.pop().unwrap().data(), &[1]);
+        assert_eq!(subscriber.pop().unwrap().data(), &[3]);
+        assert_eq!(subscriber.pop().unwrap().data(), &[2]);
+        assert!(subscriber.pop().is_none());
+    }
+
+    #[test]
+    fn dropped_handles_are_removed() {
+        let storage: HeapStorage<CanFrame> = HeapStorage::new();
+
+        let first = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        let second = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        let interface = storage.new_interface().unwrap();
+        let other_interface = storage.new_interface().unwrap();
+
+        drop(first);
+        drop(other_interface);
+        assert_eq!(storage.subscriber_list.lock().unwrap().len(), 1);
+        assert_eq!(storage.interface_list.lock().unwrap().len(), 1);
+
+        storage.insert_subscriber_queue(CanFrame::new(TransferFrameID::new(1)), DropPolicy::DropNewest).unwrap();
+        storage.insert_interface_queue(CanFrame::new(TransferFrameID::new(2))).unwrap();
+        assert!(second.remove(&TransferFrameID::new(1)).is_some());
+        assert_eq!(interface.pop().map(|x| x.id()), Some(TransferFrameID::new(2)));
+
+        // Handles may outlive the storage
+        drop(storage);
+        drop(second);
+        drop(interface);
+    }
+}
@@ -9,17 +9,98 @@ pub mod heap_storage;
 #[cfg(feature="std")]
 pub use self::heap_storage::HeapStorage;
 
+mod static_storage;
+
+pub use self::static_storage::{
+    StaticStorage,
+    StaticSubscriberStorage,
+    StaticInterfaceStorage,
+    StaticPool,
+    StoragePool,
+    FramePool,
+};
+
+mod block_pool;
+
+pub use self::block_pool::{
+    BlockPool,
+    PoolStats,
+    ConsumerStats,
+};
+
 use transfer::TransferFrame;
 use transfer::TransferFrameID;
 use transfer::FullTransferID;
 use transfer::TransferFrameIDFilter;
+use transfer::Priority;
 
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StorageError {
+    /// A queue was full and the frame being inserted was dropped,
+    /// or there was no free subscriber or interface slot left.
     OutOfSpace,
 }
 
+/// The outcome of a successful insertion into the subscriber queues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inserted {
+    /// The frame was queued without dropping anything.
+    Queued,
+    /// The frame was queued, but a full queue dropped another frame according to the `DropPolicy` to make room.
+    DroppedOther,
+}
+
+impl Inserted {
+    /// Combines the outcomes of inserting the same frame into several queues.
+    ///
+    /// Dropping the inserted frame anywhere takes precedence over dropping another frame.
+    pub(crate) fn merge(first: Result<Inserted, StorageError>, second: Result<Inserted, StorageError>) -> Result<Inserted, StorageError> {
+        match (first, second) {
+            (Err(error), _) | (_, Err(error)) => Err(error),
+            (Ok(Inserted::Queued), Ok(Inserted::Queued)) => Ok(Inserted::Queued),
+            _ => Ok(Inserted::DroppedOther),
+        }
+    }
+}
+
+/// Decides which frame to drop when inserting into a full subscriber queue.
+///
+/// Interface queues always drop the transfer being inserted, see `InterfaceStorageHandle::remove_transfer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropPolicy {
+    /// Drop the frame being inserted.
+    #[default]
+    DropNewest,
+    /// Drop the frame that have been in the queue for the longest time.
+    DropOldest,
+    /// Drop the frame with the lowest priority, the newest one if several have the same priority.
+    ///
+    /// This is the frame being inserted if none of the queued frames have a lower priority.
+    DropLowestPriority,
+}
+
+impl DropPolicy {
+    /// Returns the position of the queued frame to drop to make room for `frame`, or `None` if `frame` should be dropped.
+    ///
+    /// `queued` must yield the frames in the order they were inserted.
+    pub(crate) fn victim<'a, F: TransferFrame + 'a, I: Iterator<Item=&'a F>>(self, mut queued: I, frame: &F) -> Option<usize> {
+        match self {
+            DropPolicy::DropNewest => None,
+            DropPolicy::DropOldest => queued.next().map(|_| 0),
+            DropPolicy::DropLowestPriority => {
+                let mut lowest: Option<(usize, TransferFrameID)> = None;
+                for (index, queued) in queued.enumerate() {
+                    if lowest.map_or(true, |(_, id)| Priority(queued.id()) <= Priority(id)) {
+                        lowest = Some((index, queued.id()));
+                    }
+                }
+                lowest.filter(|&(_, id)| Priority(id) < Priority(frame.id())).map(|(index, _)| index)
+            },
+        }
+    }
+}
+
 pub trait Storage<F: TransferFrame> {
     type SubscriberStorageHandle: SubscriberStorageHandle<F>;
     type InterfaceStorageHandle: InterfaceStorageHandle<F>;
@@ -28,24 +109,38 @@ pub trait Storage<F: TransferFrame> {
     fn new() -> Self;
 
     /// Create a subscription on all frames matching a filter.
-    fn subscribe_to(&self, filter: TransferFrameIDFilter) -> Self::SubscriberStorageHandle;
+    ///
+    /// `Err(StorageError::OutOfSpace)` is returned if the storage can't hold another subscription.
+    fn subscribe_to(&self, filter: TransferFrameIDFilter) -> Result<Self::SubscriberStorageHandle, StorageError>;
 
     /// Creates an interface queue for a new interface.
-    fn new_interface(&self) -> Self::InterfaceStorageHandle;
+    ///
+    /// `Err(StorageError::OutOfSpace)` is returned if the storage can't hold another interface queue.
+    fn new_interface(&self) -> Result<Self::InterfaceStorageHandle, StorageError>;
 
     /// Insert a frame to storage and route it to the correct subscribers.
     ///
     /// If there are no relevant subscribers `frame` will be dropped.
     /// If there are multiple relevant subscribers `frame` will be routed to all of them.
-    fn insert_subscriber_queue(&self, frame: F) -> Result<(), StorageError>;
+    ///
+    /// A full queue drops a frame according to `policy`. `Err(StorageError::OutOfSpace)` is returned if `frame` itself
+    /// was dropped from any queue, and `Ok(Inserted::DroppedOther)` if it was stored in place of another frame.
+    fn insert_subscriber_queue(&self, frame: F, policy: DropPolicy) -> Result<Inserted, StorageError>;
 
     /// Insert a frame to storage and route it to the interface for transmission.
     ///
     /// If there are multiple interface storage queues the frame will be added to all of them.
+    ///
+    /// A full queue drops `frame`, and `Err(StorageError::OutOfSpace)` is returned.
     fn insert_interface_queue(&self, frame: F) -> Result<(), StorageError>;
 }
 
 pub trait SubscriberStorageHandle<F: TransferFrame> {
+    /// Removes and returns the frame with highest priority, or `None` if the queue is empty.
+    ///
+    /// Frames with equal priority are returned in the order they were inserted.
+    fn pop(&self) -> Option<F>;
+
     /// Remove and return the next frame matching the indentifier if such frame exist.
     ///
     /// It's important that `receive` returns frames in the correct order.
@@ -70,9 +165,21 @@ pub trait InterfaceStorageHandle<F: TransferFrame> {
     /// Returns the `TransferFrameID` of the `TransferFrame` with highest priority, or `None` if the queue is empty.
     fn max_priority(&self) -> Option<TransferFrameID>;
 
-    /// Pushes an item on the interface queue.
+    /// Pushes an item on this interface queue only.
     ///
-    /// This is the same as calling `insert_interface_queue` on the `Storage` which this handle is associated with.
+    /// A full queue drops `frame`, and `Err(StorageError::OutOfSpace)` is returned.
     fn push(&self, frame: F) -> Result<(), StorageError>;
+
+    /// Puts back a frame returned by `pop` that couldn't be transmitted.
+    ///
+    /// The frame is popped again before other frames of equal priority, keeping the frames of a transfer in order.
+    /// If the queue was filled up in the meantime, the frame is dropped and `Err(StorageError::OutOfSpace)` is returned.
+    fn push_front(&self, frame: F) -> Result<(), StorageError>;
+
+    /// Removes all frames of the transfer `full_id` from this interface queue.
+    ///
+    /// Used to drop the already queued frames of a transfer when one of its frames doesn't fit,
+    /// rather than transmitting a truncated transfer.
+    fn remove_transfer(&self, full_id: &FullTransferID);
 }
 
@@ -0,0 +1,655 @@
+use lib::core::cell::RefCell;
+use lib::core::marker::PhantomData;
+
+use critical_section::Mutex;
+
+use transfer::TransferFrame;
+use transfer::TransferFrameID;
+use transfer::FullTransferID;
+use transfer::TransferFrameIDFilter;
+use transfer::Priority;
+
+use storage::Storage;
+use storage::SubscriberStorageHandle;
+use storage::InterfaceStorageHandle;
+use storage::StorageError;
+use storage::DropPolicy;
+use storage::Inserted;
+
+/// A fixed capacity list of frames kept in insertion order.
+struct FrameQueue<F, const N: usize> {
+    frames: [Option<F>; N],
+    length: usize,
+}
+
+impl<F, const N: usize> FrameQueue<F, N> {
+    const EMPTY: Option<F> = None;
+
+    const fn new() -> Self {
+        FrameQueue {
+            frames: [Self::EMPTY; N],
+            length: 0,
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item=&F> {
+        self.frames[..self.length].iter().filter_map(|x| x.as_ref())
+    }
+
+    fn remove(&mut self, index: usize) -> Option<F> {
+        if index >= self.length {
+            return None;
+        }
+        let frame = self.frames[index].take();
+        self.frames[index..self.length].rotate_left(1);
+        self.length -= 1;
+        frame
+    }
+
+    fn retain<P: FnMut(&F) -> bool>(&mut self, mut predicate: P) {
+        let mut retained = 0;
+        for index in 0..self.length {
+            if self.frames[index].as_ref().map_or(false, &mut predicate) {
+                self.frames.swap(retained, index);
+                retained += 1;
+            } else {
+                self.frames[index] = None;
+            }
+        }
+        self.length = retained;
+    }
+
+    fn clear(&mut self) {
+        self.retain(|_| false);
+    }
+}
+
+impl<F: TransferFrame, const N: usize> FrameQueue<F, N> {
+    /// Returns the index of the frame with highest priority, the first one pushed if several have the same priority.
+    fn max_priority_index(&self) -> Option<usize> {
+        let mut max: Option<(usize, &F)> = None;
+        for (index, frame) in self.iter().enumerate() {
+            if max.map_or(true, |(_, max_frame)| Priority(frame.id()) > Priority(max_frame.id())) {
+                max = Some((index, frame));
+            }
+        }
+        max.map(|(index, _)| index)
+    }
+
+    fn pop(&mut self) -> Option<F> {
+        let index = self.max_priority_index()?;
+        self.remove(index)
+    }
+
+    fn push(&mut self, frame: F, policy: DropPolicy) -> Result<Inserted, StorageError> {
+        let mut inserted = Inserted::Queued;
+        if self.length == N {
+            let index = policy.victim(self.iter(), &frame).ok_or(StorageError::OutOfSpace)?;
+            self.remove(index);
+            inserted = Inserted::DroppedOther;
+        }
+        self.frames[self.length] = Some(frame);
+        self.length += 1;
+        Ok(inserted)
+    }
+
+    fn push_front(&mut self, frame: F) -> Result<(), StorageError> {
+        if self.length == N {
+            return Err(StorageError::OutOfSpace);
+        }
+        self.frames[..=self.length].rotate_right(1);
+        self.frames[0] = Some(frame);
+        self.length += 1;
+        Ok(())
+    }
+}
+
+struct SubscriberSlot<F, const N: usize> {
+    /// `None` while the slot is free.
+    filter: Option<TransferFrameIDFilter>,
+    queue: FrameQueue<F, N>,
+}
+
+struct InterfaceSlot<F, const N: usize> {
+    in_use: bool,
+    queue: FrameQueue<F, N>,
+}
+
+impl<F, const N: usize> SubscriberSlot<F, N> {
+    const FREE: Self = SubscriberSlot{filter: None, queue: FrameQueue::new()};
+}
+
+impl<F, const N: usize> InterfaceSlot<F, N> {
+    const FREE: Self = InterfaceSlot{in_use: false, queue: FrameQueue::new()};
+}
+
+/// Statically allocated memory for a `StaticStorage`.
+///
+/// Room is made for `SUBSCRIBERS` subscriptions with up to `SUBSCRIBER_FRAMES` frames queued each,
+/// and `INTERFACES` interfaces with up to `INTERFACE_FRAMES` frames queued each.
+/// Access is serialized with critical sections, so the pool can be shared with interrupt handlers.
+///
+/// The pool is normally declared with `static_storage_pool!`.
+pub struct StoragePool<F, const SUBSCRIBERS: usize, const SUBSCRIBER_FRAMES: usize, const INTERFACES: usize, const INTERFACE_FRAMES: usize> {
+    subscribers: Mutex<RefCell<[SubscriberSlot<F, SUBSCRIBER_FRAMES>; SUBSCRIBERS]>>,
+    interfaces: Mutex<RefCell<[InterfaceSlot<F, INTERFACE_FRAMES>; INTERFACES]>>,
+}
+
+impl<F, const SUBSCRIBERS: usize, const SUBSCRIBER_FRAMES: usize, const INTERFACES: usize, const INTERFACE_FRAMES: usize>
+    StoragePool<F, SUBSCRIBERS, SUBSCRIBER_FRAMES, INTERFACES, INTERFACE_FRAMES>
+{
+    pub const fn new() -> Self {
+        StoragePool {
+            subscribers: Mutex::new(RefCell::new([SubscriberSlot::FREE; SUBSCRIBERS])),
+            interfaces: Mutex::new(RefCell::new([InterfaceSlot::FREE; INTERFACES])),
+        }
+    }
+}
+
+impl<F, const SUBSCRIBERS: usize, const SUBSCRIBER_FRAMES: usize, const INTERFACES: usize, const INTERFACE_FRAMES: usize> Default
+    for StoragePool<F, SUBSCRIBERS, SUBSCRIBER_FRAMES, INTERFACES, INTERFACE_FRAMES>
+{
+    fn default() -> Self {
+        StoragePool::new()
+    }
+}
+
+/// The operations `StaticStorage` needs from a `StoragePool`.
+///
+/// This hides the capacities of the pool from the storage and handle types, it's not meant to be used directly.
+pub trait FramePool<F: TransferFrame> {
+    fn subscribe(&self, filter: TransferFrameIDFilter) -> Option<usize>;
+    fn unsubscribe(&self, subscriber: usize);
+    fn new_interface(&self) -> Option<usize>;
+    fn remove_interface(&self, interface: usize);
+
+    fn insert_subscriber_queue(&self, frame: F, policy: DropPolicy) -> Result<Inserted, StorageError>;
+    fn insert_interface_queue(&self, frame: F) -> Result<(), StorageError>;
+
+    fn subscriber_pop(&self, subscriber: usize) -> Option<F>;
+    fn subscriber_remove(&self, subscriber: usize, identifier: &TransferFrameID) -> Option<F>;
+    fn subscriber_find_id(&self, subscriber: usize, predicate: &mut dyn FnMut(&F) -> bool) -> Option<FullTransferID>;
+    fn subscriber_retain(&self, subscriber: usize, predicate: &mut dyn FnMut(&F) -> bool);
+
+    fn interface_pop(&self, interface: usize) -> Option<F>;
+    fn interface_max_priority(&self, interface: usize) -> Option<TransferFrameID>;
+    fn interface_push(&self, interface: usize, frame: F) -> Result<(), StorageError>;
+    fn interface_push_front(&self, interface: usize, frame: F) -> Result<(), StorageError>;
+    fn interface_remove_transfer(&self, interface: usize, full_id: &FullTransferID);
+}
+
+impl<F: TransferFrame + Clone + Send, const SUBSCRIBERS: usize, const SUBSCRIBER_FRAMES: usize, const INTERFACES: usize, const INTERFACE_FRAMES: usize> FramePool<F>
+    for StoragePool<F, SUBSCRIBERS, SUBSCRIBER_FRAMES, INTERFACES, INTERFACE_FRAMES>
+{
+    fn subscribe(&self, filter: TransferFrameIDFilter) -> Option<usize> {
+        critical_section::with(|cs| {
+            let mut subscribers = self.subscribers.borrow(cs).borrow_mut();
+            let index = subscribers.iter().position(|x| x.filter.is_none())?;
+            subscribers[index].filter = Some(filter);
+            subscribers[index].queue.clear();
+            Some(index)
+        })
+    }
+
+    fn unsubscribe(&self, subscriber: usize) {
+        critical_section::with(|cs| {
+            let mut subscribers = self.subscribers.borrow(cs).borrow_mut();
+            subscribers[subscriber].filter = None;
+            subscribers[subscriber].queue.clear();
+        })
+    }
+
+    fn new_interface(&self) -> Option<usize> {
+        critical_section::with(|cs| {
+            let mut interfaces = self.interfaces.borrow(cs).borrow_mut();
+            let index = interfaces.iter().position(|x| !x.in_use)?;
+            interfaces[index].in_use = true;
+            interfaces[index].queue.clear();
+            Some(index)
+        })
+    }
+
+    fn remove_interface(&self, interface: usize) {
+        critical_section::with(|cs| {
+            let mut interfaces = self.interfaces.borrow(cs).borrow_mut();
+            interfaces[interface].in_use = false;
+            interfaces[interface].queue.clear();
+        })
+    }
+
+    fn insert_subscriber_queue(&self, frame: F, policy: DropPolicy) -> Result<Inserted, StorageError> {
+        critical_section::with(|cs| {
+            let mut result = Ok(Inserted::Queued);
+            for slot in self.subscribers.borrow(cs).borrow_mut().iter_mut().filter(|x| x.filter.map_or(false, |filter| filter.is_match(frame.id()))) {
+                result = Inserted::merge(result, slot.queue.push(frame.clone(), policy));
+            }
+            result
+        })
+    }
+
+    fn insert_interface_queue(&self, frame: F) -> Result<(), StorageError> {
+        critical_section::with(|cs| {
+            let mut result = Ok(());
+            for slot in self.interfaces.borrow(cs).borrow_mut().iter_mut().filter(|x| x.in_use) {
+                if let Err(error) = slot.queue.push(frame.clone(), DropPolicy::DropNewest) {
+                    result = Err(error);
+                }
+            }
+            result
+        })
+    }
+
+    fn subscriber_pop(&self, subscriber: usize) -> Option<F> {
+        critical_section::with(|cs| self.subscribers.borrow(cs).borrow_mut()[subscriber].queue.pop())
+    }
+
+    fn subscriber_remove(&self, subscriber: usize, identifier: &TransferFrameID) -> Option<F> {
+        critical_section::with(|cs| {
+            let queue = &mut self.subscribers.borrow(cs).borrow_mut()[subscriber].queue;
+            let index = queue.iter().position(|x| x.id() == *identifier)?;
+            queue.remove(index)
+        })
+    }
+
+    fn subscriber_find_id(&self, subscriber: usize, predicate: &mut dyn FnMut(&F) -> bool) -> Option<FullTransferID> {
+        critical_section::with(|cs| {
+            Some(self.subscribers.borrow(cs).borrow()[subscriber].queue.iter().find(|x| predicate(x))?.full_id())
+        })
+    }
+
+    fn subscriber_retain(&self, subscriber: usize, predicate: &mut dyn FnMut(&F) -> bool) {
+        critical_section::with(|cs| {
+            self.subscribers.borrow(cs).borrow_mut()[subscriber].queue.retain(predicate)
+        })
+    }
+
+    fn interface_pop(&self, interface: usize) -> Option<F> {
+        critical_section::with(|cs| self.interfaces.borrow(cs).borrow_mut()[interface].queue.pop())
+    }
+
+    fn interface_max_priority(&self, interface: usize) -> Option<TransferFrameID> {
+        critical_section::with(|cs| {
+            let interfaces = self.interfaces.borrow(cs).borrow();
+            let queue = &interfaces[interface].queue;
+            let index = queue.max_priority_index()?;
+            let id = queue.iter().nth(index).map(|x| x.id());
+            id
+        })
+    }
+
+    fn interface_push(&self, interface: usize, frame: F) -> Result<(), StorageError> {
+        critical_section::with(|cs| self.interfaces.borrow(cs).borrow_mut()[interface].queue.push(frame, DropPolicy::DropNewest).map(|_| ()))
+    }
+
+    fn interface_push_front(&self, interface: usize, frame: F) -> Result<(), StorageError> {
+        critical_section::with(|cs| self.interfaces.borrow(cs).borrow_mut()[interface].queue.push_front(frame))
+    }
+
+    fn interface_remove_transfer(&self, interface: usize, full_id: &FullTransferID) {
+        critical_section::with(|cs| self.interfaces.borrow(cs).borrow_mut()[interface].queue.retain(|x| x.full_id() != *full_id))
+    }
+}
+
+/// Gives `StaticStorage` access to a `StoragePool` placed in a `static`.
+///
+/// Implemented by the types declared with `static_storage_pool!` and `static_block_pool!`.
+pub trait StaticPool<F: TransferFrame>: 'static {
+    type Pool: FramePool<F> + 'static;
+
+    fn pool() -> &'static Self::Pool;
+}
+
+/// Declares a type implementing `StaticPool` for a `StoragePool` with the given capacities.
+///
+/// # Examples
+/// ```
+/// # #[macro_use] extern crate uavcan;
+/// # extern crate embedded_types;
+/// # use embedded_types::can::ExtendedDataFrame;
+/// use uavcan::storage::StaticStorage;
+///
+/// static_storage_pool!(CanPool: ExtendedDataFrame, subscribers = 16, subscriber_frames = 32, interfaces = 1, interface_frames = 64);
+///
+/// type CanStorage = StaticStorage<ExtendedDataFrame, CanPool>;
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! static_storage_pool {
+    ($(#[$attr:meta])* $vis:vis $name:ident: $frame:ty, subscribers = $subscribers:expr, subscriber_frames = $subscriber_frames:expr, interfaces = $interfaces:expr, interface_frames = $interface_frames:expr) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        $vis struct $name;
+
+        impl $crate::storage::StaticPool<$frame> for $name {
+            type Pool = $crate::storage::StoragePool<$frame, {$subscribers}, {$subscriber_frames}, {$interfaces}, {$interface_frames}>;
+
+            fn pool() -> &'static Self::Pool {
+                static POOL: $crate::storage::StoragePool<$frame, {$subscribers}, {$subscriber_frames}, {$interfaces}, {$interface_frames}> = $crate::storage::StoragePool::new();
+                &POOL
+            }
+        }
+    };
+}
+
+/// A `Storage` without heap allocations, for `no_std` targets.
+///
+/// All frames are kept in the pool of `P`, either a `StoragePool` or a `BlockPool`, which have a fixed number of subscriber and interface slots.
+/// A slot is freed again when its handle is dropped.
+/// When a subscriber queue is full a frame is dropped according to the `DropPolicy`, see `Storage::insert_subscriber_queue`.
+///
+/// Every `StaticStorage` with the same `P` share the pool, so each node needs a pool of its own.
+#[derive(Debug)]
+pub struct StaticStorage<F: TransferFrame, P: StaticPool<F>> {
+    phantom: PhantomData<(F, P)>,
+}
+
+/// A subscription in a `StaticStorage`.
+#[derive(Debug)]
+pub struct StaticSubscriberStorage<F: TransferFrame, P: StaticPool<F>> {
+    index: usize,
+    phantom: PhantomData<(F, P)>,
+}
+
+/// An interface queue in a `StaticStorage`.
+#[derive(Debug)]
+pub struct StaticInterfaceStorage<F: TransferFrame, P: StaticPool<F>> {
+    index: usize,
+    phantom: PhantomData<(F, P)>,
+}
+
+impl<F: TransferFrame, P: StaticPool<F>> Storage<F> for StaticStorage<F, P> {
+    type SubscriberStorageHandle = StaticSubscriberStorage<F, P>;
+    type InterfaceStorageHandle = StaticInterfaceStorage<F, P>;
+
+    fn new() -> Self {
+        StaticStorage {
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns `Err(StorageError::OutOfSpace)` if all subscriber slots of the pool are in use.
+    fn subscribe_to(&self, filter: TransferFrameIDFilter) -> Result<Self::SubscriberStorageHandle, StorageError> {
+        let index = P::pool().subscribe(filter).ok_or(StorageError::OutOfSpace)?;
+        Ok(StaticSubscriberStorage {
+            index,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns `Err(StorageError::OutOfSpace)` if all interface slots of the pool are in use.
+    fn new_interface(&self) -> Result<Self::InterfaceStorageHandle, StorageError> {
+        let index = P::pool().new_interface().ok_or(StorageError::OutOfSpace)?;
+        Ok(StaticInterfaceStorage {
+            index,
+            phantom: PhantomData,
+        })
+    }
+
+    fn insert_subscriber_queue(&self, frame: F, policy: DropPolicy) -> Result<Inserted, StorageError> {
+        P::pool().insert_subscriber_queue(frame, policy)
+    }
+
+    fn insert_interface_queue(&self, frame: F) -> Result<(), StorageError> {
+        P::pool().insert_interface_queue(frame)
+    }
+}
+
+impl<F: TransferFrame, P: StaticPool<F>> SubscriberStorageHandle<F> for StaticSubscriberStorage<F, P> {
+    fn pop(&self) -> Option<F> {
+        P::pool().subscriber_pop(self.index)
+    }
+
+    fn remove(&self, identifier: &TransferFrameID) -> Option<F> {
+        P::pool().subscriber_remove(self.index, identifier)
+    }
+
+    fn find_id<Q>(&self, mut predicate: Q) -> Option<FullTransferID>
+        where Q: FnMut(&F) -> bool {
+        P::pool().subscriber_find_id(self.index, &mut predicate)
+    }
+
+    fn retain<Q>(&self, mut predicate: Q)
+        where Q: FnMut(&F) -> bool {
+        P::pool().subscriber_retain(self.index, &mut predicate)
+    }
+}
+
+impl<F: TransferFrame, P: StaticPool<F>> Drop for StaticSubscriberStorage<F, P> {
+    fn drop(&mut self) {
+        P::pool().unsubscribe(self.index);
+    }
+}
+
+impl<F: TransferFrame, P: StaticPool<F>> InterfaceStorageHandle<F> for StaticInterfaceStorage<F, P> {
+    fn pop(&self) -> Option<F> {
+        P::pool().interface_pop(self.index)
+    }
+
+    fn max_priority(&self) -> Option<TransferFrameID> {
+        P::pool().interface_max_priority(self.index)
+    }
+
+    fn push(&self, frame: F) -> Result<(), StorageError> {
+        P::pool().interface_push(self.index, frame)
+    }
+
+    fn push_front(&self, frame: F) -> Result<(), StorageError> {
+        P::pool().interface_push_front(self.index, frame)
+    }
+
+    fn remove_transfer(&self, full_id: &FullTransferID) {
+        P::pool().interface_remove_transfer(self.index, full_id)
+    }
+}
+
+impl<F: TransferFrame, P: StaticPool<F>> Drop for StaticInterfaceStorage<F, P> {
+    fn drop(&mut self) {
+        P::pool().remove_interface(self.index);
+    }
+}
+
+
+#[cfg(all(test, feature="std"))]
+mod tests {
+
+    use storage::*;
+
+    use transfer::{
+        TransferFrame,
+        TransferFrameID,
+        TransferFrameIDFilter,
+    };
+
+    use node::*;
+    use time::Timestamp;
+
+    use tests::{
+        CanFrame,
+        TestInterface,
+        EchoRequest,
+        EchoResponse,
+    };
+
+    fn frame(id: u32, data: u8) -> CanFrame {
+        let mut frame = CanFrame::new(TransferFrameID::new(id));
+        frame.set_data_length(1);
+        frame.data_as_mut()[0] = data;
+        frame
+    }
+
+    #[test]
+    fn subscriber_queue() {
+        static_storage_pool!(Pool: CanFrame, subscribers = 2, subscriber_frames = 3, interfaces = 1, interface_frames = 1);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+
+        let all = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        let odd = storage.subscribe_to(TransferFrameIDFilter::new(1, 1)).unwrap();
+
+        assert_eq!(storage.insert_subscriber_queue(frame(1, 0), DropPolicy::DropNewest), Ok(Inserted::Queued));
+        assert_eq!(storage.insert_subscriber_queue(frame(2, 1), DropPolicy::DropNewest), Ok(Inserted::Queued));
+        assert_eq!(storage.insert_subscriber_queue(frame(1, 2), DropPolicy::DropNewest), Ok(Inserted::Queued));
+        assert_eq!(storage.insert_subscriber_queue(frame(3, 3), DropPolicy::DropNewest), Err(StorageError::OutOfSpace));
+
+        // Frames are removed in the order they were inserted
+        assert_eq!(all.remove(&TransferFrameID::new(1)).unwrap().data(), &[0]);
+        assert_eq!(all.remove(&TransferFrameID::new(1)).unwrap().data(), &[2]);
+        assert_eq!(all.find_id(|_| true).map(|x| x.frame_id), Some(TransferFrameID::new(2)));
+
+        odd.retain(|x| x.id() != TransferFrameID::new(1));
+        assert_eq!(odd.pop().unwrap().data(), &[3]);
+        assert!(odd.find_id(|_| true).is_none());
+
+        // Frames are popped highest priority first, in the order they were inserted if the priority is equal
+        storage.insert_subscriber_queue(frame(5, 4), DropPolicy::DropNewest).unwrap();
+        storage.insert_subscriber_queue(frame(3, 5), DropPolicy::DropNewest).unwrap();
+        assert_eq!(all.pop().unwrap().data(), &[1]);
+        assert_eq!(all.pop().unwrap().data(), &[5]);
+        assert_eq!(all.pop().unwrap().data(), &[4]);
+        assert!(all.pop().is_none());
+    }
+
+    #[test]
+    fn slots_are_reused() {
+        static_storage_pool!(Pool: CanFrame, subscribers = 1, subscriber_frames = 1, interfaces = 1, interface_frames = 1);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+
+        let subscriber = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        storage.insert_subscriber_queue(frame(1, 0), DropPolicy::DropNewest).unwrap();
+        drop(subscriber);
+
+        let subscriber = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        assert!(subscriber.find_id(|_| true).is_none());
+
+        let interface = storage.new_interface().unwrap();
+        interface.push(frame(1, 0)).unwrap();
+        drop(interface);
+        assert!(storage.new_interface().unwrap().pop().is_none());
+    }
+
+    #[test]
+    fn out_of_slots() {
+        static_storage_pool!(Pool: CanFrame, subscribers = 1, subscriber_frames = 1, interfaces = 1, interface_frames = 1);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+
+        let first = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        assert_eq!(storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).err(), Some(StorageError::OutOfSpace));
+        drop(first);
+        assert!(storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).is_ok());
+
+        let _interface = storage.new_interface().unwrap();
+        assert_eq!(storage.new_interface().err(), Some(StorageError::OutOfSpace));
+    }
+
+    #[test]
+    fn interface_priority() {
+        static_storage_pool!(Pool: CanFrame, subscribers = 1, subscriber_frames = 1, interfaces = 2, interface_frames = 3);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+        let first = storage.new_interface().unwrap();
+        let second = storage.new_interface().unwrap();
+
+        storage.insert_interface_queue(frame(5, 0)).unwrap();
+        storage.insert_interface_queue(frame(2, 1)).unwrap();
+        storage.insert_interface_queue(frame(5, 2)).unwrap();
+        assert_eq!(first.push(frame(1, 3)), Err(StorageError::OutOfSpace));
+
+        assert_eq!(first.max_priority(), Some(TransferFrameID::new(2)));
+        assert_eq!(first.pop().unwrap().data(), &[1]);
+        assert_eq!(first.pop().unwrap().data(), &[0]);
+        assert_eq!(first.pop().unwrap().data(), &[2]);
+        assert!(first.pop().is_none());
+
+        assert_eq!(second.pop().unwrap().data(), &[1]);
+    }
+
+    #[test]
+    fn push_to_single_interface() {
+        static_storage_pool!(Pool: CanFrame, subscribers = 1, subscriber_frames = 1, interfaces = 2, interface_frames = 2);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+        let first = storage.new_interface().unwrap();
+        let second = storage.new_interface().unwrap();
+
+        storage.insert_interface_queue(frame(5, 0)).unwrap();
+        first.push(frame(2, 1)).unwrap();
+
+        assert_eq!(first.pop().unwrap().data(), &[1]);
+        assert_eq!(first.pop().unwrap().data(), &[0]);
+        assert_eq!(second.pop().unwrap().data(), &[0]);
+        assert!(second.pop().is_none());
+    }
+
+    #[test]
+    fn push_front_keeps_order() {
+        static_storage_pool!(Pool: CanFrame, subscribers = 1, subscriber_frames = 1, interfaces = 1, interface_frames = 3);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+        let interface = storage.new_interface().unwrap();
+
+        interface.push(frame(5, 0)).unwrap();
+        interface.push(frame(5, 1)).unwrap();
+        let first = interface.pop().unwrap();
+        interface.push(frame(5, 2)).unwrap();
+        interface.push_front(first).unwrap();
+        assert_eq!(interface.push_front(frame(5, 3)), Err(StorageError::OutOfSpace));
+
+        assert_eq!(interface.pop().unwrap().data(), &[0]);
+        assert_eq!(interface.pop().unwrap().data(), &[1]);
+        assert_eq!(interface.pop().unwrap().data(), &[2]);
+        assert!(interface.pop().is_none());
+    }
+
+    #[test]
+    fn request_response() {
+        static_storage_pool!(ClientPool: CanFrame, subscribers = 4, subscriber_frames = 4, interfaces = 1, interface_frames = 4);
+        static_storage_pool!(ServerPool: CanFrame, subscribers = 4, subscriber_frames = 4, interfaces = 1, interface_frames = 4);
+
+        let client_interface = TestInterface::default();
+        let client: SimpleNode<TestInterface, &TestInterface, StaticStorage<CanFrame, ClientPool>> = SimpleNode::new(&client_interface, NodeConfig{id: Some(NodeID::new(10)), ..NodeConfig::default()});
+
+        let server_interface = TestInterface::default();
+        let server: SimpleNode<TestInterface, &TestInterface, StaticStorage<CanFrame, ServerPool>> = SimpleNode::new(&server_interface, NodeConfig{id: Some(NodeID::new(42)), ..NodeConfig::default()});
+        let service_server = server.serve::<EchoRequest>().unwrap();
+
+        let handle = client.request(NodeID::new(42), EchoRequest{value: 7}, Timestamp::from_micros(1000)).unwrap();
+        client.flush_transmissions();
+        client_interface.transfer_to(&server_interface);
+        server.flush_receptions();
+
+        let request = service_server.receive().unwrap().unwrap();
+        server.respond(request.token, EchoResponse{value: request.body.value + 1}).unwrap();
+        server.flush_transmissions();
+        server_interface.transfer_to(&client_interface);
+        client.flush_receptions();
+
+        assert_eq!(handle.receive(Timestamp::from_micros(0)), Some(Ok(EchoResponse{value: 8})));
+    }
+
+    #[test]
+    fn drop_policies() {
+        static_storage_pool!(Pool: CanFrame, subscribers = 3, subscriber_frames = 2, interfaces = 1, interface_frames = 1);
+        let storage: StaticStorage<CanFrame, Pool> = StaticStorage::new();
+        let queued = |subscriber: &StaticSubscriberStorage<CanFrame, Pool>| {
+            let mut data = Vec::new();
+            while let Some(id) = subscriber.find_id(|_| true) {
+                data.push(subscriber.remove(&id.frame_id).unwrap().data()[0]);
+            }
+            data
+        };
+
+        let subscriber = storage.subscribe_to(TransferFrameIDFilter::new(0, 0)).unwrap();
+        let insert = |frames: &[(u32, u8)], policy| -> Vec<Result<Inserted, StorageError>> {
+            frames.iter().map(|&(id, data)| storage.insert_subscriber_queue(frame(id, data), policy)).collect()
+        };
+        let queued_frame = Ok(Inserted::Queued);
+        let dropped_other = Ok(Inserted::DroppedOther);
+        let dropped_itself = Err(StorageError::OutOfSpace);
+
+        assert_eq!(insert(&[(5, 0), (2, 1), (7, 2)], DropPolicy::DropNewest), vec![queued_frame, queued_frame, dropped_itself]);
+        assert_eq!(queued(&subscriber), vec![0, 1]);
+
+        assert_eq!(insert(&[(5, 0), (2, 1), (7, 2)], DropPolicy::DropOldest), vec![queued_frame, queued_frame, dropped_other]);
+        assert_eq!(queued(&subscriber), vec![1, 2]);
+
+        // A frame with lower priority than all queued frames is dropped itself
+        assert_eq!(insert(&[(5, 0), (2, 1), (7, 2), (3, 3)], DropPolicy::DropLowestPriority), vec![queued_frame, queued_frame, dropped_itself, dropped_other]);
+        assert_eq!(queued(&subscriber), vec![1, 3]);
+    }
+}
@@ -0,0 +1,92 @@
+//! Everything related to time keeping
+//!
+//! Uavcan.rs doesn't assume anything about the clock of the platform it's running on.
+//! Instead the application is responsible for passing the current time to the library where it's needed.
+
+use lib::core::ops::{
+    Add,
+    Sub,
+};
+
+pub use lib::core::time::Duration;
+
+/// A monotonic timestamp with microsecond resolution.
+///
+/// The epoch is arbitrary (typically the boot of the system),
+/// the only requirement is that time never goes backwards.
+///
+/// # Examples
+/// ```
+/// use uavcan::time::Timestamp;
+/// use uavcan::time::Duration;
+///
+/// let t0 = Timestamp::from_micros(1_000);
+/// let t1 = t0 + Duration::from_millis(2);
+///
+/// assert_eq!(t1, Timestamp::from_micros(3_000));
+/// assert_eq!(t1.duration_since(t0), Duration::from_millis(2));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    /// Constructs a new `Timestamp` from microseconds since the epoch.
+    pub fn from_micros(micros: u64) -> Self {
+        Timestamp(micros)
+    }
+
+    /// Returns the number of microseconds since the epoch.
+    pub fn as_micros(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns the amount of time elapsed from `earlier` to `self`.
+    ///
+    /// If `earlier` is later than `self` a zero `Duration` is returned.
+    pub fn duration_since(&self, earlier: Timestamp) -> Duration {
+        Duration::from_micros(self.0.saturating_sub(earlier.0))
+    }
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, rhs: Duration) -> Timestamp {
+        Timestamp(self.0 + duration_as_micros(rhs))
+    }
+}
+
+impl Sub<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, rhs: Duration) -> Timestamp {
+        Timestamp(self.0.saturating_sub(duration_as_micros(rhs)))
+    }
+}
+
+/// The time a frame was received or transmitted by an interface.
+///
+/// The monotonic timestamp is always present, while the UTC timestamp is only present
+/// if the node knows the UTC time (e.g. from a GNSS receiver or a time sync master).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct FrameTimestamp {
+    /// Time according to the monotonic clock of the node.
+    pub monotonic: Timestamp,
+
+    /// Microseconds since the Unix epoch.
+    pub utc: Option<Timestamp>,
+}
+
+impl FrameTimestamp {
+    /// Constructs a new `FrameTimestamp` without UTC time.
+    pub fn from_monotonic(monotonic: Timestamp) -> Self {
+        FrameTimestamp {
+            monotonic,
+            utc: None,
+        }
+    }
+}
+
+fn duration_as_micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
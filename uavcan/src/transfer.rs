@@ -2,11 +2,25 @@
 //!
 //! The only transfer protocol that is currently supported by the uavcan protocol is CAN2.0B.
 
-use lib::core::convert::From;
+use lib::core::convert::{
+    From,
+    TryFrom,
+};
 use lib::core::cmp::Ordering;
 
 use embedded_types;
 
+use bit_field::BitField;
+
+use NodeID;
+
+use time::FrameTimestamp;
+
+use types::{
+    u2,
+    u14,
+};
+
 pub use embedded_types::io::Error as IOError;
 
 /// `TransferInterface` is an interface to a hardware unit which can communicate over a CAN like transfer protocol
@@ -29,6 +43,14 @@ pub trait TransferInterface {
     /// Receive a frame, removing to from the receive buffer.
     /// if there are no frames in the receive buffer this function will return `None`
     fn receive(&self) -> Option<Self::Frame>;
+
+    /// Returns the time the last frame with `id` was transmitted on the bus.
+    ///
+    /// Interfaces capable of timestamping transmissions should override this method, the default implementation returns `None`.
+    /// The timestamp must come from the same clocks as the timestamps of received frames.
+    fn transmission_timestamp(&self, _id: TransferFrameID) -> Option<FrameTimestamp> {
+        None
+    }
 }
 
 /// `TransferFrame` is a CAN like frame that can be sent over a network
@@ -68,6 +90,14 @@ pub trait TransferFrame {
     /// ## Panics
     /// `set_data_lengt(&mut self, length: usize)` should panic if `length > T::MAX_DATA_LENGTH`
     fn set_data_length(&mut self, length: usize);
+
+    /// Returns the time the frame was received by the interface.
+    ///
+    /// Interfaces capable of timestamping receptions should return frames carrying the timestamp,
+    /// the default implementation returns `None`.
+    fn timestamp(&self) -> Option<FrameTimestamp> {
+        None
+    }
     
     /// Returns the tail byte of the TransferFrame assuming the current length
     ///
@@ -177,6 +207,214 @@ impl TransferFrameIDFilter {
     }
 }
 
+/// A `TransferFrameID` decoded according to the Uavcan CAN ID layout
+///
+/// # Examples
+/// ```
+/// use std::convert::TryFrom;
+///
+/// use uavcan::NodeID;
+/// use uavcan::transfer::CanId;
+/// use uavcan::transfer::TransferFrameID;
+/// use uavcan::transfer::TransferPriority;
+///
+/// let can_id = CanId::Message{priority: TransferPriority::MEDIUM, type_id: 341, source: NodeID::new(42)};
+/// let id = TransferFrameID::from(can_id);
+/// assert_eq!(u32::from(id), (16 << 24) | (341 << 8) | 42);
+/// assert_eq!(CanId::try_from(id), Ok(can_id));
+///
+/// // Service transfers can't be sent by or addressed to anonymous nodes
+/// assert!(CanId::try_from(TransferFrameID::new((10 << 16) | (1 << 15) | (42 << 8) | (1 << 7))).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CanId {
+    /// A message broadcast by a node with a `NodeID`.
+    Message {
+        priority: TransferPriority,
+        type_id: u16,
+        source: NodeID,
+    },
+
+    /// A message broadcast by an anonymous node.
+    ///
+    /// Only the two least significant bits of the data type ID are carried,
+    /// the rest of the ID is used for a discriminator derived from the payload.
+    AnonymousMessage {
+        priority: TransferPriority,
+        type_id: u2,
+        discriminator: u14,
+    },
+
+    /// A service request sent from `source` to `destination`.
+    ServiceRequest {
+        priority: TransferPriority,
+        type_id: u8,
+        source: NodeID,
+        destination: NodeID,
+    },
+
+    /// A service response sent from `source` to `destination`.
+    ServiceResponse {
+        priority: TransferPriority,
+        type_id: u8,
+        source: NodeID,
+        destination: NodeID,
+    },
+}
+
+impl CanId {
+    /// Returns the priority of the transfer.
+    pub fn priority(&self) -> TransferPriority {
+        match *self {
+            CanId::Message{priority, ..} => priority,
+            CanId::AnonymousMessage{priority, ..} => priority,
+            CanId::ServiceRequest{priority, ..} => priority,
+            CanId::ServiceResponse{priority, ..} => priority,
+        }
+    }
+
+    /// Returns the data type ID of the transfer.
+    ///
+    /// For anonymous messages only the two least significant bits are known.
+    pub fn type_id(&self) -> u16 {
+        match *self {
+            CanId::Message{type_id, ..} => type_id,
+            CanId::AnonymousMessage{type_id, ..} => u16::from(u8::from(type_id)),
+            CanId::ServiceRequest{type_id, ..} => u16::from(type_id),
+            CanId::ServiceResponse{type_id, ..} => u16::from(type_id),
+        }
+    }
+
+    /// Returns the `NodeID` of the sender, or `None` if the sender is anonymous.
+    pub fn source(&self) -> Option<NodeID> {
+        match *self {
+            CanId::Message{source, ..} => Some(source),
+            CanId::AnonymousMessage{..} => None,
+            CanId::ServiceRequest{source, ..} => Some(source),
+            CanId::ServiceResponse{source, ..} => Some(source),
+        }
+    }
+
+    /// Returns the `NodeID` a service transfer is addressed to, or `None` for messages.
+    pub fn destination(&self) -> Option<NodeID> {
+        match *self {
+            CanId::Message{..} | CanId::AnonymousMessage{..} => None,
+            CanId::ServiceRequest{destination, ..} => Some(destination),
+            CanId::ServiceResponse{destination, ..} => Some(destination),
+        }
+    }
+
+    /// Returns a filter matching messages of data type `type_id` from any node with a `NodeID`.
+    ///
+    /// The filter also matches anonymous messages whose discriminator happens to complete `type_id`,
+    /// as the source bits can't be required to be non-zero. These must be discarded by the receiver.
+    pub fn message_filter(type_id: u16) -> TransferFrameIDFilter {
+        let mut id = 0;
+        id.set_bits(8..24, u32::from(type_id));
+        TransferFrameIDFilter::new(id, 0x00ff_ff80)
+    }
+
+    /// Returns a filter matching all anonymous messages carrying the two least significant bits of `type_id`.
+    pub fn anonymous_message_filter(type_id: u16) -> TransferFrameIDFilter {
+        let mut id = 0;
+        id.set_bits(8..10, u32::from(type_id).get_bits(0..2));
+        TransferFrameIDFilter::new(id, 0x0000_03ff)
+    }
+
+    /// Returns a filter matching service requests of data type `type_id` addressed to `destination` from any node.
+    ///
+    /// Anonymous nodes can't be addressed, for `destination = None` the filter only matches invalid requests addressed to node 0.
+    pub fn service_request_filter(type_id: u8, destination: Option<NodeID>) -> TransferFrameIDFilter {
+        let mut id = 0;
+        id.set_bit(7, true);
+        id.set_bits(8..15, destination.map_or(0, u32::from));
+        id.set_bit(15, true);
+        id.set_bits(16..24, u32::from(type_id));
+        TransferFrameIDFilter::new(id, 0x00ff_ff80)
+    }
+
+    /// Returns a filter matching service responses of data type `type_id` sent from `source` to `destination`.
+    pub fn service_response_filter(type_id: u8, source: NodeID, destination: NodeID) -> TransferFrameIDFilter {
+        let id = CanId::ServiceResponse{priority: TransferPriority::HIGHEST, type_id, source, destination};
+        TransferFrameIDFilter::new(u32::from(TransferFrameID::from(id)), 0x00ff_ffff)
+    }
+}
+
+impl From<CanId> for TransferFrameID {
+    fn from(id: CanId) -> Self {
+        let mut value = 0;
+        value.set_bits(24..29, u32::from(id.priority()));
+        match id {
+            CanId::Message{type_id, source, ..} => {
+                value.set_bits(0..7, u32::from(source));
+                value.set_bits(8..24, u32::from(type_id));
+            },
+            CanId::AnonymousMessage{type_id, discriminator, ..} => {
+                value.set_bits(8..10, u32::from(u8::from(type_id)));
+                value.set_bits(10..24, u32::from(u16::from(discriminator)));
+            },
+            CanId::ServiceRequest{type_id, source, destination, ..} => {
+                value.set_bits(0..7, u32::from(source));
+                value.set_bit(7, true);
+                value.set_bits(8..15, u32::from(destination));
+                value.set_bit(15, true);
+                value.set_bits(16..24, u32::from(type_id));
+            },
+            CanId::ServiceResponse{type_id, source, destination, ..} => {
+                value.set_bits(0..7, u32::from(source));
+                value.set_bit(7, true);
+                value.set_bits(8..15, u32::from(destination));
+                value.set_bits(16..24, u32::from(type_id));
+            },
+        }
+        TransferFrameID::new(value)
+    }
+}
+
+/// The error returned when a `TransferFrameID` is not a valid Uavcan CAN ID
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InvalidCanId(pub TransferFrameID);
+
+impl TryFrom<TransferFrameID> for CanId {
+    type Error = InvalidCanId;
+
+    /// Decodes `id`, failing for service transfers sent by or addressed to node 0.
+    fn try_from(id: TransferFrameID) -> Result<Self, Self::Error> {
+        let value = u32::from(id);
+        let priority = TransferPriority::new(value.get_bits(24..29) as u8);
+        let source = value.get_bits(0..7) as u8;
+
+        if !value.get_bit(7) {
+            if source == 0 {
+                Ok(CanId::AnonymousMessage {
+                    priority,
+                    type_id: u2::new(value.get_bits(8..10) as u8),
+                    discriminator: u14::new(value.get_bits(10..24) as u16),
+                })
+            } else {
+                Ok(CanId::Message {
+                    priority,
+                    type_id: value.get_bits(8..24) as u16,
+                    source: NodeID::new(source),
+                })
+            }
+        } else {
+            let destination = value.get_bits(8..15) as u8;
+            if source == 0 || destination == 0 {
+                return Err(InvalidCanId(id));
+            }
+
+            let type_id = value.get_bits(16..24) as u8;
+            let (source, destination) = (NodeID::new(source), NodeID::new(destination));
+            if value.get_bit(15) {
+                Ok(CanId::ServiceRequest{priority, type_id, source, destination})
+            } else {
+                Ok(CanId::ServiceResponse{priority, type_id, source, destination})
+            }
+        }
+    }
+}
+
 /// The 5-bit ID used to distinguish consecutive transfers
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub struct TransferID(u8);
@@ -189,6 +427,12 @@ impl TransferID {
         assert_eq!(value & !0x1f, 0);
         TransferID(value)
     }
+
+    /// Returns the `TransferID` following this one, wrapping around after 31.
+    pub fn next(self) -> Self {
+        let TransferID(value) = self;
+        TransferID((value + 1) & 0x1f)
+    }
     
     /// Deasserts bits based on the asserted bits of `mask`
     pub fn mask(self, mask: Self) -> Self {
@@ -205,6 +449,66 @@ impl From<TransferID> for u8 {
     }
 }
 
+/// The 5-bit priority of a transfer
+///
+/// Lower values mean higher priority, `TransferPriority::HIGHEST` is 0 while `TransferPriority::LOWEST` is 31.
+///
+/// # Examples
+/// ```
+/// use uavcan::transfer::TransferPriority;
+///
+/// assert_eq!(TransferPriority::new(16), TransferPriority::MEDIUM);
+/// assert_eq!(TransferPriority::default(), TransferPriority::MEDIUM);
+/// assert_eq!(u8::from(TransferPriority::LOWEST), 31);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct TransferPriority(u8);
+
+impl TransferPriority {
+    /// The highest priority a transfer can have.
+    pub const HIGHEST: TransferPriority = TransferPriority(0);
+    
+    /// A priority suitable for time critical transfers, like control loops.
+    pub const HIGH: TransferPriority = TransferPriority(8);
+    
+    /// The default priority.
+    pub const MEDIUM: TransferPriority = TransferPriority(16);
+
+    /// A priority suitable for non-critical transfers, like telemetry.
+    pub const LOW: TransferPriority = TransferPriority(24);
+    
+    /// The lowest priority a transfer can have.
+    pub const LOWEST: TransferPriority = TransferPriority(31);
+
+    /// Constructs a new `TransferPriority`
+    /// ## Panic
+    /// Panics if `value` is something not representable with 5-bits
+    pub fn new(value: u8) -> TransferPriority {
+        assert_eq!(value & !0x1f, 0);
+        TransferPriority(value)
+    }
+}
+
+impl Default for TransferPriority {
+    fn default() -> Self {
+        TransferPriority::MEDIUM
+    }
+}
+
+impl From<TransferPriority> for u8 {
+    fn from(priority: TransferPriority) -> u8 {
+        let TransferPriority(value) = priority;
+        value
+    }
+}
+
+impl From<TransferPriority> for u32 {
+    fn from(priority: TransferPriority) -> u32 {
+        let TransferPriority(value) = priority;
+        u32::from(value)
+    }
+}
+
 /// A Wrapper that can be used for sorting after priority
 ///
 /// PartialEq, Eq, PartialOrd and Ord is implemented but only cares about priority.
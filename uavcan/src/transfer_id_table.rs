@@ -0,0 +1,141 @@
+use transfer::{
+    TransferFrameID,
+    TransferID,
+};
+
+/// The number of transfer ID counters a `TransferIDTable` can keep track of.
+pub(crate) const TRANSFER_ID_TABLE_CAPACITY: usize = 32;
+
+/// Only the priority bits are not part of what distinguishes one kind of transfer from another.
+const KEY_MASK: u32 = 0x00ff_ffff;
+
+/// Keeps track of the next `TransferID` for every kind of outgoing transfer.
+///
+/// A kind of transfer is identified by everything in the `TransferFrameID` except the priority,
+/// which makes it unique for every (data type, destination) tuple as required by the Uavcan specification.
+///
+/// The table has a capacity of `TRANSFER_ID_TABLE_CAPACITY` counters. If more kinds of transfers are used,
+/// the counter that was created first is replaced, causing the transfer ID of that kind to restart from 0.
+#[derive(Debug)]
+pub(crate) struct TransferIDTable {
+    entries: [Option<(u32, TransferID)>; TRANSFER_ID_TABLE_CAPACITY],
+    next_replacement: usize,
+}
+
+impl TransferIDTable {
+    pub fn new() -> Self {
+        TransferIDTable {
+            entries: [None; TRANSFER_ID_TABLE_CAPACITY],
+            next_replacement: 0,
+        }
+    }
+
+    /// Returns the `TransferID` to use for the next transfer with `id` and increments the counter.
+    pub fn next_transfer_id(&mut self, id: TransferFrameID) -> TransferID {
+        let key = u32::from(id) & KEY_MASK;
+
+        for entry in self.entries.iter_mut() {
+            match *entry {
+                Some((entry_key, ref mut transfer_id)) if entry_key == key => {
+                    let current = *transfer_id;
+                    *transfer_id = current.next();
+                    return current;
+                },
+                _ => (),
+            }
+        }
+
+        let index = if let Some(index) = self.entries.iter().position(|x| x.is_none()) {
+            index
+        } else {
+            let index = self.next_replacement;
+            self.next_replacement = (self.next_replacement + 1) % TRANSFER_ID_TABLE_CAPACITY;
+            index
+        };
+
+        let current = TransferID::new(0);
+        self.entries[index] = Some((key, current.next()));
+        current
+    }
+
+    /// Gives back a `TransferID` returned by `next_transfer_id` for a transfer that was never transmitted.
+    ///
+    /// The counter is only rewound if no other transfer ID has been handed out for `id` since.
+    pub fn release_transfer_id(&mut self, id: TransferFrameID, transfer_id: TransferID) {
+        let key = u32::from(id) & KEY_MASK;
+
+        for entry in self.entries.iter_mut() {
+            match *entry {
+                Some((entry_key, ref mut next)) if entry_key == key => {
+                    if *next == transfer_id.next() {
+                        *next = transfer_id;
+                    }
+                    return;
+                },
+                _ => (),
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use transfer_id_table::*;
+
+    #[test]
+    fn wraps_modulo_32() {
+        let mut table = TransferIDTable::new();
+        let id = TransferFrameID::new(0x0015_5520);
+
+        for i in 0..32 {
+            assert_eq!(table.next_transfer_id(id), TransferID::new(i));
+        }
+        assert_eq!(table.next_transfer_id(id), TransferID::new(0));
+    }
+
+    #[test]
+    fn independent_counters() {
+        let mut table = TransferIDTable::new();
+        let message = TransferFrameID::new(0x0015_5520);
+        let request = TransferFrameID::new(0x00c8_aa8a);
+
+        assert_eq!(table.next_transfer_id(message), TransferID::new(0));
+        assert_eq!(table.next_transfer_id(message), TransferID::new(1));
+        assert_eq!(table.next_transfer_id(request), TransferID::new(0));
+
+        // Priority is not part of the key
+        assert_eq!(table.next_transfer_id(TransferFrameID::new(0x1f15_5520)), TransferID::new(2));
+    }
+
+    #[test]
+    fn release_transfer_id() {
+        let mut table = TransferIDTable::new();
+        let id = TransferFrameID::new(0x0015_5520);
+
+        let first = table.next_transfer_id(id);
+        table.release_transfer_id(id, first);
+        assert_eq!(table.next_transfer_id(id), first);
+
+        // Only the last handed out transfer ID can be given back
+        let second = table.next_transfer_id(id);
+        table.next_transfer_id(id);
+        table.release_transfer_id(id, second);
+        assert_eq!(table.next_transfer_id(id), TransferID::new(3));
+    }
+
+    #[test]
+    fn replaces_oldest_when_full() {
+        let mut table = TransferIDTable::new();
+
+        for i in 0..TRANSFER_ID_TABLE_CAPACITY as u32 {
+            table.next_transfer_id(TransferFrameID::new(i << 8));
+            table.next_transfer_id(TransferFrameID::new(i << 8));
+        }
+
+        assert_eq!(table.next_transfer_id(TransferFrameID::new(0x00ff_ff00)), TransferID::new(0));
+        assert_eq!(table.next_transfer_id(TransferFrameID::new(0)), TransferID::new(0));
+        assert_eq!(table.next_transfer_id(TransferFrameID::new(2 << 8)), TransferID::new(2));
+    }
+}
@@ -247,7 +247,9 @@ macro_rules! impl_array{
                 if *flattened_field == 0 {
                     
                     let buffer_len = buffer.bit_length();
-                    if buffer_len + *bit < Self::LENGTH_BITS {
+                    if buffer_len == 0 {
+                        return DeserializationResult::BufferInsufficient
+                    } else if buffer_len + *bit < Self::LENGTH_BITS {
                         self.deserialized_length.set_bits(*bit as u8..(*bit+buffer_len) as u8, buffer.pop_bits(buffer_len) as usize);
                         *bit += buffer_len;
                         return DeserializationResult::BufferInsufficient
@@ -257,6 +259,14 @@ macro_rules! impl_array{
                         *bit = 0;
                     }
                 }
+
+                // an empty array is finished as soon as the length is known
+                if !tail_array_optimization && self.deserialized_length == 0 {
+                    *flattened_field = Self::FLATTENED_FIELDS_NUMBER;
+                    self.current_length = 0;
+                    *bit = 0;
+                    return DeserializationResult::Finished;
+                }
                 
                 while *flattened_field < Self::FLATTENED_FIELDS_NUMBER {
                     let element = (*flattened_field - 1) / T::FLATTENED_FIELDS_NUMBER;